@@ -0,0 +1,751 @@
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anchor_lang::solana_program::{instruction::Instruction, system_program, sysvar};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{get_associated_token_address, ID as ASSOCIATED_TOKEN_PROGRAM_ID};
+use anchor_spl::token::ID as TOKEN_PROGRAM_ID;
+use psyduk_raffle::{accounts, instruction, psyduk_mint, AssetPrize, BonusTier, FeeMode, Schedule, ID, MAX_BONUS_TIERS};
+
+use crate::pda::{
+    asset_prize_address, claim_address, deny_list_address, draw_progress_address, draw_record_address, price_config_address, program_data_address, promotion_address, raffle_address, receipt_address, reserve_address, stake_pool_address,
+    prize_vault_address, stake_position_address, stake_vault_address, subscription_address, vault_address, withdrawal_address,
+};
+use crate::treasury_wallet;
+
+/// Creates the raffle on `schedule` and funds its vault, paid for by `admin`.
+pub fn initialize(admin: &Pubkey, schedule: Schedule) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::Initialize {
+            raffle: raffle_address(),
+            vault: vault_address(),
+            admin: *admin,
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: instruction::Initialize { schedule }.data(),
+    }
+}
+
+/// Buys as many tickets as `amount` lamports cover.
+pub fn buy_ticket(buyer: &Pubkey, amount: u64) -> Instruction {
+    buy_ticket_for(buyer, amount, None, None)
+}
+
+/// Buys tickets paid for by `buyer` and credited to `beneficiary`, or to the buyer when `None`.
+/// Passing the buyer's PSYDUK token account earns holder bonus tickets.
+pub fn buy_ticket_for(
+    buyer: &Pubkey,
+    amount: u64,
+    beneficiary: Option<Pubkey>,
+    holder_token_account: Option<Pubkey>,
+) -> Instruction {
+    buy(buyer, amount, beneficiary, holder_token_account, None)
+}
+
+/// Buys tickets like `buy_ticket_for` while they are priced in USD, passing the
+/// configured `price_feed` the program converts the price with.
+pub fn buy_ticket_in_usd(
+    buyer: &Pubkey,
+    amount: u64,
+    beneficiary: Option<Pubkey>,
+    holder_token_account: Option<Pubkey>,
+    price_feed: &Pubkey,
+) -> Instruction {
+    buy(buyer, amount, beneficiary, holder_token_account, Some(*price_feed))
+}
+
+fn buy(
+    buyer: &Pubkey,
+    amount: u64,
+    beneficiary: Option<Pubkey>,
+    holder_token_account: Option<Pubkey>,
+    price_feed: Option<Pubkey>,
+) -> Instruction {
+    // Gifts can't be cancelled, so only tickets the buyer keeps need a receipt
+    let keeps_tickets = beneficiary.is_none_or(|owner| owner == *buyer);
+    Instruction {
+        program_id: ID,
+        accounts: accounts::BuyTicket {
+            buyer: *buyer,
+            raffle: raffle_address(),
+            vault: vault_address(),
+            treasury: treasury_wallet(),
+            system_program: system_program::id(),
+            holder_token_account,
+            asset_prize: None,
+            creator: None,
+            receipt: keeps_tickets.then(|| receipt_address(buyer)),
+            deny_list: deny_list_address(),
+            price_config: price_config_address(),
+            price_feed,
+        }
+        .to_account_metas(None),
+        data: instruction::BuyTicket { amount, beneficiary }.data(),
+    }
+}
+
+/// Buys tickets in an asset prize round, paying `creator` their share.
+pub fn buy_asset_ticket(buyer: &Pubkey, amount: u64, creator: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::BuyTicket {
+            buyer: *buyer,
+            raffle: raffle_address(),
+            vault: vault_address(),
+            treasury: treasury_wallet(),
+            system_program: system_program::id(),
+            holder_token_account: None,
+            asset_prize: Some(asset_prize_address()),
+            creator: Some(*creator),
+            receipt: None,
+            deny_list: deny_list_address(),
+            price_config: price_config_address(),
+            price_feed: None,
+        }
+        .to_account_metas(None),
+        data: instruction::BuyTicket { amount, beneficiary: None }.data(),
+    }
+}
+
+/// Cancels `count` of the tickets `buyer` bought for itself this round, refunding
+/// them less the cancellation penalty.
+pub fn cancel_tickets(buyer: &Pubkey, count: u64) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::CancelTickets {
+            buyer: *buyer,
+            raffle: raffle_address(),
+            vault: vault_address(),
+            receipt: receipt_address(buyer),
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: instruction::CancelTickets { count }.data(),
+    }
+}
+
+/// Fixes the slot whose successor's hash draws the round, once sales have closed.
+pub fn lock_round() -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::LockRound {
+            raffle: raffle_address(),
+        }
+        .to_account_metas(None),
+        data: instruction::LockRound {}.data(),
+    }
+}
+
+/// Draws the round ending at `round_end_time`, paying the jackpot to `winner`, who
+/// must own the drawn ticket after any redraws past denied wallets. `payer` funds the round's draw record.
+pub fn select_winner(payer: &Pubkey, round_end_time: i64, winner: &Pubkey) -> Instruction {
+    draw(payer, round_end_time, winner, None, instruction::SelectWinner {}.data())
+}
+
+/// Draws an asset prize round, sending `asset_prize` to the winner's associated
+/// token account, which `payer` creates if needed.
+pub fn select_asset_winner(payer: &Pubkey, round_end_time: i64, winner: &Pubkey, asset_prize: &AssetPrize) -> Instruction {
+    draw(payer, round_end_time, winner, Some(asset_prize), instruction::SelectWinner {}.data())
+}
+
+/// Starts a draw spread over several transactions, fixing its entropy. `payer`
+/// funds the progress account the first time one is needed.
+pub fn begin_draw(payer: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::BeginDraw {
+            payer: *payer,
+            raffle: raffle_address(),
+            draw_progress: draw_progress_address(),
+            slot_hashes: sysvar::slot_hashes::id(),
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: instruction::BeginDraw {}.data(),
+    }
+}
+
+/// Hashes up to `max_tickets` more tickets of the ledger into the begun draw.
+pub fn advance_draw(max_tickets: u64) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::AdvanceDraw {
+            raffle: raffle_address(),
+            draw_progress: draw_progress_address(),
+        }
+        .to_account_metas(None),
+        data: instruction::AdvanceDraw { max_tickets }.data(),
+    }
+}
+
+/// Pays out a fully hashed batched draw to `winner`, like `select_winner`.
+/// `payer` funds the draw record and receives the progress account's rent.
+pub fn finalize_draw(payer: &Pubkey, round_end_time: i64, winner: &Pubkey) -> Instruction {
+    draw(payer, round_end_time, winner, None, instruction::FinalizeDraw {}.data())
+}
+
+/// Pays out a fully hashed batched draw of an asset prize round, like `select_asset_winner`.
+pub fn finalize_asset_draw(payer: &Pubkey, round_end_time: i64, winner: &Pubkey, asset_prize: &AssetPrize) -> Instruction {
+    draw(payer, round_end_time, winner, Some(asset_prize), instruction::FinalizeDraw {}.data())
+}
+
+fn draw(payer: &Pubkey, round_end_time: i64, winner: &Pubkey, asset_prize: Option<&AssetPrize>, data: Vec<u8>) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::SelectWinner {
+            payer: *payer,
+            raffle: raffle_address(),
+            vault: vault_address(),
+            reserve: reserve_address(),
+            winner: *winner,
+            treasury: treasury_wallet(),
+            draw_record: draw_record_address(round_end_time),
+            slot_hashes: sysvar::slot_hashes::id(),
+            draw_progress: draw_progress_address(),
+            deny_list: deny_list_address(),
+            system_program: system_program::id(),
+            asset_prize: asset_prize.map(|_| asset_prize_address()),
+            prize_vault: asset_prize.map(|_| prize_vault_address()),
+            prize_mint: asset_prize.map(|prize| prize.mint),
+            winner_token_account: asset_prize.map(|prize| get_associated_token_address(winner, &prize.mint)),
+            creator: asset_prize.map(|prize| prize.creator),
+            token_program: asset_prize.map(|_| TOKEN_PROGRAM_ID),
+            associated_token_program: asset_prize.map(|_| ASSOCIATED_TOKEN_PROGRAM_ID),
+        }
+        .to_account_metas(None),
+        data,
+    }
+}
+
+/// Checks that the vault covers the jackpot, unpaid fees and rent.
+pub fn reconcile() -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::Reconcile {
+            raffle: raffle_address(),
+            vault: vault_address(),
+        }
+        .to_account_metas(None),
+        data: instruction::Reconcile {}.data(),
+    }
+}
+
+/// Queries the current round's `RoundSummary`, returned as return data.
+pub fn get_round_summary() -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::ViewRound {
+            raffle: raffle_address(),
+        }
+        .to_account_metas(None),
+        data: instruction::GetRoundSummary {}.data(),
+    }
+}
+
+/// Queries the number of tickets `wallet` holds this round, returned as a `u64`.
+pub fn get_tickets_of(wallet: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::ViewRound {
+            raffle: raffle_address(),
+        }
+        .to_account_metas(None),
+        data: instruction::GetTicketsOf { wallet: *wallet }.data(),
+    }
+}
+
+/// Queries the `WinProbability` of `wallet` this round, returned as return data.
+pub fn get_win_probability(wallet: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::ViewRound {
+            raffle: raffle_address(),
+        }
+        .to_account_metas(None),
+        data: instruction::GetWinProbability { wallet: *wallet }.data(),
+    }
+}
+
+/// Escrows payment for `tickets_per_round` tickets in each of the next `rounds` rounds.
+pub fn subscribe(subscriber: &Pubkey, tickets_per_round: u64, rounds: u64) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::Subscribe {
+            subscriber: *subscriber,
+            subscription: subscription_address(subscriber),
+            system_program: system_program::id(),
+            price_config: price_config_address(),
+        }
+        .to_account_metas(None),
+        data: instruction::Subscribe { tickets_per_round, rounds }.data(),
+    }
+}
+
+/// Enters the subscriptions of `subscribers` into the open round; `payer` covers any ledger growth.
+pub fn apply_subscriptions(payer: &Pubkey, subscribers: &[Pubkey]) -> Instruction {
+    let mut accounts = accounts::ApplySubscriptions {
+        payer: *payer,
+        raffle: raffle_address(),
+        vault: vault_address(),
+        treasury: treasury_wallet(),
+        system_program: system_program::id(),
+    }
+    .to_account_metas(None);
+    accounts.extend(
+        subscribers
+            .iter()
+            .map(|subscriber| AccountMeta::new(subscription_address(subscriber), false)),
+    );
+
+    Instruction {
+        program_id: ID,
+        accounts,
+        data: instruction::ApplySubscriptions {}.data(),
+    }
+}
+
+/// Closes the subscription of `subscriber`, refunding the unused rounds.
+pub fn unsubscribe(subscriber: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::Unsubscribe {
+            subscriber: *subscriber,
+            subscription: subscription_address(subscriber),
+        }
+        .to_account_metas(None),
+        data: instruction::Unsubscribe {}.data(),
+    }
+}
+
+/// Publishes the Merkle root of free ticket grants for the round ending at `round_end_time`.
+pub fn publish_free_tickets(admin: &Pubkey, round_end_time: i64, merkle_root: [u8; 32]) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::PublishFreeTickets {
+            raffle: raffle_address(),
+            promotion: promotion_address(round_end_time),
+            admin: *admin,
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: instruction::PublishFreeTickets { round_end_time, merkle_root }.data(),
+    }
+}
+
+/// Claims the `ticket_count` free tickets granted to `claimant` for the round ending at `round_end_time`.
+pub fn claim_free_tickets(
+    claimant: &Pubkey,
+    round_end_time: i64,
+    ticket_count: u64,
+    proof: Vec<[u8; 32]>,
+) -> Instruction {
+    let promotion = promotion_address(round_end_time);
+    Instruction {
+        program_id: ID,
+        accounts: accounts::ClaimFreeTickets {
+            claimant: *claimant,
+            raffle: raffle_address(),
+            promotion,
+            claim: claim_address(&promotion, claimant),
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: instruction::ClaimFreeTickets { ticket_count, proof }.data(),
+    }
+}
+
+/// Opens PSYDUK staking; `admin` must be the raffle admin. The staking builders
+/// assume PSYDUK lives under the original SPL token program.
+pub fn initialize_staking(admin: &Pubkey, tokens_per_ticket: u64, max_tickets_per_stake: u64) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::InitializeStaking {
+            raffle: raffle_address(),
+            stake_pool: stake_pool_address(),
+            stake_vault: stake_vault_address(),
+            mint: psyduk_mint(),
+            admin: *admin,
+            token_program: TOKEN_PROGRAM_ID,
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: instruction::InitializeStaking { tokens_per_ticket, max_tickets_per_stake }.data(),
+    }
+}
+
+/// Stakes `amount` PSYDUK from `owner_token_account`, locked for at least `lock_duration` seconds.
+pub fn stake(owner: &Pubkey, owner_token_account: &Pubkey, amount: u64, lock_duration: i64) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::Stake {
+            owner: *owner,
+            stake_pool: stake_pool_address(),
+            stake_position: stake_position_address(owner),
+            stake_vault: stake_vault_address(),
+            owner_token_account: *owner_token_account,
+            mint: psyduk_mint(),
+            token_program: TOKEN_PROGRAM_ID,
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: instruction::Stake { amount, lock_duration }.data(),
+    }
+}
+
+/// Starts the unstake cooldown of `owner`'s position.
+pub fn request_unstake(owner: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::RequestUnstake {
+            owner: *owner,
+            stake_position: stake_position_address(owner),
+        }
+        .to_account_metas(None),
+        data: instruction::RequestUnstake {}.data(),
+    }
+}
+
+/// Withdraws `owner`'s stake to `owner_token_account` after the cooldown.
+pub fn unstake(owner: &Pubkey, owner_token_account: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::Unstake {
+            owner: *owner,
+            stake_pool: stake_pool_address(),
+            stake_position: stake_position_address(owner),
+            stake_vault: stake_vault_address(),
+            owner_token_account: *owner_token_account,
+            mint: psyduk_mint(),
+            token_program: TOKEN_PROGRAM_ID,
+        }
+        .to_account_metas(None),
+        data: instruction::Unstake {}.data(),
+    }
+}
+
+/// Enters the tickets earned by the stakes of `owners` into the open round; `payer` covers any ledger growth.
+pub fn allocate_stake_tickets(payer: &Pubkey, owners: &[Pubkey]) -> Instruction {
+    let mut accounts = accounts::AllocateStakeTickets {
+        payer: *payer,
+        raffle: raffle_address(),
+        stake_pool: stake_pool_address(),
+        system_program: system_program::id(),
+    }
+    .to_account_metas(None);
+    accounts.extend(
+        owners
+            .iter()
+            .map(|owner| AccountMeta::new(stake_position_address(owner), false)),
+    );
+
+    Instruction {
+        program_id: ID,
+        accounts,
+        data: instruction::AllocateStakeTickets {}.data(),
+    }
+}
+
+/// Makes `prize_amount` of `prize_mint` the prize of the current round, approved by
+/// `admin` and escrowed by `creator`, who receives the ticket revenue.
+pub fn create_asset_raffle(admin: &Pubkey, creator: &Pubkey, prize_mint: &Pubkey, prize_amount: u64) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::CreateAssetRaffle {
+            raffle: raffle_address(),
+            admin: *admin,
+            creator: *creator,
+            asset_prize: asset_prize_address(),
+            prize_vault: prize_vault_address(),
+            prize_mint: *prize_mint,
+            token_program: TOKEN_PROGRAM_ID,
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: instruction::CreateAssetRaffle { prize_amount }.data(),
+    }
+}
+
+/// Escrows the asset prize from `creator_token_account`, opening sales.
+pub fn deposit_prize(creator: &Pubkey, creator_token_account: &Pubkey, prize_mint: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::DepositPrize {
+            creator: *creator,
+            asset_prize: asset_prize_address(),
+            prize_vault: prize_vault_address(),
+            creator_token_account: *creator_token_account,
+            prize_mint: *prize_mint,
+            token_program: TOKEN_PROGRAM_ID,
+        }
+        .to_account_metas(None),
+        data: instruction::DepositPrize {}.data(),
+    }
+}
+
+/// Returns `asset_prize` to its creator's `creator_token_account` once nobody can
+/// win it; `authority` must be the creator or the raffle admin.
+pub fn reclaim_prize(authority: &Pubkey, creator_token_account: &Pubkey, asset_prize: &AssetPrize) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::ReclaimPrize {
+            raffle: raffle_address(),
+            authority: *authority,
+            asset_prize: asset_prize_address(),
+            prize_vault: prize_vault_address(),
+            creator_token_account: *creator_token_account,
+            creator: asset_prize.creator,
+            prize_mint: asset_prize.mint,
+            token_program: TOKEN_PROGRAM_ID,
+        }
+        .to_account_metas(None),
+        data: instruction::ReclaimPrize {}.data(),
+    }
+}
+
+/// Sets the sales cutoff and the minimum slots between the last entry and the draw;
+/// `admin` must be the raffle admin.
+pub fn set_draw_guards(admin: &Pubkey, sales_cutoff: i64, min_draw_slots: u64) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::SetDrawGuards {
+            raffle: raffle_address(),
+            admin: *admin,
+        }
+        .to_account_metas(None),
+        data: instruction::SetDrawGuards { sales_cutoff, min_draw_slots }.data(),
+    }
+}
+
+/// Sets whether the house fee is taken from entries or from the prize; `admin`
+/// must be the raffle admin and the round must have no tickets.
+pub fn set_fee_mode(admin: &Pubkey, fee_mode: FeeMode) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::SetFeeMode {
+            raffle: raffle_address(),
+            admin: *admin,
+        }
+        .to_account_metas(None),
+        data: instruction::SetFeeMode { fee_mode }.data(),
+    }
+}
+
+/// Chooses whether fees are held in the vault for timelocked withdrawal; `admin`
+/// must be the raffle admin.
+pub fn set_hold_fees(admin: &Pubkey, hold_fees: bool) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::SetHoldFees {
+            raffle: raffle_address(),
+            admin: *admin,
+        }
+        .to_account_metas(None),
+        data: instruction::SetHoldFees { hold_fees }.data(),
+    }
+}
+
+/// Queues a withdrawal of `amount` lamports of held fees to `destination`; `admin`
+/// must be the raffle admin and pays for the withdrawal account.
+pub fn queue_withdrawal(admin: &Pubkey, amount: u64, destination: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::QueueWithdrawal {
+            raffle: raffle_address(),
+            admin: *admin,
+            withdrawal: withdrawal_address(),
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: instruction::QueueWithdrawal { amount, destination: *destination }.data(),
+    }
+}
+
+/// Pays out the queued withdrawal to `destination` once unlocked; `admin` must be
+/// the raffle admin.
+pub fn execute_withdrawal(admin: &Pubkey, destination: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::ExecuteWithdrawal {
+            raffle: raffle_address(),
+            admin: *admin,
+            vault: vault_address(),
+            withdrawal: withdrawal_address(),
+            destination: *destination,
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: instruction::ExecuteWithdrawal {}.data(),
+    }
+}
+
+/// Drops the queued withdrawal; `admin` must be the raffle admin.
+pub fn cancel_withdrawal(admin: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::CancelWithdrawal {
+            raffle: raffle_address(),
+            admin: *admin,
+            withdrawal: withdrawal_address(),
+        }
+        .to_account_metas(None),
+        data: instruction::CancelWithdrawal {}.data(),
+    }
+}
+
+/// Adds `amount` lamports to the reserve backing the minimum prize, paid by `funder`.
+pub fn fund_reserve(funder: &Pubkey, amount: u64) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::FundReserve {
+            funder: *funder,
+            reserve: reserve_address(),
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: instruction::FundReserve { amount }.data(),
+    }
+}
+
+/// Prices tickets at `ticket_price_cents` USD cents through `price_feed`, or at the
+/// fixed lamport price when zero; `admin` must be the raffle admin and pays for
+/// the config the first time.
+pub fn set_usd_pricing(
+    admin: &Pubkey,
+    ticket_price_cents: u64,
+    price_feed: &Pubkey,
+    max_price_age: i64,
+    max_confidence_bps: u16,
+) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::SetUsdPricing {
+            raffle: raffle_address(),
+            admin: *admin,
+            price_config: price_config_address(),
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: instruction::SetUsdPricing {
+            ticket_price_cents,
+            price_feed: *price_feed,
+            max_price_age,
+            max_confidence_bps,
+        }
+        .data(),
+    }
+}
+
+/// Sets the guaranteed minimum prize in lamports; `admin` must be the raffle admin.
+pub fn set_min_prize(admin: &Pubkey, min_prize: u64) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::SetMinPrize {
+            raffle: raffle_address(),
+            admin: *admin,
+        }
+        .to_account_metas(None),
+        data: instruction::SetMinPrize { min_prize }.data(),
+    }
+}
+
+/// Sets the percentage of a cancelled ticket's refund kept in the jackpot; `admin`
+/// must be the raffle admin.
+pub fn set_cancel_penalty(admin: &Pubkey, penalty_percent: u8) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::SetCancelPenalty {
+            raffle: raffle_address(),
+            admin: *admin,
+        }
+        .to_account_metas(None),
+        data: instruction::SetCancelPenalty { penalty_percent }.data(),
+    }
+}
+
+/// Replaces the holder bonus tier table; `admin` must be the raffle admin.
+pub fn set_bonus_tiers(admin: &Pubkey, tiers: [BonusTier; MAX_BONUS_TIERS]) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::SetBonusTiers {
+            raffle: raffle_address(),
+            admin: *admin,
+        }
+        .to_account_metas(None),
+        data: instruction::SetBonusTiers { tiers }.data(),
+    }
+}
+
+/// Replaces the round schedule; `admin` must be the raffle admin.
+pub fn set_schedule(admin: &Pubkey, schedule: Schedule) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::SetSchedule {
+            raffle: raffle_address(),
+            admin: *admin,
+        }
+        .to_account_metas(None),
+        data: instruction::SetSchedule { schedule }.data(),
+    }
+}
+
+/// Bars `wallet` from buying tickets and from winning; `admin` must be the raffle
+/// admin and pays for the deny list the first time.
+pub fn deny_wallet(admin: &Pubkey, wallet: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::DenyWallet {
+            raffle: raffle_address(),
+            admin: *admin,
+            deny_list: deny_list_address(),
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: instruction::DenyWallet { wallet: *wallet }.data(),
+    }
+}
+
+/// Takes `wallet` off the deny list; `admin` must be the raffle admin.
+pub fn allow_wallet(admin: &Pubkey, wallet: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::AllowWallet {
+            raffle: raffle_address(),
+            admin: *admin,
+            deny_list: deny_list_address(),
+        }
+        .to_account_metas(None),
+        data: instruction::AllowWallet { wallet: *wallet }.data(),
+    }
+}
+
+/// Cancels a round nobody has entered, pausing sales until the raffle is
+/// rescheduled; `admin` must be the raffle admin.
+pub fn cancel_round(admin: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::CancelRound {
+            raffle: raffle_address(),
+            admin: *admin,
+        }
+        .to_account_metas(None),
+        data: instruction::CancelRound {}.data(),
+    }
+}
+
+/// Moves the raffle account to the current layout, funding the vault for a legacy
+/// jackpot; `authority` must be the upgrade authority.
+pub fn migrate(authority: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts::Migrate {
+            raffle: raffle_address(),
+            vault: vault_address(),
+            authority: *authority,
+            program: ID,
+            program_data: program_data_address(),
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: instruction::Migrate {}.data(),
+    }
+}
@@ -0,0 +1,28 @@
+//! Client-side helpers for the Psyduk raffle program.
+//!
+//! Everything here is built on the program's own instruction, account and
+//! constant definitions (compiled with the `cpi` feature), so off-chain code
+//! never has to hand-roll discriminators or byte offsets.
+
+pub mod instructions;
+pub mod merkle;
+pub mod pda;
+pub mod state;
+
+pub use psyduk_raffle::{
+    bonus_tickets, draw_index, extend_ledger_hash, ledger_hash, slot_hash_after, split_payment, AssetPrize, BonusTier, DenyList, DrawProgress, PriceConfig, PriceFeed, DrawRecord,
+    DrawTranscript, FeeMode, FreeTicketsClaimed, Raffle, RaffleError, RaffleV0, Schedule, ScheduleKind, StakePool, StakePosition, StakeTicketsAllocated, Subscription,
+    RoundStatus, RoundSummary, TicketReceipt, TicketsCancelled, TicketsPurchased, WinProbability, ID as PROGRAM_ID, MAX_BONUS_TIERS, MAX_STAKE_LOCK, MAX_TICKETS, NO_ROUND,
+    MAX_CANCEL_PENALTY_PERCENT, MAX_CONFIDENCE_BPS, MAX_DENIED_WALLETS, MAX_REDRAWS, PSYDUK_MINT, RAFFLE_DURATION, RAFFLE_VERSION, TICKET_PRICE, TREASURY_FEE_PERCENT,
+    UNSTAKE_COOLDOWN, WITHDRAWAL_DELAY, Withdrawal, WithdrawalCancelled, WithdrawalExecuted, WithdrawalQueued,
+};
+
+pub use anchor_lang::Discriminator;
+
+use anchor_lang::prelude::Pubkey;
+use std::str::FromStr;
+
+/// Wallet receiving the treasury share of every purchase.
+pub fn treasury_wallet() -> Pubkey {
+    Pubkey::from_str(psyduk_raffle::TREASURY_WALLET).unwrap()
+}
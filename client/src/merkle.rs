@@ -0,0 +1,49 @@
+use anchor_lang::prelude::Pubkey;
+use psyduk_raffle::{free_ticket_leaf, merkle_parent};
+
+/// Merkle tree over free ticket grants, built the way `claim_free_tickets` verifies it.
+/// A node without a sibling is carried up to the next layer unchanged.
+pub struct FreeTicketTree {
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+impl FreeTicketTree {
+    /// Builds the tree of (wallet, ticket_count) grants, in the order given.
+    pub fn new(grants: &[(Pubkey, u64)]) -> Self {
+        let mut layers = vec![grants
+            .iter()
+            .map(|(wallet, ticket_count)| free_ticket_leaf(wallet, *ticket_count))
+            .collect::<Vec<_>>()];
+        while layers.last().unwrap().len() > 1 {
+            let parents = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => merkle_parent(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(parents);
+        }
+        Self { layers }
+    }
+
+    /// Root to publish with `publish_free_tickets`, all zeroes for an empty tree.
+    pub fn root(&self) -> [u8; 32] {
+        self.layers.last().unwrap().first().copied().unwrap_or_default()
+    }
+
+    /// Proof for the grant at `index` in the list the tree was built from.
+    pub fn proof(&self, mut index: usize) -> Vec<[u8; 32]> {
+        let mut proof = vec![];
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        proof
+    }
+}
@@ -0,0 +1,96 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use psyduk_raffle::{
+    ASSET_PRIZE_SEED, CLAIM_SEED, DENY_LIST_SEED, DRAW_PROGRESS_SEED, DRAW_SEED, ID, PRICE_CONFIG_SEED, PRIZE_VAULT_SEED, PROMOTION_SEED, RAFFLE_SEED, RECEIPT_SEED, RESERVE_SEED, STAKE_POOL_SEED,
+    STAKE_SEED, STAKE_VAULT_SEED, SUBSCRIPTION_SEED, VAULT_SEED, WITHDRAWAL_SEED,
+};
+
+/// Address of the singleton raffle account.
+pub fn raffle_address() -> Pubkey {
+    Pubkey::find_program_address(&[RAFFLE_SEED], &ID).0
+}
+
+/// Address of the vault holding the jackpot.
+pub fn vault_address() -> Pubkey {
+    Pubkey::find_program_address(&[VAULT_SEED], &ID).0
+}
+
+/// Address of the reserve backing the minimum prize.
+pub fn reserve_address() -> Pubkey {
+    Pubkey::find_program_address(&[RESERVE_SEED], &ID).0
+}
+
+/// Address of the list of wallets barred from the raffle, once one is denied.
+pub fn deny_list_address() -> Pubkey {
+    Pubkey::find_program_address(&[DENY_LIST_SEED], &ID).0
+}
+
+/// Address of the USD ticket pricing config, once the admin has set one.
+pub fn price_config_address() -> Pubkey {
+    Pubkey::find_program_address(&[PRICE_CONFIG_SEED], &ID).0
+}
+
+/// Address of the queued treasury withdrawal, if there is one.
+pub fn withdrawal_address() -> Pubkey {
+    Pubkey::find_program_address(&[WITHDRAWAL_SEED], &ID).0
+}
+
+/// Address of the subscription PDA belonging to `subscriber`.
+pub fn subscription_address(subscriber: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[SUBSCRIPTION_SEED, subscriber.as_ref()], &ID).0
+}
+
+/// Address of the receipt of the tickets `wallet` bought for itself this round.
+pub fn receipt_address(wallet: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[RECEIPT_SEED, wallet.as_ref()], &ID).0
+}
+
+/// Address of the free ticket promotion for the round ending at `round_end_time`.
+pub fn promotion_address(round_end_time: i64) -> Pubkey {
+    Pubkey::find_program_address(&[PROMOTION_SEED, &round_end_time.to_le_bytes()], &ID).0
+}
+
+/// Address of the receipt marking `wallet`'s claim on `promotion`.
+pub fn claim_address(promotion: &Pubkey, wallet: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[CLAIM_SEED, promotion.as_ref(), wallet.as_ref()], &ID).0
+}
+
+/// Address of the record of how the round ending at `round_end_time` was drawn.
+pub fn draw_record_address(round_end_time: i64) -> Pubkey {
+    Pubkey::find_program_address(&[DRAW_SEED, &round_end_time.to_le_bytes()], &ID).0
+}
+
+/// Address of the progress account of a draw spread over several transactions.
+pub fn draw_progress_address() -> Pubkey {
+    Pubkey::find_program_address(&[DRAW_PROGRESS_SEED], &ID).0
+}
+
+/// Address of the stake pool, which also signs for the stake vault.
+pub fn stake_pool_address() -> Pubkey {
+    Pubkey::find_program_address(&[STAKE_POOL_SEED], &ID).0
+}
+
+/// Address of the token account holding staked PSYDUK.
+pub fn stake_vault_address() -> Pubkey {
+    Pubkey::find_program_address(&[STAKE_VAULT_SEED], &ID).0
+}
+
+/// Address of the stake position belonging to `owner`.
+pub fn stake_position_address(owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[STAKE_SEED, owner.as_ref()], &ID).0
+}
+
+/// Address of the asset prize of the current round, if it has one.
+pub fn asset_prize_address() -> Pubkey {
+    Pubkey::find_program_address(&[ASSET_PRIZE_SEED], &ID).0
+}
+
+/// Address of the token account escrowing the asset prize.
+pub fn prize_vault_address() -> Pubkey {
+    Pubkey::find_program_address(&[PRIZE_VAULT_SEED], &ID).0
+}
+
+/// Address of the program data account holding the upgrade authority.
+pub fn program_data_address() -> Pubkey {
+    Pubkey::find_program_address(&[ID.as_ref()], &bpf_loader_upgradeable::id()).0
+}
@@ -0,0 +1,109 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use psyduk_raffle::{
+    AssetPrize, DenyList, DrawProgress, DrawRecord, DrawTranscript, PriceConfig, Raffle, RaffleV0, StakePool, StakePosition, Subscription, TicketReceipt, Withdrawal,
+    LEGACY_RAFFLE_SPACE,
+};
+
+/// Raffle account contents in whichever layout they were found.
+pub enum RaffleAccount {
+    Legacy(RaffleV0),
+    Current(Raffle),
+}
+
+/// Decodes a raffle account in the current layout, checking its discriminator.
+pub fn decode_raffle(data: &[u8]) -> Result<Raffle> {
+    Raffle::try_deserialize(&mut &data[..])
+}
+
+/// Decodes a raffle account that may still be in the pre-versioning layout.
+pub fn decode_any_raffle(data: &[u8]) -> Result<RaffleAccount> {
+    if data.len() == LEGACY_RAFFLE_SPACE {
+        if data[..8] != Raffle::DISCRIMINATOR {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+        return Ok(RaffleAccount::Legacy(RaffleV0::deserialize(&mut &data[8..])?));
+    }
+    decode_raffle(data).map(RaffleAccount::Current)
+}
+
+/// Decodes a subscription account, checking its discriminator.
+pub fn decode_subscription(data: &[u8]) -> Result<Subscription> {
+    Subscription::try_deserialize(&mut &data[..])
+}
+
+/// Decodes a ticket receipt, checking its discriminator.
+pub fn decode_ticket_receipt(data: &[u8]) -> Result<TicketReceipt> {
+    TicketReceipt::try_deserialize(&mut &data[..])
+}
+
+/// Decodes the deny list, checking its discriminator.
+pub fn decode_deny_list(data: &[u8]) -> Result<DenyList> {
+    DenyList::try_deserialize(&mut &data[..])
+}
+
+/// Decodes the USD ticket pricing config, checking its discriminator.
+pub fn decode_price_config(data: &[u8]) -> Result<PriceConfig> {
+    PriceConfig::try_deserialize(&mut &data[..])
+}
+
+/// Decodes the queued treasury withdrawal, checking its discriminator.
+pub fn decode_withdrawal(data: &[u8]) -> Result<Withdrawal> {
+    Withdrawal::try_deserialize(&mut &data[..])
+}
+
+/// Decodes the asset prize account, checking its discriminator.
+pub fn decode_asset_prize(data: &[u8]) -> Result<AssetPrize> {
+    AssetPrize::try_deserialize(&mut &data[..])
+}
+
+/// Decodes a draw record, checking its discriminator.
+pub fn decode_draw_record(data: &[u8]) -> Result<DrawRecord> {
+    DrawRecord::try_deserialize(&mut &data[..])
+}
+
+/// Decodes the progress account of a batched draw, checking its discriminator.
+pub fn decode_draw_progress(data: &[u8]) -> Result<DrawProgress> {
+    DrawProgress::try_deserialize(&mut &data[..])
+}
+
+/// Decodes the stake pool account, checking its discriminator.
+pub fn decode_stake_pool(data: &[u8]) -> Result<StakePool> {
+    StakePool::try_deserialize(&mut &data[..])
+}
+
+/// Decodes a stake position account, checking its discriminator.
+pub fn decode_stake_position(data: &[u8]) -> Result<StakePosition> {
+    StakePosition::try_deserialize(&mut &data[..])
+}
+
+/// Decodes what a `get_` query instruction returned, e.g. the return data of a
+/// simulated `get_round_summary` as a `RoundSummary`.
+pub fn decode_return_data<T: AnchorDeserialize>(data: &[u8]) -> Result<T> {
+    Ok(T::try_from_slice(data)?)
+}
+
+/// Number of tickets `wallet` holds in the current round.
+pub fn tickets_of(raffle: &Raffle, wallet: &Pubkey) -> usize {
+    raffle.tickets.iter().filter(|ticket| *ticket == wallet).count()
+}
+
+/// Owner of the ticket a locked round draws, given the SlotHashes sysvar data,
+/// redrawn past the wallets on `deny_list`. Fails the way `select_winner` would
+/// while no slot past the target has a hash yet or once the target has aged out.
+pub fn drawn_winner(raffle: &Raffle, slot_hashes: &[u8], deny_list: &DenyList) -> Result<Pubkey> {
+    require!(!raffle.tickets.is_empty(), psyduk_raffle::RaffleError::NoTickets);
+    require!(raffle.draw_slot != 0, psyduk_raffle::RaffleError::RoundNotLocked);
+    let (entropy_slot, slot_hash) = psyduk_raffle::slot_hash_after(slot_hashes, raffle.draw_slot)?;
+    let transcript = DrawTranscript::new(raffle.draw_slot, entropy_slot, slot_hash, &raffle.tickets);
+    transcript.eligible_winner(&raffle.tickets, deny_list)
+}
+
+/// Owner of the ticket a batched draw of the current round will pay, from the
+/// entropy `begin_draw` fixed and redrawn past the wallets on `deny_list`.
+pub fn batched_winner(raffle: &Raffle, progress: &DrawProgress, deny_list: &DenyList) -> Result<Pubkey> {
+    require!(!raffle.tickets.is_empty(), psyduk_raffle::RaffleError::NoTickets);
+    require!(progress.round_end_time == raffle.end_time, psyduk_raffle::RaffleError::DrawNotStarted);
+    let transcript = DrawTranscript::new(progress.draw_slot, progress.entropy_slot, progress.slot_hash, &raffle.tickets);
+    transcript.eligible_winner(&raffle.tickets, deny_list)
+}
@@ -0,0 +1,58 @@
+use solana_sdk::signature::{read_keypair_file, Keypair};
+use std::env;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Keeper settings, read from the environment (and `.env`)
+pub struct Config {
+    pub rpc_url: String,
+    pub keypair: Keypair,
+    pub telegram_token: Option<String>,
+    pub telegram_chat_ids: Vec<String>,
+    pub priority_fee_micro_lamports: u64,
+    pub compute_unit_limit: u32,
+    pub max_retries: usize,
+    pub poll_interval_secs: u64,
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self, BoxError> {
+        Ok(Self {
+            rpc_url: env::var("SOLANA_RPC_URL").unwrap_or_else(|_| "https://api.devnet.solana.com".to_string()),
+            keypair: load_keypair()?,
+            telegram_token: env::var("TELOXIDE_TOKEN").ok(),
+            telegram_chat_ids: env::var("TELEGRAM_CHAT_IDS")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(String::from)
+                .collect(),
+            priority_fee_micro_lamports: parse_var("PRIORITY_FEE_MICRO_LAMPORTS", 10_000)?,
+            compute_unit_limit: parse_var("COMPUTE_UNIT_LIMIT", 400_000)?,
+            max_retries: parse_var("MAX_RETRIES", 5)?,
+            poll_interval_secs: parse_var("POLL_INTERVAL_SECS", 5)?,
+        })
+    }
+}
+
+/// Loads the keeper keypair from `KEEPER_KEYPAIR` (a JSON byte array) or the file at `KEEPER_KEYPAIR_PATH`
+fn load_keypair() -> Result<Keypair, BoxError> {
+    if let Ok(json) = env::var("KEEPER_KEYPAIR") {
+        let bytes: Vec<u8> = serde_json::from_str(&json)?;
+        return Ok(Keypair::from_bytes(&bytes)?);
+    }
+
+    let path = env::var("KEEPER_KEYPAIR_PATH")
+        .map_err(|_| "Set KEEPER_KEYPAIR or KEEPER_KEYPAIR_PATH to the keeper keypair")?;
+    read_keypair_file(&path).map_err(|err| format!("Failed to read keypair {}: {}", path, err).into())
+}
+
+fn parse_var<T: std::str::FromStr>(name: &str, default: T) -> Result<T, BoxError> {
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| format!("{} has an invalid value: {}", name, value).into()),
+        Err(_) => Ok(default),
+    }
+}
@@ -0,0 +1,348 @@
+use dotenv::dotenv;
+use log::{error, info, warn};
+use psyduk_raffle_client::{
+    instructions, pda, state, AssetPrize, DenyList, Discriminator, DrawProgress, Raffle, RaffleError, RoundStatus, StakePosition,
+    Subscription, NO_ROUND, PROGRAM_ID,
+};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Signature, Signer};
+use solana_sdk::sysvar;
+use solana_sdk::transaction::Transaction;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::sleep;
+
+mod config;
+mod telegram;
+
+use config::Config;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Subscriptions or stakes entered per transaction, within the account limit
+const ENTRIES_PER_TRANSACTION: usize = 20;
+
+/// Ledgers up to this many tickets are drawn by a single `select_winner`
+const SINGLE_DRAW_TICKETS: usize = 256;
+
+/// Tickets hashed per `advance_draw` of a batched draw
+const DRAW_CHUNK_TICKETS: u64 = 256;
+
+/// Result of a successful `select_winner` or `finalize_draw`
+struct DrawOutcome {
+    signature: Signature,
+    winner: Pubkey,
+    prize: u64,
+    tickets_sold: usize,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), BoxError> {
+    dotenv().ok();
+    pretty_env_logger::init();
+
+    let config = Config::from_env()?;
+    let client = RpcClient::new_with_commitment(config.rpc_url.clone(), CommitmentConfig::confirmed());
+    info!("Keeper {} watching raffle {}", config.keypair.pubkey(), pda::raffle_address());
+
+    let mut recurring_entered_for = None;
+    loop {
+        match fetch_raffle(&client).await {
+            Ok(raffle) if accepts_entries(&raffle) && recurring_entered_for != Some(raffle.end_time) => {
+                match enter_recurring(&client, &config, &raffle).await {
+                    (Ok(subscriptions), Ok(stakes)) => {
+                        info!(
+                            "Entered {} subscriptions and {} stakes into the round ending at {}",
+                            subscriptions, stakes, raffle.end_time
+                        );
+                        recurring_entered_for = Some(raffle.end_time);
+                        continue;
+                    }
+                    // Whatever was entered stays entered, the rest is retried on the next poll
+                    (subscriptions, stakes) => {
+                        if let Err(err) = subscriptions {
+                            error!("Entering subscriptions failed: {}", err);
+                        }
+                        if let Err(err) = stakes {
+                            error!("Entering stakes failed: {}", err);
+                        }
+                    }
+                }
+            }
+            Ok(raffle) if raffle.end_time > unix_now() => {
+                // Sleep until the round ends, waking up regularly in case it changes
+                let wait = (raffle.end_time - unix_now()) as u64;
+                sleep(Duration::from_secs(wait.clamp(1, config.poll_interval_secs))).await;
+                continue;
+            }
+            Ok(raffle) if raffle.tickets.is_empty() => {
+                info!("Round ended without tickets, waiting for a purchase");
+            }
+            Ok(_) => match draw(&client, &config).await {
+                Ok(outcome) => announce_results(&client, &config, &outcome).await,
+                Err(err) => error!("Draw failed: {}", err),
+            },
+            Err(err) => error!("Failed to fetch raffle: {}", err),
+        }
+
+        sleep(Duration::from_secs(config.poll_interval_secs)).await;
+    }
+}
+
+async fn fetch_raffle(client: &RpcClient) -> Result<Raffle, BoxError> {
+    let data = client.get_account_data(&pda::raffle_address()).await?;
+    Ok(state::decode_raffle(&data)?)
+}
+
+/// Whether the raffle would take entries right now, counting a round that ended
+/// without tickets, which the next entry rolls forward.
+fn accepts_entries(raffle: &Raffle) -> bool {
+    let now = unix_now();
+    let sales_close = raffle.end_time.saturating_sub(raffle.sales_cutoff);
+    raffle.status == RoundStatus::Open
+        && raffle.start_time != NO_ROUND
+        && raffle.start_time <= now
+        && (now < sales_close || raffle.tickets.is_empty())
+}
+
+/// Enters every subscription with prepaid rounds left and every active stake into
+/// the open round, returning how many of each were pending. Stakes are entered
+/// even if subscriptions fail, and either fails only once all its batches were tried.
+async fn enter_recurring(
+    client: &RpcClient,
+    config: &Config,
+    raffle: &Raffle,
+) -> (Result<usize, BoxError>, Result<usize, BoxError>) {
+    let payer = config.keypair.pubkey();
+
+    let subscriptions = async {
+        let subscribers: Vec<Pubkey> = fetch_program_accounts::<Subscription>(client)
+            .await?
+            .iter()
+            .filter_map(|data| state::decode_subscription(data).ok())
+            .filter(|subscription| subscription.rounds_remaining > 0 && subscription.last_round_end != raffle.end_time)
+            .map(|subscription| subscription.subscriber)
+            .collect();
+        send_batches(client, config, &subscribers, |batch| instructions::apply_subscriptions(&payer, batch)).await?;
+        Ok(subscribers.len())
+    }
+    .await;
+
+    let stakes = async {
+        let stakers: Vec<Pubkey> = fetch_program_accounts::<StakePosition>(client)
+            .await?
+            .iter()
+            .filter_map(|data| state::decode_stake_position(data).ok())
+            .filter(|position| position.is_active() && position.last_round_end != raffle.end_time)
+            .map(|position| position.owner)
+            .collect();
+        send_batches(client, config, &stakers, |batch| instructions::allocate_stake_tickets(&payer, batch)).await?;
+        Ok(stakers.len())
+    }
+    .await;
+
+    (subscriptions, stakes)
+}
+
+/// Sends the instruction `build` makes for each batch of `wallets`, carrying on
+/// past a failed batch so it doesn't hold up the others.
+async fn send_batches(
+    client: &RpcClient,
+    config: &Config,
+    wallets: &[Pubkey],
+    build: impl Fn(&[Pubkey]) -> Instruction,
+) -> Result<(), BoxError> {
+    let batches = wallets.chunks(ENTRIES_PER_TRANSACTION);
+    let total = batches.len();
+    let mut failed = 0;
+    for batch in batches {
+        if let Err(err) = send(client, config, build(batch)).await {
+            warn!("Batch of {} entries starting at {} failed: {}", batch.len(), batch[0], err);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} of {} batches failed", failed, total).into());
+    }
+    Ok(())
+}
+
+/// Data of every program account of type `T`.
+async fn fetch_program_accounts<T: Discriminator>(client: &RpcClient) -> Result<Vec<Vec<u8>>, BoxError> {
+    let accounts = client
+        .get_program_accounts_with_config(
+            &PROGRAM_ID,
+            RpcProgramAccountsConfig {
+                filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, &T::DISCRIMINATOR))]),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            },
+        )
+        .await?;
+    Ok(accounts.into_iter().map(|(_, account)| account.data).collect())
+}
+
+/// Sends `instruction` with the configured priority fee and waits for confirmation.
+async fn send(client: &RpcClient, config: &Config, instruction: Instruction) -> Result<Signature, BoxError> {
+    let instructions = [
+        ComputeBudgetInstruction::set_compute_unit_limit(config.compute_unit_limit),
+        ComputeBudgetInstruction::set_compute_unit_price(config.priority_fee_micro_lamports),
+        instruction,
+    ];
+    let blockhash = client.get_latest_blockhash().await?;
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&config.keypair.pubkey()),
+        &[&config.keypair],
+        blockhash,
+    );
+    Ok(client.send_and_confirm_transaction(&transaction).await?)
+}
+
+/// Locks the round, then submits `select_winner` with a priority fee once a slot after
+/// the lock target has a hash. The drawn ticket is predicted from the SlotHashes
+/// sysvar, which fixes it for good; a lock whose target has aged out is renewed.
+/// Large ledgers are drawn in batches instead, picking up any batched draw of the
+/// round another keeper has begun.
+async fn draw(client: &RpcClient, config: &Config) -> Result<DrawOutcome, BoxError> {
+    let payer = config.keypair.pubkey();
+    let mut last_error: BoxError = "No draw attempt made".into();
+
+    for attempt in 1..=config.max_retries {
+        let raffle = fetch_raffle(client).await?;
+        if raffle.end_time > unix_now() || raffle.tickets.is_empty() {
+            return Err("Round was already drawn".into());
+        }
+
+        let asset_prize = if raffle.asset_round {
+            Some(state::decode_asset_prize(&client.get_account_data(&pda::asset_prize_address()).await?)?)
+        } else {
+            None
+        };
+        let progress = fetch_draw_progress(client).await.filter(|progress| progress.round_end_time == raffle.end_time);
+        let slot_hashes = client.get_account_data(&sysvar::slot_hashes::id()).await?;
+        let deny_list = fetch_deny_list(client).await;
+        let result = match (progress, state::drawn_winner(&raffle, &slot_hashes, &deny_list)) {
+            (Some(progress), _) => finish_batched_draw(client, config, &raffle, &progress, &deny_list, asset_prize.as_ref())
+                .await
+                .map(Some),
+            (None, Ok(_)) if raffle.tickets.len() > SINGLE_DRAW_TICKETS => {
+                send(client, config, instructions::begin_draw(&payer)).await.map(|signature| {
+                    info!("Batched draw begun in {}", signature);
+                    None
+                })
+            }
+            (None, Ok(winner)) => {
+                let instruction = match &asset_prize {
+                    Some(asset_prize) => instructions::select_asset_winner(&payer, raffle.end_time, &winner, asset_prize),
+                    None => instructions::select_winner(&payer, raffle.end_time, &winner),
+                };
+                send(client, config, instruction).await.map(|signature| Some((signature, winner)))
+            }
+            (None, Err(err)) if err == RaffleError::RoundNotLocked.into() || err == RaffleError::DrawSlotExpired.into() => {
+                send(client, config, instructions::lock_round()).await.map(|signature| {
+                    info!("Round locked in {}", signature);
+                    None
+                })
+            }
+            (None, Err(err)) => Err(err.into()),
+        };
+
+        match result {
+            Ok(Some((signature, winner))) => {
+                info!("Round drawn in {} (attempt {})", signature, attempt);
+                let (prize, _) = raffle.split_payout()?;
+                return Ok(DrawOutcome {
+                    signature,
+                    winner,
+                    prize: prize + raffle.min_prize_top_up(prize),
+                    tickets_sold: raffle.tickets.len(),
+                });
+            }
+            Ok(None) => sleep(Duration::from_secs(1)).await,
+            Err(err) => {
+                warn!("Draw attempt {}/{} failed: {}", attempt, config.max_retries, err);
+                last_error = err;
+                sleep(Duration::from_secs(1 << (attempt - 1).min(5))).await;
+            }
+        }
+    }
+
+    Err(last_error)
+}
+
+/// Hashes the rest of the ledger into a begun batched draw and pays it out.
+async fn finish_batched_draw(
+    client: &RpcClient,
+    config: &Config,
+    raffle: &Raffle,
+    progress: &DrawProgress,
+    deny_list: &DenyList,
+    asset_prize: Option<&AssetPrize>,
+) -> Result<(Signature, Pubkey), BoxError> {
+    let payer = config.keypair.pubkey();
+    let winner = state::batched_winner(raffle, progress, deny_list)?;
+
+    let mut cursor = progress.cursor as usize;
+    while cursor < raffle.tickets.len() {
+        let signature = send(client, config, instructions::advance_draw(DRAW_CHUNK_TICKETS)).await?;
+        cursor = (cursor + DRAW_CHUNK_TICKETS as usize).min(raffle.tickets.len());
+        info!("Hashed {} of {} tickets in {}", cursor, raffle.tickets.len(), signature);
+    }
+
+    let instruction = match asset_prize {
+        Some(asset_prize) => instructions::finalize_asset_draw(&payer, raffle.end_time, &winner, asset_prize),
+        None => instructions::finalize_draw(&payer, raffle.end_time, &winner),
+    };
+    Ok((send(client, config, instruction).await?, winner))
+}
+
+/// The batched draw progress account, if one has ever been created.
+async fn fetch_draw_progress(client: &RpcClient) -> Option<DrawProgress> {
+    let data = client.get_account_data(&pda::draw_progress_address()).await.ok()?;
+    state::decode_draw_progress(&data).ok()
+}
+
+/// The deny list, empty if the admin has never denied a wallet.
+async fn fetch_deny_list(client: &RpcClient) -> DenyList {
+    let data = client.get_account_data(&pda::deny_list_address()).await.ok();
+    data.and_then(|data| state::decode_deny_list(&data).ok()).unwrap_or_default()
+}
+
+async fn announce_results(client: &RpcClient, config: &Config, outcome: &DrawOutcome) {
+    let next_round = match fetch_raffle(client).await {
+        Ok(raffle) if raffle.end_time == NO_ROUND => "not scheduled yet".to_string(),
+        Ok(raffle) => format!("in {} minutes", (raffle.end_time - unix_now()).max(0) / 60),
+        Err(_) => "soon".to_string(),
+    };
+    let message = format!(
+        "🎉 *Raffle Round Ended!*\n\n🏆 *Winner:* `{}`\n💰 *Prize:* {} SOL\n🎟️ *Tickets sold:* {}\n🔗 `{}`\n\nThe next draw is {}. Get your tickets! 🎟️",
+        outcome.winner,
+        outcome.prize as f64 / 1_000_000_000.0,
+        outcome.tickets_sold,
+        outcome.signature,
+        next_round
+    );
+    info!("{}", message);
+
+    match &config.telegram_token {
+        Some(token) => telegram::announce(token, &config.telegram_chat_ids, &message).await,
+        None => warn!("TELOXIDE_TOKEN not set, skipping Telegram announcement"),
+    }
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
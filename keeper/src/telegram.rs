@@ -0,0 +1,21 @@
+use log::error;
+use serde_json::json;
+
+/// Posts a Markdown message to every configured chat, logging failures
+pub async fn announce(token: &str, chat_ids: &[String], text: &str) {
+    let client = reqwest::Client::new();
+    let url = format!("https://api.telegram.org/bot{}/sendMessage", token);
+
+    for chat_id in chat_ids {
+        let result = client
+            .post(&url)
+            .json(&json!({ "chat_id": chat_id, "text": text, "parse_mode": "Markdown" }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        if let Err(err) = result {
+            error!("Failed to post results to chat {}: {}", chat_id, err);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! A price feed its authority can set to any value, for testing the raffle's USD
+//! ticket pricing locally. Feed accounts use the layout of the Pyth receiver's
+//! `PriceUpdateV2` accounts, which the raffle reads, and the program is deployed
+//! at the receiver's address on local clusters so the raffle's owner check holds.
+
+use anchor_lang::prelude::*;
+
+declare_id!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+#[program]
+mod mock_price_feed {
+    use super::*;
+
+    /// Creates a feed at a fresh keypair address, written by `write_authority`.
+    pub fn create_feed(ctx: Context<CreateFeed>) -> Result<()> {
+        let feed = &mut ctx.accounts.feed;
+        feed.write_authority = ctx.accounts.write_authority.key();
+        feed.verification_level = VerificationLevel::Full;
+        Ok(())
+    }
+
+    /// Publishes `price` ± `confidence`, both scaled by 10^`exponent`, as of `publish_time`.
+    pub fn set_price(ctx: Context<SetPrice>, price: i64, confidence: u64, exponent: i32, publish_time: i64) -> Result<()> {
+        let feed = &mut ctx.accounts.feed;
+        let message = &mut feed.price_message;
+        message.prev_publish_time = message.publish_time;
+        message.price = price;
+        message.conf = confidence;
+        message.exponent = exponent;
+        message.publish_time = publish_time;
+        message.ema_price = price;
+        message.ema_conf = confidence;
+        feed.posted_slot = Clock::get()?.slot;
+        Ok(())
+    }
+
+    /// Sets how many guardian signatures the feed claims were verified.
+    pub fn set_verification_level(ctx: Context<SetPrice>, verification_level: VerificationLevel) -> Result<()> {
+        ctx.accounts.feed.verification_level = verification_level;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct CreateFeed<'info> {
+    #[account(init, payer = write_authority, space = PriceUpdateV2::SPACE)]
+    pub feed: Account<'info, PriceUpdateV2>,          // New feed account
+    #[account(mut)]
+    pub write_authority: Signer<'info>,               // Wallet allowed to set the price
+    pub system_program: Program<'info, System>,       // System program for account creation
+}
+
+#[derive(Accounts)]
+pub struct SetPrice<'info> {
+    #[account(mut, has_one = write_authority)]
+    pub feed: Account<'info, PriceUpdateV2>,          // Feed to update
+    pub write_authority: Signer<'info>,               // The feed's authority
+}
+
+#[account]
+pub struct PriceUpdateV2 {
+    pub write_authority: Pubkey,  // Wallet allowed to set the price
+    pub verification_level: VerificationLevel, // Guardian signatures verified
+    pub price_message: PriceFeedMessage, // The published price
+    pub posted_slot: u64,         // Slot the price was last set in
+}
+
+impl PriceUpdateV2 {
+    pub const SPACE: usize = 8 + 32 + 2 + 32 + 8 + 8 + 4 + 8 + 8 + 8 + 8 + 8;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VerificationLevel {
+    /// Only `num_signatures` guardian signatures were verified.
+    Partial { num_signatures: u8 },
+    /// Every guardian signature needed was verified.
+    #[default]
+    Full,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PriceFeedMessage {
+    pub feed_id: [u8; 32],        // Pyth id of the price feed
+    pub price: i64,               // USD price of one SOL, scaled by 10^exponent
+    pub conf: u64,                // Confidence interval around the price, same scale
+    pub exponent: i32,            // Power of ten the price is scaled by
+    pub publish_time: i64,        // When the price was published
+    pub prev_publish_time: i64,   // When the previous price was published
+    pub ema_price: i64,           // Moving average of the price
+    pub ema_conf: u64,            // Moving average of the confidence interval
+}
@@ -0,0 +1,19 @@
+use anchor_lang::prelude::*;
+
+pub const ASSET_PRIZE_SEED: &[u8] = b"asset_prize"; // Fixed seed for the asset prize PDA, authority of the prize vault
+pub const PRIZE_VAULT_SEED: &[u8] = b"prize_vault"; // Fixed seed for the token account escrowing the asset prize
+
+/// An NFT or fixed SPL amount standing in for the jackpot of the current round.
+/// While one is attached, ticket revenue goes to its creator minus the treasury fee.
+#[account]
+pub struct AssetPrize {
+    pub creator: Pubkey,              // Wallet that escrows the prize and receives the ticket revenue
+    pub mint: Pubkey,                 // Mint of the prize
+    pub amount: u64,                  // Base units of the mint won, 1 for an NFT
+    pub deposited: bool,              // Whether the prize is in the prize vault, which opens sales
+    pub bump: u8,                     // Bump of this PDA, which signs for the prize vault
+}
+
+impl AssetPrize {
+    pub const SPACE: usize = 8 + 32 + 32 + 8 + 1 + 1;
+}
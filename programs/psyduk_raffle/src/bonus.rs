@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+use std::str::FromStr;
+
+use crate::RaffleError;
+
+pub const MAX_BONUS_TIERS: usize = 4; // Tier slots in the Raffle header
+pub const PSYDUK_MINT: &str = "iQuoGfqmXh6J3PShHDntayXGVixfp44wzGkVaH8r8RE"; // Token whose holders earn bonus tickets
+
+/// Address of the PSYDUK mint.
+pub fn psyduk_mint() -> Pubkey {
+    Pubkey::from_str(PSYDUK_MINT).unwrap()
+}
+
+/// Extra tickets for holding at least `min_balance` PSYDUK, in the token's base
+/// units, as a percentage of the tickets paid for. A zeroed tier is unused.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BonusTier {
+    pub min_balance: u64,
+    pub bonus_percent: u16,
+}
+
+impl BonusTier {
+    pub const SIZE: usize = 8 + 2;
+
+    fn is_used(&self) -> bool {
+        self.bonus_percent > 0
+    }
+}
+
+/// Tiers must list the used slots first, with strictly increasing balances,
+/// and never grant more than the tickets paid for.
+pub fn validate_bonus_tiers(tiers: &[BonusTier; MAX_BONUS_TIERS]) -> Result<()> {
+    let used = tiers.iter().take_while(|tier| tier.is_used()).count();
+    let valid = tiers[used..].iter().all(|tier| *tier == BonusTier::default())
+        && tiers[..used].iter().all(|tier| tier.bonus_percent <= 100)
+        && tiers[..used].windows(2).all(|pair| pair[0].min_balance < pair[1].min_balance);
+    require!(valid, RaffleError::InvalidBonusTiers);
+    Ok(())
+}
+
+/// Bonus tickets earned on `paid_tickets` by a holder of `balance`, from the
+/// highest tier reached. Fractions of a ticket are rounded down.
+pub fn bonus_tickets(tiers: &[BonusTier; MAX_BONUS_TIERS], balance: u64, paid_tickets: u64) -> u64 {
+    tiers
+        .iter()
+        .filter(|tier| tier.is_used() && balance >= tier.min_balance)
+        .map(|tier| paid_tickets.saturating_mul(tier.bonus_percent as u64) / 100)
+        .max()
+        .unwrap_or(0)
+}
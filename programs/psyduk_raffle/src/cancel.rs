@@ -0,0 +1,104 @@
+use anchor_lang::prelude::*;
+
+use crate::RaffleError;
+
+pub const RECEIPT_SEED: &[u8] = b"receipt"; // Seed prefix for per-wallet ticket receipt PDAs
+pub const MAX_CANCEL_PENALTY_PERCENT: u8 = 100; // Upper bound of the cancellation penalty
+
+/// The tickets a wallet bought for itself in the current round, which are the
+/// only ones it can cancel. Free, staking, subscription and gifted tickets are
+/// never recorded here.
+#[account]
+pub struct TicketReceipt {
+    pub wallet: Pubkey,           // Wallet that bought the tickets
+    pub round_end_time: i64,      // End time of the round the counts below are for
+    pub paid_tickets: u64,        // Tickets paid for and not cancelled
+    pub bonus_tickets: u64,       // Holder bonus tickets earned by those purchases
+    pub cost_paid: u64,           // Lamports those purchases cost
+    pub jackpot_paid: u64,        // Part of the cost put into the jackpot
+    pub fees_held: u64,           // Part of the cost held in the vault as fees
+}
+
+impl TicketReceipt {
+    pub const SPACE: usize = 8 + 32 + 8 + 8 + 8 + 8 + 8 + 8;
+
+    /// Adds a purchase made in the round ending at `round_end_time`, first
+    /// forgetting the counts of any earlier round.
+    pub fn record(&mut self, wallet: Pubkey, round_end_time: i64, tickets: u64, bonus: u64, payment: Payment) -> Result<()> {
+        if self.round_end_time != round_end_time {
+            self.paid_tickets = 0;
+            self.bonus_tickets = 0;
+            self.cost_paid = 0;
+            self.jackpot_paid = 0;
+            self.fees_held = 0;
+        }
+        self.wallet = wallet;
+        self.round_end_time = round_end_time;
+        self.paid_tickets = self.paid_tickets.checked_add(tickets).ok_or(RaffleError::ArithmeticOverflow)?;
+        self.bonus_tickets = self.bonus_tickets.checked_add(bonus).ok_or(RaffleError::ArithmeticOverflow)?;
+        self.cost_paid = self.cost_paid.checked_add(payment.cost).ok_or(RaffleError::ArithmeticOverflow)?;
+        self.jackpot_paid = self.jackpot_paid.checked_add(payment.jackpot).ok_or(RaffleError::ArithmeticOverflow)?;
+        self.fees_held = self.fees_held.checked_add(payment.held_fee).ok_or(RaffleError::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Takes `count` paid tickets of the round ending at `round_end_time` off the
+    /// receipt. They are refunded their share of `cost_paid` less `penalty_percent`
+    /// of it, and the bonus tickets they no longer earn are forfeited.
+    ///
+    /// Only what is still in the vault can be refunded: their jackpot share and
+    /// their fees held there, up to the `unpaid_fees` not yet withdrawn. A fee
+    /// already paid out to the treasury is retained, counting towards the penalty.
+    pub fn cancel(&mut self, round_end_time: i64, count: u64, penalty_percent: u8, unpaid_fees: u64) -> Result<Cancellation> {
+        require!(count > 0, RaffleError::IncorrectAmount);
+        require!(
+            self.round_end_time == round_end_time && count <= self.paid_tickets,
+            RaffleError::NotEnoughTickets
+        );
+
+        let kept = self.paid_tickets - count;
+        let share = |total: u64| (total as u128 * count as u128 / self.paid_tickets as u128) as u64;
+        let cost = share(self.cost_paid);
+        let jackpot = share(self.jackpot_paid);
+        let held_fee = share(self.fees_held);
+        let bonus_kept = (self.bonus_tickets as u128 * kept as u128 / self.paid_tickets as u128) as u64;
+        let penalty = (cost as u128 * penalty_percent as u128 / 100) as u64;
+        let released_fee = held_fee.min(unpaid_fees);
+        let refund = (cost - penalty).min(jackpot + released_fee);
+
+        let cancellation = Cancellation {
+            tickets: count,
+            bonus_tickets: self.bonus_tickets - bonus_kept,
+            refund,
+            penalty,
+            fee_retained: cost - penalty - refund,
+            released_fee,
+        };
+        self.paid_tickets = kept;
+        self.bonus_tickets = bonus_kept;
+        self.cost_paid -= cost;
+        self.jackpot_paid -= jackpot;
+        self.fees_held -= held_fee;
+        Ok(cancellation)
+    }
+}
+
+/// How the cost of a purchase was split when it was paid.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Payment {
+    pub cost: u64,                // Lamports paid for the tickets
+    pub jackpot: u64,             // Part put into the jackpot
+    pub held_fee: u64,            // Part of the fee held in the vault
+}
+
+/// What cancelling tickets takes out of the round. The held fees released that
+/// aren't refunded join the jackpot with the rest of the penalty.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cancellation {
+    pub tickets: u64,             // Paid tickets cancelled
+    pub bonus_tickets: u64,       // Bonus tickets forfeited with them
+    pub refund: u64,              // Lamports returned from the vault
+    pub penalty: u64,             // Part of the cost kept as the cancellation penalty
+    pub fee_retained: u64,        // Fee already paid to the treasury, kept beyond the penalty
+    pub released_fee: u64,        // Held fees taken off the raffle's unpaid fees
+}
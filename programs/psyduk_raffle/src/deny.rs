@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::keccak;
+
+use crate::RaffleError;
+
+pub const DENY_LIST_SEED: &[u8] = b"deny_list"; // Fixed seed for the deny list PDA
+pub const MAX_DENIED_WALLETS: usize = 256; // Capacity of the deny list
+
+/// Wallets barred from buying tickets and from winning, kept sorted so lookups
+/// are a binary search. The account only exists once the admin has denied a
+/// wallet; until then nobody is barred.
+#[account]
+#[derive(Default)]
+pub struct DenyList {
+    pub wallets: Vec<Pubkey>,     // Denied wallets, in ascending order
+}
+
+impl DenyList {
+    pub const SPACE: usize = 8 + 4 + 32 * MAX_DENIED_WALLETS;
+
+    /// Whether `wallet` is denied.
+    pub fn contains(&self, wallet: &Pubkey) -> bool {
+        self.wallets.binary_search(wallet).is_ok()
+    }
+
+    /// Denies `wallet`, which is a no-op if it already is.
+    pub fn insert(&mut self, wallet: Pubkey) -> Result<()> {
+        if let Err(position) = self.wallets.binary_search(&wallet) {
+            require!(self.wallets.len() < MAX_DENIED_WALLETS, RaffleError::DenyListFull);
+            self.wallets.insert(position, wallet);
+        }
+        Ok(())
+    }
+
+    /// Hash committing to the denied wallets, in order.
+    pub fn hash(&self) -> [u8; 32] {
+        let wallets: Vec<&[u8]> = self.wallets.iter().map(|wallet| wallet.as_ref()).collect();
+        keccak::hashv(&wallets).to_bytes()
+    }
+
+    /// Lifts the denial of `wallet`, which is a no-op if it isn't denied.
+    pub fn remove(&mut self, wallet: &Pubkey) {
+        if let Ok(position) = self.wallets.binary_search(wallet) {
+            self.wallets.remove(position);
+        }
+    }
+}
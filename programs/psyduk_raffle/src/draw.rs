@@ -0,0 +1,194 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::keccak;
+use anchor_lang::solana_program::slot_hashes::MAX_ENTRIES;
+
+use crate::{DenyList, RaffleError};
+
+pub const DRAW_SEED: &[u8] = b"draw"; // Seed prefix for per-round draw record PDAs
+pub const DRAW_PROGRESS_SEED: &[u8] = b"draw_progress"; // Fixed seed for the PDA of a draw spread over transactions
+pub const SLOT_HASH_WINDOW: u64 = MAX_ENTRIES as u64; // Slots the SlotHashes sysvar remembers
+pub const MAX_REDRAWS: u64 = 32; // Redraws tried when drawn tickets belong to denied wallets
+
+const SLOT_HASH_ENTRY_SIZE: usize = 8 + 32;
+
+/// How a round was drawn, kept so anyone can check it.
+#[account]
+pub struct DrawRecord {
+    pub round_end_time: i64,      // End time of the round drawn
+    pub winner: Pubkey,           // Owner of the ticket paid, after any redraws past denied wallets
+    pub prize: u64,               // Lamports paid to the winner
+    pub house_fee: u64,           // Lamports of the jackpot paid to the treasury
+    pub transcript: DrawTranscript, // Everything the first pick was computed from
+    pub redraws: u64,             // Redraws made past tickets of denied wallets, 0 if the first pick won
+    pub paid_index: u64,          // Index of the ticket paid in the ledger
+    pub deny_list_hash: [u8; 32], // Hash of the deny list the draw skipped wallets on
+}
+
+impl DrawRecord {
+    pub const SPACE: usize = 8 + 8 + 32 + 8 + 8 + DrawTranscript::SIZE + 8 + 8 + 32;
+
+    /// Replays the draw, with its redraws past the wallets on `deny_list` as it
+    /// stood at the draw, against the round's ticket ledger and returns the winner
+    /// paid. Fails if any recorded value doesn't follow from its inputs.
+    pub fn verify(&self, tickets: &[Pubkey], deny_list: &DenyList) -> Result<Pubkey> {
+        self.transcript.verify(tickets)?;
+        require!(deny_list.hash() == self.deny_list_hash, RaffleError::InvalidDrawTranscript);
+        let (redraws, paid_index) = self.transcript.eligible_draw(tickets, deny_list)?;
+        require!(
+            redraws == self.redraws && paid_index == self.paid_index && tickets[paid_index as usize] == self.winner,
+            RaffleError::InvalidDrawTranscript
+        );
+        Ok(self.winner)
+    }
+}
+
+/// A draw driven over several transactions: `begin_draw` fixes the entropy,
+/// `advance_draw` hashes the ledger a chunk at a time and `finalize_draw` pays out.
+#[account]
+pub struct DrawProgress {
+    pub round_end_time: i64,      // End time of the round being drawn
+    pub draw_slot: u64,           // Target slot fixed when the round was locked
+    pub entropy_slot: u64,        // First slot after the target with a recorded hash
+    pub slot_hash: [u8; 32],      // Hash of that slot
+    pub cursor: u64,              // Tickets hashed so far
+    pub ledger_hash: [u8; 32],    // Hash of the tickets before the cursor
+}
+
+impl DrawProgress {
+    pub const SPACE: usize = 8 + 8 + 8 + 8 + 32 + 8 + 32;
+
+    /// Hashes up to `max_tickets` more tickets of the ledger.
+    pub fn advance(&mut self, tickets: &[Pubkey], max_tickets: u64) {
+        let start = self.cursor as usize;
+        let end = start.saturating_add(max_tickets as usize).min(tickets.len());
+        self.ledger_hash = extend_ledger_hash(self.ledger_hash, &tickets[start..end]);
+        self.cursor = end as u64;
+    }
+
+    /// The transcript of the draw once all `ticket_count` tickets are hashed.
+    pub fn transcript(&self, ticket_count: usize) -> DrawTranscript {
+        DrawTranscript::from_ledger_hash(self.draw_slot, self.entropy_slot, self.slot_hash, self.ledger_hash, ticket_count)
+    }
+}
+
+/// Inputs and intermediate values of a draw. `select_winner` builds it with
+/// `DrawTranscript::new` and a verifier holding the round's ticket ledger replays
+/// it with `verify`, so both sides run the same code.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DrawTranscript {
+    pub ledger_hash: [u8; 32],    // Hash of the ticket ledger, in entry order
+    pub draw_slot: u64,           // Target slot fixed when the round was locked
+    pub entropy_slot: u64,        // First slot after the target with a recorded hash
+    pub slot_hash: [u8; 32],      // Hash of that slot
+    pub draw_hash: [u8; 32],      // Hash of the slot hash and the ledger hash
+    pub modulus: u64,             // Number of tickets drawn from
+    pub winning_index: u64,       // Index of the drawn ticket in the ledger
+}
+
+impl DrawTranscript {
+    pub const SIZE: usize = 32 + 8 + 8 + 32 + 32 + 8 + 8;
+
+    /// Draws a ticket from `tickets` with the hash of `entropy_slot`, the first
+    /// slot after `draw_slot` to have one.
+    pub fn new(draw_slot: u64, entropy_slot: u64, slot_hash: [u8; 32], tickets: &[Pubkey]) -> Self {
+        Self::from_ledger_hash(draw_slot, entropy_slot, slot_hash, ledger_hash(tickets), tickets.len())
+    }
+
+    /// Draws from a ledger of `ticket_count` tickets that has already been hashed.
+    pub fn from_ledger_hash(
+        draw_slot: u64,
+        entropy_slot: u64,
+        slot_hash: [u8; 32],
+        ledger_hash: [u8; 32],
+        ticket_count: usize,
+    ) -> Self {
+        let draw_hash = keccak::hashv(&[&slot_hash, &ledger_hash]).to_bytes();
+        Self {
+            ledger_hash,
+            draw_slot,
+            entropy_slot,
+            slot_hash,
+            draw_hash,
+            modulus: ticket_count as u64,
+            winning_index: draw_index(&draw_hash, ticket_count) as u64,
+        }
+    }
+
+    /// Replays the draw against the round's ticket ledger and returns the owner of
+    /// the first ticket picked, failing if any recorded value doesn't follow from
+    /// its inputs. `DrawRecord::verify` follows any redraws to the winner paid.
+    pub fn verify(&self, tickets: &[Pubkey]) -> Result<Pubkey> {
+        require!(!tickets.is_empty(), RaffleError::NoTickets);
+        let replayed = Self::new(self.draw_slot, self.entropy_slot, self.slot_hash, tickets);
+        require!(
+            replayed == *self && self.entropy_slot > self.draw_slot,
+            RaffleError::InvalidDrawTranscript
+        );
+        Ok(tickets[self.winning_index as usize])
+    }
+
+    /// Index of the ticket picked by the `attempt`th draw, the first being
+    /// `winning_index` and each redraw hashing the attempt onto `draw_hash`.
+    pub fn redraw_index(&self, attempt: u64) -> u64 {
+        if attempt == 0 {
+            return self.winning_index;
+        }
+        let redraw_hash = keccak::hashv(&[&self.draw_hash, &attempt.to_le_bytes()]).to_bytes();
+        draw_index(&redraw_hash, self.modulus as usize) as u64
+    }
+
+    /// The redraws needed to pick a ticket from `tickets` that doesn't belong to
+    /// a denied wallet, up to `MAX_REDRAWS`, and the index of that ticket.
+    pub fn eligible_draw(&self, tickets: &[Pubkey], deny_list: &DenyList) -> Result<(u64, u64)> {
+        (0..=MAX_REDRAWS)
+            .map(|attempt| (attempt, self.redraw_index(attempt)))
+            .find(|(_, index)| !deny_list.contains(&tickets[*index as usize]))
+            .ok_or_else(|| error!(RaffleError::NoEligibleWinner))
+    }
+
+    /// Owner of the ticket `eligible_draw` picks.
+    pub fn eligible_winner(&self, tickets: &[Pubkey], deny_list: &DenyList) -> Result<Pubkey> {
+        let (_, index) = self.eligible_draw(tickets, deny_list)?;
+        Ok(tickets[index as usize])
+    }
+}
+
+/// Hash committing to every ticket of a round in ledger order. Each ticket is
+/// chained onto the hash of the ones before it, starting from zeroes.
+pub fn ledger_hash(tickets: &[Pubkey]) -> [u8; 32] {
+    extend_ledger_hash([0; 32], tickets)
+}
+
+/// Chains `tickets` onto the hash of the ledger before them, so a long ledger
+/// can be hashed a chunk at a time.
+pub fn extend_ledger_hash(mut hash: [u8; 32], tickets: &[Pubkey]) -> [u8; 32] {
+    for ticket in tickets {
+        hash = keccak::hashv(&[&hash, ticket.as_ref()]).to_bytes();
+    }
+    hash
+}
+
+/// Finds the first slot after `target` in the raw SlotHashes sysvar data, which
+/// lists up to `SLOT_HASH_WINDOW` recent slots newest first, skipped slots absent.
+/// Fails with `DrawTooSoon` until such a slot exists and with `DrawSlotExpired`
+/// once the target has aged out of the sysvar.
+pub fn slot_hash_after(slot_hashes: &[u8], target: u64) -> Result<(u64, [u8; 32])> {
+    let count = slot_hashes
+        .get(..8)
+        .map(|len| u64::from_le_bytes(len.try_into().unwrap()))
+        .ok_or(RaffleError::InvalidSlotHashes)?;
+    let mut found = None;
+    for entry in slot_hashes[8..].chunks_exact(SLOT_HASH_ENTRY_SIZE).take(count as usize) {
+        let slot = u64::from_le_bytes(entry[..8].try_into().unwrap());
+        if slot <= target {
+            return found.ok_or_else(|| error!(RaffleError::DrawTooSoon));
+        }
+        found = Some((slot, entry[8..].try_into().unwrap()));
+    }
+    err!(RaffleError::DrawSlotExpired)
+}
+
+/// Maps a draw hash onto an index into a ticket ledger of `ticket_count` entries.
+pub fn draw_index(draw_hash: &[u8; 32], ticket_count: usize) -> usize {
+    (u64::from_le_bytes(draw_hash[0..8].try_into().unwrap()) % ticket_count as u64) as usize
+}
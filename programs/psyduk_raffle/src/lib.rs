@@ -1,106 +1,366 @@
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::{program::invoke, system_instruction};
+use anchor_lang::Discriminator;
+use anchor_lang::solana_program::{program::{invoke, invoke_signed}, system_instruction};
 use anchor_lang::solana_program::pubkey::Pubkey;
-use anchor_lang::solana_program::keccak;
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use anchor_spl::associated_token::{self, AssociatedToken, Create};
+use anchor_spl::token_interface::{self, CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked};
 use std::str::FromStr;
 
 // Program ID for Solana
 declare_id!("87JSCiht1TyXmT1yHbYZpKGtgJRhKzBYyFrmENvAogef");
 
+pub mod asset;
+pub mod bonus;
+pub mod cancel;
+pub mod deny;
+pub mod draw;
+pub mod merkle;
+pub mod price;
+pub mod schedule;
+pub mod staking;
+pub mod view;
+pub mod withdrawal;
+pub use asset::*;
+pub use bonus::*;
+pub use cancel::*;
+pub use deny::*;
+pub use draw::*;
+pub use merkle::*;
+pub use price::*;
+pub use schedule::*;
+pub use staking::*;
+pub use view::*;
+pub use withdrawal::*;
+
 // Constants
-const RAFFLE_DURATION: i64 = 15 * 60; // 15 minutes in seconds
-const TICKET_PRICE: u64 = 10_000_000; // 0.01 SOL in lamports
-const RAFFLE_SEED: &[u8] = b"raffle"; // Fixed seed for raffle PDA
-const TREASURY_WALLET: &str = "7fbAEwAuTHgBPxxf6dtvr8opw9tzVxYBVu1gXZNUJsAg"; // Replace with actual treasury wallet
+pub const RAFFLE_DURATION: i64 = 15 * 60; // 15 minutes in seconds
+pub const TICKET_PRICE: u64 = 10_000_000; // 0.01 SOL in lamports
+pub const RAFFLE_SEED: &[u8] = b"raffle"; // Fixed seed for raffle PDA
+pub const VAULT_SEED: &[u8] = b"vault"; // Fixed seed for the vault PDA holding the jackpot
+pub const RESERVE_SEED: &[u8] = b"reserve"; // Fixed seed for the PDA backing the minimum prize
+pub const SUBSCRIPTION_SEED: &[u8] = b"subscription"; // Seed prefix for per-wallet subscription PDAs
+pub const PROMOTION_SEED: &[u8] = b"promotion"; // Seed prefix for per-round free ticket promotions
+pub const CLAIM_SEED: &[u8] = b"claim"; // Seed prefix for free ticket claim receipts
+pub const TREASURY_WALLET: &str = "7fbAEwAuTHgBPxxf6dtvr8opw9tzVxYBVu1gXZNUJsAg"; // Replace with actual treasury wallet
+pub const TREASURY_FEE_PERCENT: u64 = 20; // Share of each purchase sent to the treasury
+pub const RAFFLE_VERSION: u8 = 1; // Current layout version of the Raffle account
+pub const LEGACY_RAFFLE_SPACE: usize = 8 + 32100; // Size of Raffle accounts created before versioning
+pub const RAFFLE_RESERVED_BYTES: usize = 10; // Padding kept free for future Raffle fields
+pub const MAX_TICKETS: usize = 1000; // Maximum number of tickets in a single round
+pub const MAX_TICKETS_PER_INSTRUCTION: usize = MAX_PERMITTED_DATA_INCREASE / 32; // Tickets one instruction can add, the ledger growing at most 10 KiB per instruction
 
 #[program]
 mod raffle {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+    pub fn initialize(ctx: Context<Initialize>, schedule: Schedule) -> Result<()> {
         // Derive the raffle PDA based on the fixed seed
-        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], &ctx.program_id);
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
 
         // Ensure that the raffle PDA doesn't already exist
         let raffle_account = &mut ctx.accounts.raffle;
         require!(raffle_account.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
 
+        // Fund the vault up to its rent-exempt minimum so payouts never leave it rent-paying
+        let vault_rent = Rent::get()?.minimum_balance(0);
+        let vault_shortfall = vault_rent.saturating_sub(ctx.accounts.vault.lamports());
+        if vault_shortfall > 0 {
+            invoke(
+                &system_instruction::transfer(
+                    &ctx.accounts.admin.key(),
+                    &ctx.accounts.vault.key(),
+                    vault_shortfall,
+                ),
+                &[
+                    ctx.accounts.admin.to_account_info(),
+                    ctx.accounts.vault.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+
+        raffle_account.version = RAFFLE_VERSION;
+        raffle_account.admin = ctx.accounts.admin.key();
         raffle_account.jackpot = 0;
-        raffle_account.start_time = Clock::get()?.unix_timestamp;
-        raffle_account.end_time = raffle_account.start_time + RAFFLE_DURATION;
+        raffle_account.unpaid_fees = 0;
         raffle_account.tickets = vec![];
 
+        // Open the first round according to the schedule
+        let current_time = Clock::get()?.unix_timestamp;
+        schedule.validate(current_time)?;
+        raffle_account.schedule = schedule;
+        raffle_account.open_next_round(current_time)?;
+
         msg!("Raffle initialized at PDA: {}", raffle_pda);
 
         Ok(())
     }
 
-    pub fn buy_ticket(ctx: Context<BuyTicket>, amount: u64) -> Result<()> {
+    /// Buys tickets paid for by `buyer`. They, and any winnings, belong to
+    /// `beneficiary` when one is given and to the buyer otherwise.
+    pub fn buy_ticket(ctx: Context<BuyTicket>, amount: u64, beneficiary: Option<Pubkey>) -> Result<()> {
         // Derive the raffle PDA again using the fixed seed
-        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], &ctx.program_id);
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
     
         // Ensure the raffle PDA is correct
         let raffle = &mut ctx.accounts.raffle;
         require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+
+        raffle.require_open_round(Clock::get()?.unix_timestamp)?;
+
+        // Denied wallets can neither buy tickets nor be given them
+        let deny_list = load_deny_list(&ctx.accounts.deny_list, ctx.program_id)?;
+        let owner = beneficiary.unwrap_or(ctx.accounts.buyer.key());
+        require!(
+            !deny_list.contains(&ctx.accounts.buyer.key()) && !deny_list.contains(&owner),
+            RaffleError::WalletDenied
+        );
+
+        // Ensure the treasury account is the configured treasury wallet
+        let treasury_wallet = Pubkey::from_str(TREASURY_WALLET).unwrap();  // Replace with actual treasury wallet
+        require!(ctx.accounts.treasury.key() == treasury_wallet, RaffleError::InvalidTreasuryWallet);
     
+        // Tickets cost TICKET_PRICE unless priced in USD through the configured feed
+        let ticket_price = match load_price_config(&ctx.accounts.price_config, ctx.program_id)? {
+            Some(config) if config.ticket_price_cents > 0 => {
+                let price_feed = ctx.accounts.price_feed.as_ref().ok_or(RaffleError::InvalidPriceFeed)?;
+                require!(
+                    price_feed.key() == config.price_feed && *price_feed.owner == pyth_receiver_program(),
+                    RaffleError::InvalidPriceFeed
+                );
+                let feed = PriceFeed::read(&price_feed.try_borrow_data()?)?;
+                config.ticket_price(&feed, Clock::get()?.unix_timestamp)?
+            }
+            _ => TICKET_PRICE,
+        };
+
         // Ensure the user sent enough for at least one ticket
-        require!(amount >= TICKET_PRICE, RaffleError::InsufficientFunds);
+        require!(amount >= ticket_price, RaffleError::InsufficientFunds);
+    
+        // Calculate number of tickets and remaining amount. Only the cost of whole
+        // tickets is ever transferred, so the leftover stays with the buyer.
+        let tickets_bought = amount / ticket_price;
+        let leftover = amount % ticket_price;
+        let cost = tickets_bought
+            .checked_mul(ticket_price)
+            .ok_or(RaffleError::ArithmeticOverflow)?;
+        // Asset prize rounds pay the prize's creator instead of growing the jackpot
+        let (jackpot_increment, creator_share, treasury_cut) = if raffle.asset_round {
+            let asset_prize = ctx.accounts.asset_prize.as_ref().ok_or(RaffleError::InvalidAssetPrize)?;
+            require!(asset_prize.deposited, RaffleError::PrizeNotDeposited);
+            let creator = ctx.accounts.creator.as_ref().ok_or(RaffleError::InvalidCreatorAccount)?;
+            require!(creator.key() == asset_prize.creator, RaffleError::InvalidCreatorAccount);
+            let (creator_share, treasury_cut) = split_payment(cost)?;
+            (0, creator_share, treasury_cut)
+        } else {
+            let (jackpot_increment, treasury_cut) = raffle.split_entry(cost)?;
+            (jackpot_increment, 0, treasury_cut)
+        };
+
+        // PSYDUK holders earn bonus tickets on top of the ones paid for
+        let bonus = match &ctx.accounts.holder_token_account {
+            Some(token_account) => {
+                require!(token_account.owner == ctx.accounts.buyer.key(), RaffleError::InvalidHolderTokenAccount);
+                require!(token_account.mint == psyduk_mint(), RaffleError::InvalidHolderTokenAccount);
+                bonus_tickets(&raffle.bonus_tiers, token_account.amount, tickets_bought)
+            }
+            None => 0,
+        };
+        require!(
+            tickets_bought + bonus <= MAX_TICKETS_PER_INSTRUCTION as u64,
+            RaffleError::TooManyTicketsPerInstruction
+        );
+        let ticket_count = raffle.tickets.len() + (tickets_bought + bonus) as usize;
+        require!(ticket_count <= MAX_TICKETS, RaffleError::RaffleFull);
+
+        // Fees held for withdrawal go into the vault with the jackpot share
+        let pushed_fee = raffle.hold_fee(treasury_cut)?;
+        let held_fee = treasury_cut - pushed_fee;
     
-        // Calculate number of tickets and remaining amount
-        let tickets_bought = amount / TICKET_PRICE;
-        let leftover = amount % TICKET_PRICE;
+        // Move the jackpot share into the vault
+        invoke(
+            &system_instruction::transfer(
+                &ctx.accounts.buyer.key(),
+                &ctx.accounts.vault.key(),
+                jackpot_increment + held_fee,
+            ),
+            &[
+                ctx.accounts.buyer.to_account_info(),
+                ctx.accounts.vault.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+        raffle.jackpot = raffle
+            .jackpot
+            .checked_add(jackpot_increment)
+            .ok_or(RaffleError::ArithmeticOverflow)?;
     
-        // Refund leftover lamports if any
-        if leftover > 0 {
+        // Transfer the creator's share of an asset prize round
+        if let Some(creator) = ctx.accounts.creator.as_ref().filter(|_| creator_share > 0) {
             invoke(
                 &system_instruction::transfer(
                     &ctx.accounts.buyer.key(),
-                    &ctx.accounts.buyer.key(),
-                    leftover,
+                    &creator.key(),
+                    creator_share,
                 ),
                 &[
                     ctx.accounts.buyer.to_account_info(),
+                    creator.to_account_info(),
                     ctx.accounts.system_program.to_account_info(),
                 ],
             )?;
         }
-    
-        // Add 80% of the payment to the jackpot
-        let jackpot_increment = (amount * 80) / 100;
-        raffle.jackpot += jackpot_increment;
-    
-        // Transfer 20% to the treasury
-        let treasury_cut = (amount * 20) / 100;
-        let treasury_wallet = Pubkey::from_str(TREASURY_WALLET).unwrap();  // Replace with actual treasury wallet
+
+        // Transfer the fee to the treasury
         invoke(
             &system_instruction::transfer(
                 &ctx.accounts.buyer.key(),
                 &treasury_wallet,
-                treasury_cut,
+                pushed_fee,
             ),
             &[
                 ctx.accounts.buyer.to_account_info(),
+                ctx.accounts.treasury.to_account_info(),
                 ctx.accounts.system_program.to_account_info(),
             ],
         )?;
     
+        // Grow the raffle account to fit the new tickets, the buyer covering the extra rent
+        grow_raffle(
+            &raffle.to_account_info(),
+            &ctx.accounts.buyer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            ticket_count,
+        )?;
+
         // Store tickets in the raffle state
-        for _ in 0..tickets_bought {
-            raffle.tickets.push(ctx.accounts.buyer.key());
+        for _ in 0..tickets_bought + bonus {
+            raffle.tickets.push(owner);
+        }
+        raffle.bonus_tickets = raffle
+            .bonus_tickets
+            .checked_add(bonus)
+            .ok_or(RaffleError::ArithmeticOverflow)?;
+        raffle.last_entry_slot = Clock::get()?.slot;
+
+        // Only tickets the buyer keeps can be cancelled later, so only they need
+        // a receipt, and asset round tickets are final
+        if owner == ctx.accounts.buyer.key() && !raffle.asset_round {
+            let receipt = ctx.accounts.receipt.as_mut().ok_or(RaffleError::MissingReceipt)?;
+            let payment = Payment { cost, jackpot: jackpot_increment, held_fee };
+            receipt.record(owner, raffle.end_time, tickets_bought, bonus, payment)?;
         }
     
-        msg!("{} tickets bought by {}", tickets_bought, ctx.accounts.buyer.key());
+        msg!(
+            "{} tickets (+{} bonus) bought by {} for {} ({} lamports not spent)",
+            tickets_bought,
+            bonus,
+            ctx.accounts.buyer.key(),
+            owner,
+            leftover
+        );
+        emit!(TicketsPurchased {
+            payer: ctx.accounts.buyer.key(),
+            beneficiary: owner,
+            tickets: tickets_bought,
+            bonus_tickets: bonus,
+            cost,
+            round_end_time: raffle.end_time,
+        });
     
         Ok(())
     }
     
+    /// Hands back `count` of the tickets the buyer bought for itself this round,
+    /// before sales close. The buyer is refunded what those tickets cost less the
+    /// cancellation penalty, which stays in the jackpot, and forfeits the bonus
+    /// tickets they earned. Fees already paid to the treasury can't be returned
+    /// and count towards the penalty; fees held in the vault are refunded. The wallet's latest tickets leave
+    /// the ledger, every other entry keeping its order. Asset prize rounds have
+    /// already paid their revenue to the creator, so their tickets are final.
+    pub fn cancel_tickets(ctx: Context<CancelTickets>, count: u64) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+        raffle.require_open_round(Clock::get()?.unix_timestamp)?;
+        require!(!raffle.asset_round, RaffleError::AssetTicketsNotCancellable);
+
+        let buyer = ctx.accounts.buyer.key();
+        let cancellation = ctx.accounts.receipt.cancel(
+            raffle.end_time,
+            count,
+            raffle.cancel_penalty_percent,
+            raffle.unpaid_fees,
+        )?;
+        raffle.remove_tickets(&buyer, cancellation.tickets + cancellation.bonus_tickets)?;
+        raffle.bonus_tickets = raffle
+            .bonus_tickets
+            .checked_sub(cancellation.bonus_tickets)
+            .ok_or(RaffleError::ArithmeticOverflow)?;
+        // The refund comes out of the released fees first, then the jackpot
+        raffle.unpaid_fees = raffle
+            .unpaid_fees
+            .checked_sub(cancellation.released_fee)
+            .ok_or(RaffleError::ArithmeticOverflow)?;
+        raffle.jackpot = raffle
+            .jackpot
+            .checked_add(cancellation.released_fee)
+            .and_then(|jackpot| jackpot.checked_sub(cancellation.refund))
+            .ok_or(RaffleError::ArithmeticOverflow)?;
+        raffle.last_entry_slot = Clock::get()?.slot;
+
+        let vault_bump = [ctx.bumps.vault];
+        invoke_signed(
+            &system_instruction::transfer(
+                &ctx.accounts.vault.key(),
+                &buyer,
+                cancellation.refund,
+            ),
+            &[
+                ctx.accounts.vault.to_account_info(),
+                ctx.accounts.buyer.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[&[VAULT_SEED, &vault_bump]],
+        )?;
+
+        msg!(
+            "{} cancelled {} tickets (-{} bonus), refunded {} lamports ({} penalty, {} fee retained)",
+            buyer,
+            cancellation.tickets,
+            cancellation.bonus_tickets,
+            cancellation.refund,
+            cancellation.penalty,
+            cancellation.fee_retained
+        );
+        emit!(TicketsCancelled {
+            wallet: buyer,
+            tickets: cancellation.tickets,
+            bonus_tickets: cancellation.bonus_tickets,
+            refund: cancellation.refund,
+            penalty: cancellation.penalty,
+            fee_retained: cancellation.fee_retained,
+            round_end_time: raffle.end_time,
+        });
+
+        Ok(())
+    }
+
+    /// Draws the round in a single transaction, hashing the whole ledger. Rounds
+    /// too large for one transaction's compute budget are drawn with
+    /// `begin_draw`, `advance_draw` and `finalize_draw` instead.
     pub fn select_winner(ctx: Context<SelectWinner>) -> Result<()> {
         // Derive the raffle PDA again using the fixed seed
-        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], &ctx.program_id);
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
 
         // Ensure the raffle PDA is correct
         let raffle = &mut ctx.accounts.raffle;
         require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
 
         // Ensure raffle has ended
         let current_time = Clock::get()?.unix_timestamp;
@@ -109,93 +369,2329 @@ mod raffle {
         // Ensure there are tickets
         require!(!raffle.tickets.is_empty(), RaffleError::NoTickets);
 
-        // Select random winner using Keccak256 for better randomness
-        let seed = Clock::get()?.unix_timestamp.to_le_bytes();
-        let hash = keccak::hash(&seed).to_bytes();
-        let winner_index = (u64::from_le_bytes(hash[0..8].try_into().unwrap()) as usize) % raffle.tickets.len();
-        let winner = raffle.tickets[winner_index];
+        // Only a locked round can be drawn, and a draw already begun in batches
+        // must be finished that way
+        raffle.transition(RoundStatus::Drawing)?;
+
+        // Draw from the hash of the first slot after the target fixed at lock time
+        let (entropy_slot, slot_hash) =
+            slot_hash_after(&ctx.accounts.slot_hashes.try_borrow_data()?, raffle.draw_slot)?;
+        let transcript = DrawTranscript::new(raffle.draw_slot, entropy_slot, slot_hash, &raffle.tickets);
+        raffle.transition(RoundStatus::PayingOut)?;
+
+        settle_draw(ctx, transcript)
+    }
+
+    /// Starts a draw spread over several transactions by fixing its entropy, the
+    /// hash of the first slot after the lock target. From then on the round is
+    /// drawn from that hash however long the rest of the draw takes.
+    pub fn begin_draw(ctx: Context<BeginDraw>) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+        require!(Clock::get()?.unix_timestamp >= raffle.end_time, RaffleError::RaffleStillActive);
+        require!(!raffle.tickets.is_empty(), RaffleError::NoTickets);
+        raffle.transition(RoundStatus::Drawing)?;
+
+        // The progress account is reused from round to round
+        let progress = &mut ctx.accounts.draw_progress;
+        let (entropy_slot, slot_hash) =
+            slot_hash_after(&ctx.accounts.slot_hashes.try_borrow_data()?, raffle.draw_slot)?;
+
+        progress.round_end_time = raffle.end_time;
+        progress.draw_slot = raffle.draw_slot;
+        progress.entropy_slot = entropy_slot;
+        progress.slot_hash = slot_hash;
+        progress.cursor = 0;
+        progress.ledger_hash = [0; 32];
+
+        msg!("Draw begun from the hash of slot {}", entropy_slot);
+
+        Ok(())
+    }
+
+    /// Hashes up to `max_tickets` more tickets of the ledger into the draw begun
+    /// by `begin_draw`. Anyone can call it until the whole ledger is hashed.
+    pub fn advance_draw(ctx: Context<AdvanceDraw>, max_tickets: u64) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+        require!(max_tickets > 0, RaffleError::IncorrectAmount);
+        raffle.status.check_transition(RoundStatus::PayingOut)?;
+
+        let progress = &mut ctx.accounts.draw_progress;
+        require!(progress.round_end_time == raffle.end_time, RaffleError::DrawNotStarted);
+        progress.advance(&raffle.tickets, max_tickets);
+        if progress.cursor as usize == raffle.tickets.len() {
+            raffle.transition(RoundStatus::PayingOut)?;
+        }
+
+        msg!("Draw hashed {} of {} tickets", progress.cursor, raffle.tickets.len());
+
+        Ok(())
+    }
+
+    /// Pays out a draw whose ledger `advance_draw` has fully hashed, exactly as
+    /// `select_winner` would, and closes the progress account to `payer`.
+    pub fn finalize_draw(ctx: Context<SelectWinner>) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+        raffle.status.check_transition(RoundStatus::Open)?;
+
+        let progress = load_draw_progress(&ctx.accounts.draw_progress, ctx.program_id)?
+            .filter(|progress| progress.round_end_time == raffle.end_time)
+            .ok_or(RaffleError::DrawNotStarted)?;
+        let transcript = progress.transcript(raffle.tickets.len());
+        close_account(&ctx.accounts.draw_progress, &ctx.accounts.payer.to_account_info())?;
+
+        settle_draw(ctx, transcript)
+    }
+
+    /// Fixes the slot whose successor's hash will draw the round, once sales have
+    /// closed. The target is the current slot, or later if the last entry was too
+    /// recent, so the entropy comes from blocks produced after every entry. A lock
+    /// whose target has aged out of the SlotHashes sysvar can be renewed.
+    pub fn lock_round(ctx: Context<LockRound>) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+
+        let clock = Clock::get()?;
+        require!(
+            clock.unix_timestamp >= raffle.end_time.saturating_sub(raffle.sales_cutoff),
+            RaffleError::RaffleStillActive
+        );
+        require!(!raffle.tickets.is_empty(), RaffleError::NoTickets);
+        raffle.transition(RoundStatus::SalesClosed)?;
+        require!(
+            raffle.draw_slot == 0 || clock.slot > raffle.draw_slot.saturating_add(SLOT_HASH_WINDOW),
+            RaffleError::RoundAlreadyLocked
+        );
+
+        let earliest = raffle
+            .last_entry_slot
+            .checked_add(raffle.min_draw_slots)
+            .ok_or(RaffleError::ArithmeticOverflow)?;
+        raffle.draw_slot = clock.slot.max(earliest);
+
+        msg!("Round locked, drawing from the first slot after {}", raffle.draw_slot);
+
+        Ok(())
+    }
+
+    /// Permissionless check that the vault holds every lamport the books say it should.
+    pub fn reconcile(ctx: Context<Reconcile>) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+
+        let vault = ctx.accounts.vault.to_account_info();
+        let rent_exempt_minimum = Rent::get()?.minimum_balance(vault.data_len());
+        let required = raffle
+            .jackpot
+            .checked_add(raffle.unpaid_fees)
+            .and_then(|total| total.checked_add(rent_exempt_minimum))
+            .ok_or(RaffleError::ArithmeticOverflow)?;
+        let balance = vault.lamports();
+
+        if balance < required {
+            msg!(
+                "Vault imbalance: holds {} lamports, owes {} (jackpot {}, unpaid fees {}, rent {})",
+                balance,
+                required,
+                raffle.jackpot,
+                raffle.unpaid_fees,
+                rent_exempt_minimum
+            );
+            return err!(RaffleError::VaultImbalance);
+        }
+
+        msg!("Vault reconciled: {} lamports surplus", balance - required);
+
+        Ok(())
+    }
+
+    /// Returns the state of the current round. Like the other `get_` instructions it
+    /// only reads, so clients can run it through `simulateTransaction` and decode
+    /// the return data instead of the raffle account.
+    pub fn get_round_summary(ctx: Context<ViewRound>) -> Result<RoundSummary> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+
+        RoundSummary::of(raffle)
+    }
+
+    /// Returns the number of tickets `wallet` holds in the current round.
+    pub fn get_tickets_of(ctx: Context<ViewRound>, wallet: Pubkey) -> Result<u64> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+
+        Ok(tickets_of(raffle, &wallet))
+    }
+
+    /// Returns the odds of `wallet` winning the current round.
+    pub fn get_win_probability(ctx: Context<ViewRound>, wallet: Pubkey) -> Result<WinProbability> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+
+        Ok(WinProbability::of(raffle, &wallet))
+    }
+
+    /// Escrows payment for `tickets_per_round` tickets in each of the next `rounds`
+    /// rounds. The tickets are entered round by round through `apply_subscriptions`.
+    /// Subscriptions are prepaid at `TICKET_PRICE`, so none can be taken out while
+    /// tickets are priced in USD; earlier ones run on at the price they prepaid.
+    pub fn subscribe(ctx: Context<Subscribe>, tickets_per_round: u64, rounds: u64) -> Result<()> {
+        require!(tickets_per_round > 0 && rounds > 0, RaffleError::IncorrectAmount);
+        require!(
+            tickets_per_round as usize <= MAX_TICKETS_PER_INSTRUCTION,
+            RaffleError::TooManyTicketsPerInstruction
+        );
+        if let Some(config) = load_price_config(&ctx.accounts.price_config, ctx.program_id)? {
+            require!(config.ticket_price_cents == 0, RaffleError::SubscriptionsUnavailable);
+        }
 
-        // Transfer jackpot to winner
+        let escrow = tickets_per_round
+            .checked_mul(rounds)
+            .and_then(|tickets| tickets.checked_mul(TICKET_PRICE))
+            .ok_or(RaffleError::ArithmeticOverflow)?;
         invoke(
             &system_instruction::transfer(
-                &ctx.accounts.program.key(),
-                &winner,
-                raffle.jackpot,
+                &ctx.accounts.subscriber.key(),
+                &ctx.accounts.subscription.key(),
+                escrow,
+            ),
+            &[
+                ctx.accounts.subscriber.to_account_info(),
+                ctx.accounts.subscription.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.subscriber = ctx.accounts.subscriber.key();
+        subscription.tickets_per_round = tickets_per_round;
+        subscription.rounds_remaining = rounds;
+        subscription.last_round_end = 0;
+
+        msg!(
+            "{} subscribed to {} tickets for {} rounds ({} lamports escrowed)",
+            subscription.subscriber,
+            tickets_per_round,
+            rounds,
+            escrow
+        );
+
+        Ok(())
+    }
+
+    /// Enters every subscription passed in the remaining accounts into the open
+    /// round, paying for the tickets out of each subscription's escrow. Subscriptions
+    /// that are used up, already entered this round or don't fit in the round or in
+    /// this instruction's ledger growth are skipped, so the crank can pass its whole
+    /// list every round and retry the skipped ones in another transaction.
+    pub fn apply_subscriptions(ctx: Context<ApplySubscriptions>) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+        raffle.require_open_round(Clock::get()?.unix_timestamp)?;
+
+        // Subscriptions pay into the jackpot, so they sit out asset prize rounds
+        if raffle.asset_round {
+            msg!("Subscriptions are not entered into asset prize rounds");
+            return Ok(());
+        }
+
+        let treasury_wallet = Pubkey::from_str(TREASURY_WALLET).unwrap();
+        require!(ctx.accounts.treasury.key() == treasury_wallet, RaffleError::InvalidTreasuryWallet);
+
+        let vault_info = ctx.accounts.vault.to_account_info();
+        let treasury_info = ctx.accounts.treasury.to_account_info();
+        let starting_len = raffle.tickets.len();
+        let mut entered = 0;
+        for subscription_info in ctx.remaining_accounts {
+            require!(subscription_info.owner == ctx.program_id, RaffleError::InvalidSubscriptionAccount);
+            require!(subscription_info.is_writable, RaffleError::InvalidSubscriptionAccount);
+            let mut subscription = Subscription::try_deserialize(&mut &subscription_info.try_borrow_data()?[..])?;
+
+            if subscription.rounds_remaining == 0 || subscription.last_round_end == raffle.end_time {
+                continue;
+            }
+            let ticket_count = raffle.tickets.len() + subscription.tickets_per_round as usize;
+            if ticket_count > MAX_TICKETS {
+                msg!("No room for subscription {} this round", subscription_info.key());
+                continue;
+            }
+            if ticket_count - starting_len > MAX_TICKETS_PER_INSTRUCTION {
+                msg!("No room for subscription {} in this instruction", subscription_info.key());
+                continue;
+            }
+
+            // The escrow is owned by this program, so the payment moves without a CPI
+            let cost = subscription
+                .tickets_per_round
+                .checked_mul(TICKET_PRICE)
+                .ok_or(RaffleError::ArithmeticOverflow)?;
+            let (jackpot_increment, treasury_cut) = raffle.split_entry(cost)?;
+            let pushed_fee = raffle.hold_fee(treasury_cut)?;
+            move_lamports(subscription_info, &vault_info, jackpot_increment + treasury_cut - pushed_fee)?;
+            move_lamports(subscription_info, &treasury_info, pushed_fee)?;
+            raffle.jackpot = raffle
+                .jackpot
+                .checked_add(jackpot_increment)
+                .ok_or(RaffleError::ArithmeticOverflow)?;
+
+            for _ in 0..subscription.tickets_per_round {
+                raffle.tickets.push(subscription.subscriber);
+            }
+            subscription.rounds_remaining -= 1;
+            subscription.last_round_end = raffle.end_time;
+            subscription.try_serialize(&mut &mut subscription_info.try_borrow_mut_data()?[..])?;
+
+            emit!(TicketsPurchased {
+                payer: subscription.subscriber,
+                beneficiary: subscription.subscriber,
+                tickets: subscription.tickets_per_round,
+                bonus_tickets: 0,
+                cost,
+                round_end_time: raffle.end_time,
+            });
+            entered += 1;
+        }
+
+        if entered > 0 {
+            raffle.last_entry_slot = Clock::get()?.slot;
+        }
+
+        // Grow the raffle account to fit the new tickets, the crank covering the extra rent
+        let ticket_count = raffle.tickets.len();
+        grow_raffle(
+            &raffle.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            ticket_count,
+        )?;
+
+        msg!("{} subscriptions entered into the round ending at {}", entered, raffle.end_time);
+
+        Ok(())
+    }
+
+    /// Closes the subscription, refunding the escrow of every round not yet entered
+    /// together with the account's rent.
+    pub fn unsubscribe(ctx: Context<Unsubscribe>) -> Result<()> {
+        msg!(
+            "{} unsubscribed with {} rounds unused",
+            ctx.accounts.subscriber.key(),
+            ctx.accounts.subscription.rounds_remaining
+        );
+
+        Ok(())
+    }
+
+    /// Publishes the Merkle root of the (wallet, ticket_count) grants of free
+    /// tickets for the round ending at `round_end_time`.
+    pub fn publish_free_tickets(ctx: Context<PublishFreeTickets>, round_end_time: i64, merkle_root: [u8; 32]) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+        require!(ctx.accounts.raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(round_end_time > Clock::get()?.unix_timestamp, RaffleError::InvalidPromotion);
+
+        let promotion = &mut ctx.accounts.promotion;
+        promotion.round_end_time = round_end_time;
+        promotion.merkle_root = merkle_root;
+
+        msg!("Free tickets published for the round ending at {}", round_end_time);
+
+        Ok(())
+    }
+
+    /// Enters the free tickets granted to the claimant by the current round's
+    /// promotion. The claim receipt PDA makes every grant claimable once.
+    pub fn claim_free_tickets(ctx: Context<ClaimFreeTickets>, ticket_count: u64, proof: Vec<[u8; 32]>) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+        raffle.require_open_round(Clock::get()?.unix_timestamp)?;
+
+        // The promotion must be for this round and grant exactly these tickets
+        let promotion = &ctx.accounts.promotion;
+        require!(promotion.round_end_time == raffle.end_time, RaffleError::InvalidPromotion);
+        let claimant = ctx.accounts.claimant.key();
+        let leaf = free_ticket_leaf(&claimant, ticket_count);
+        require!(verify_merkle_proof(&promotion.merkle_root, leaf, &proof), RaffleError::InvalidMerkleProof);
+
+        require!(
+            ticket_count <= MAX_TICKETS_PER_INSTRUCTION as u64,
+            RaffleError::TooManyTicketsPerInstruction
+        );
+        let ticket_count_after = raffle.tickets.len() + ticket_count as usize;
+        require!(ticket_count_after <= MAX_TICKETS, RaffleError::RaffleFull);
+        grow_raffle(
+            &raffle.to_account_info(),
+            &ctx.accounts.claimant.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            ticket_count_after,
+        )?;
+        for _ in 0..ticket_count {
+            raffle.tickets.push(claimant);
+        }
+        raffle.last_entry_slot = Clock::get()?.slot;
+
+        let claim = &mut ctx.accounts.claim;
+        claim.wallet = claimant;
+        claim.tickets = ticket_count;
+
+        msg!("{} claimed {} free tickets", claimant, ticket_count);
+        emit!(FreeTicketsClaimed {
+            wallet: claimant,
+            tickets: ticket_count,
+            round_end_time: raffle.end_time,
+        });
+
+        Ok(())
+    }
+
+    /// Opens PSYDUK staking: creates the stake pool and the vault holding staked tokens.
+    pub fn initialize_staking(ctx: Context<InitializeStaking>, tokens_per_ticket: u64, max_tickets_per_stake: u64) -> Result<()> {
+        require!(tokens_per_ticket > 0, RaffleError::IncorrectAmount);
+        require!(
+            max_tickets_per_stake as usize <= MAX_TICKETS_PER_INSTRUCTION,
+            RaffleError::TooManyTicketsPerInstruction
+        );
+
+        let pool = &mut ctx.accounts.stake_pool;
+        pool.mint = ctx.accounts.mint.key();
+        pool.tokens_per_ticket = tokens_per_ticket;
+        pool.max_tickets_per_stake = max_tickets_per_stake;
+        pool.total_staked = 0;
+        pool.bump = ctx.bumps.stake_pool;
+
+        msg!("Staking opened: {} tokens per ticket, at most {} tickets per stake", tokens_per_ticket, max_tickets_per_stake);
+
+        Ok(())
+    }
+
+    /// Locks `amount` PSYDUK into the stake vault for at least `lock_duration`
+    /// seconds. Staking again adds to the position and can only extend its lock.
+    pub fn stake(ctx: Context<Stake>, amount: u64, lock_duration: i64) -> Result<()> {
+        require!(amount > 0, RaffleError::IncorrectAmount);
+        require!((0..=MAX_STAKE_LOCK).contains(&lock_duration), RaffleError::InvalidLockDuration);
+        let position = &mut ctx.accounts.stake_position;
+        require!(position.is_active(), RaffleError::StakeCoolingDown);
+
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.owner_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.stake_vault.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
             ),
-            &[ctx.accounts.program.to_account_info()],
+            amount,
+            ctx.accounts.mint.decimals,
         )?;
 
-        // Reset raffle
-        raffle.jackpot = 0;
-        raffle.start_time = current_time + 10; // 10 seconds to next raffle
-        raffle.end_time = raffle.start_time + RAFFLE_DURATION;
-        raffle.tickets = vec![];
+        let current_time = Clock::get()?.unix_timestamp;
+        let locked_until = current_time
+            .checked_add(lock_duration)
+            .ok_or(RaffleError::ArithmeticOverflow)?;
+        position.owner = ctx.accounts.owner.key();
+        position.amount = position
+            .amount
+            .checked_add(amount)
+            .ok_or(RaffleError::ArithmeticOverflow)?;
+        position.lock_duration = position.lock_duration.max(lock_duration);
+        position.locked_until = position.locked_until.max(locked_until);
+
+        let pool = &mut ctx.accounts.stake_pool;
+        pool.total_staked = pool
+            .total_staked
+            .checked_add(amount)
+            .ok_or(RaffleError::ArithmeticOverflow)?;
 
-        msg!("Winner selected: {}", winner);
+        msg!(
+            "{} staked {} tokens, locked until {}",
+            position.owner,
+            position.amount,
+            position.locked_until
+        );
 
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(init, payer = admin, space = 8 + 32100)] // Space includes discriminator
-    pub raffle: Account<'info, Raffle>,               // PDA for Raffle
-    #[account(mut)]
-    pub admin: Signer<'info>,                         // Admin initializing the raffle
-    pub system_program: Program<'info, System>,       // System program for account creation
-}
+    /// Starts the unstake cooldown once the lock has passed. The position stops
+    /// earning tickets straight away.
+    pub fn request_unstake(ctx: Context<RequestUnstake>) -> Result<()> {
+        let position = &mut ctx.accounts.stake_position;
+        require!(position.is_active(), RaffleError::StakeCoolingDown);
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time >= position.locked_until, RaffleError::StakeLocked);
 
-#[derive(Accounts)]
-pub struct BuyTicket<'info> {
-    #[account(mut)]
-    pub buyer: Signer<'info>,                         // Ticket buyer
-    #[account(mut)]
-    pub raffle: Account<'info, Raffle>,               // Raffle account storing ticket details
-    #[account(mut)]
-    pub program: AccountInfo<'info>,                  // PDA for program fees
-    pub system_program: Program<'info, System>,       // System program for funds transfer
-}
+        position.cooldown_ends = current_time
+            .checked_add(UNSTAKE_COOLDOWN)
+            .ok_or(RaffleError::ArithmeticOverflow)?;
 
-#[derive(Accounts)]
-pub struct SelectWinner<'info> {
-    #[account(mut)]
-    pub raffle: Account<'info, Raffle>,               // Raffle account for winner selection
-    #[account(mut)]
-    pub program: AccountInfo<'info>,                  // PDA holding funds
-    pub system_program: Program<'info, System>,       // System program for winner payment
-}
+        msg!("{} can unstake from {}", position.owner, position.cooldown_ends);
 
+        Ok(())
+    }
 
-#[account]
-pub struct Raffle {
-    pub jackpot: u64,          // Total prize pool
-    pub start_time: i64,       // Raffle start timestamp
-    pub end_time: i64,         // Raffle end timestamp
-    pub tickets: Vec<Pubkey>,  // List of participants' public keys
-}
+    /// Returns the staked tokens after the cooldown and closes the position.
+    pub fn unstake(ctx: Context<Unstake>) -> Result<()> {
+        let position = &ctx.accounts.stake_position;
+        require!(!position.is_active(), RaffleError::StakeLocked);
+        require!(Clock::get()?.unix_timestamp >= position.cooldown_ends, RaffleError::StakeCoolingDown);
 
+        let pool_seeds: &[&[u8]] = &[STAKE_POOL_SEED, &[ctx.accounts.stake_pool.bump]];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.stake_vault.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.owner_token_account.to_account_info(),
+                    authority: ctx.accounts.stake_pool.to_account_info(),
+                },
+                &[pool_seeds],
+            ),
+            position.amount,
+            ctx.accounts.mint.decimals,
+        )?;
 
-#[error_code]
-pub enum RaffleError {
-    #[msg("The amount sent is insufficient to buy a ticket.")]
-    InsufficientFunds,
+        let pool = &mut ctx.accounts.stake_pool;
+        pool.total_staked = pool
+            .total_staked
+            .checked_sub(position.amount)
+            .ok_or(RaffleError::ArithmeticOverflow)?;
 
-    #[msg("The amount sent does not match the ticket price.")]
-    IncorrectAmount,
+        msg!("{} unstaked {} tokens", position.owner, position.amount);
 
-    #[msg("The provided treasury wallet is invalid.")]
-    InvalidTreasuryWallet,
+        Ok(())
+    }
 
-    #[msg("Failed to transfer funds.")]
-    TransferFailed,
+    /// Enters the free tickets earned by every stake position passed in the
+    /// remaining accounts into the open round. Positions that are unstaking,
+    /// already entered this round, earn nothing or don't fit in the round or in
+    /// this instruction's ledger growth are skipped, to be entered by another
+    /// transaction.
+    pub fn allocate_stake_tickets(ctx: Context<AllocateStakeTickets>) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
 
-    #[msg("The raffle is still active. Cannot end the raffle before the end time.")]
-    RaffleStillActive,
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+        let current_time = Clock::get()?.unix_timestamp;
+        raffle.require_open_round(current_time)?;
 
-    #[msg("No tickets have been purchased for this raffle.")]
-    NoTickets,
+        let pool = &ctx.accounts.stake_pool;
+        let starting_len = raffle.tickets.len();
+        let mut entered = 0;
+        for position_info in ctx.remaining_accounts {
+            require!(position_info.owner == ctx.program_id, RaffleError::InvalidStakeAccount);
+            require!(position_info.is_writable, RaffleError::InvalidStakeAccount);
+            let mut position = StakePosition::try_deserialize(&mut &position_info.try_borrow_data()?[..])?;
 
-    #[msg("Invalid raffle account.")]
-    InvalidRaffleAccount,
+            let tickets = pool.tickets_for(&position, current_time);
+            if !position.is_active() || position.last_round_end == raffle.end_time || tickets == 0 {
+                continue;
+            }
+            let ticket_count = raffle.tickets.len() + tickets as usize;
+            if ticket_count > MAX_TICKETS {
+                msg!("No room for stake {} this round", position_info.key());
+                continue;
+            }
+            if ticket_count - starting_len > MAX_TICKETS_PER_INSTRUCTION {
+                msg!("No room for stake {} in this instruction", position_info.key());
+                continue;
+            }
+
+            for _ in 0..tickets {
+                raffle.tickets.push(position.owner);
+            }
+            position.last_round_end = raffle.end_time;
+            position.try_serialize(&mut &mut position_info.try_borrow_mut_data()?[..])?;
+
+            emit!(StakeTicketsAllocated {
+                wallet: position.owner,
+                tickets,
+                round_end_time: raffle.end_time,
+            });
+            entered += 1;
+        }
+
+        if entered > 0 {
+            raffle.last_entry_slot = Clock::get()?.slot;
+        }
+
+        // Grow the raffle account to fit the new tickets, the crank covering the extra rent
+        let ticket_count = raffle.tickets.len();
+        grow_raffle(
+            &raffle.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            ticket_count,
+        )?;
+
+        msg!("{} stakes entered into the round ending at {}", entered, raffle.end_time);
+
+        Ok(())
+    }
+
+    /// Makes an NFT or fixed SPL amount the prize of the current round, which must
+    /// have no tickets yet. The admin approves and `creator` pays for the escrow;
+    /// sales open once the creator has deposited the prize.
+    pub fn create_asset_raffle(ctx: Context<CreateAssetRaffle>, prize_amount: u64) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+        require!(raffle.tickets.is_empty(), RaffleError::RoundInProgress);
+        raffle.status.require_open()?;
+        require!(prize_amount > 0, RaffleError::IncorrectAmount);
+
+        let asset_prize = &mut ctx.accounts.asset_prize;
+        asset_prize.creator = ctx.accounts.creator.key();
+        asset_prize.mint = ctx.accounts.prize_mint.key();
+        asset_prize.amount = prize_amount;
+        asset_prize.deposited = false;
+        asset_prize.bump = ctx.bumps.asset_prize;
+        raffle.asset_round = true;
+
+        msg!(
+            "Round ending at {} raffles {} of {} for {}",
+            raffle.end_time,
+            prize_amount,
+            asset_prize.mint,
+            asset_prize.creator
+        );
+
+        Ok(())
+    }
+
+    /// Moves the asset prize from the creator into the prize vault, opening sales.
+    pub fn deposit_prize(ctx: Context<DepositPrize>) -> Result<()> {
+        require!(!ctx.accounts.asset_prize.deposited, RaffleError::PrizeAlreadyDeposited);
+
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.creator_token_account.to_account_info(),
+                    mint: ctx.accounts.prize_mint.to_account_info(),
+                    to: ctx.accounts.prize_vault.to_account_info(),
+                    authority: ctx.accounts.creator.to_account_info(),
+                },
+            ),
+            ctx.accounts.asset_prize.amount,
+            ctx.accounts.prize_mint.decimals,
+        )?;
+
+        let asset_prize = &mut ctx.accounts.asset_prize;
+        asset_prize.deposited = true;
+
+        msg!("{} deposited {} of {}", asset_prize.creator, asset_prize.amount, asset_prize.mint);
+
+        Ok(())
+    }
+
+    /// Returns an asset prize nobody can win to its creator, closing the escrow:
+    /// once the round is cancelled, has ended without tickets or before the prize
+    /// was deposited. Either the creator or the admin can reclaim it.
+    pub fn reclaim_prize(ctx: Context<ReclaimPrize>) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+        let authority = ctx.accounts.authority.key();
+        let asset_prize = &ctx.accounts.asset_prize;
+        require!(
+            authority == asset_prize.creator || authority == raffle.admin,
+            RaffleError::Unauthorized
+        );
+        require!(raffle.asset_round && raffle.tickets.is_empty(), RaffleError::PrizeNotReclaimable);
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(
+            raffle.status == RoundStatus::Cancelled || current_time >= raffle.end_time || !asset_prize.deposited,
+            RaffleError::PrizeNotReclaimable
+        );
+
+        let prize_seeds: &[&[u8]] = &[ASSET_PRIZE_SEED, &[asset_prize.bump]];
+        let escrowed = ctx.accounts.prize_vault.amount;
+        if escrowed > 0 {
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.prize_vault.to_account_info(),
+                        mint: ctx.accounts.prize_mint.to_account_info(),
+                        to: ctx.accounts.creator_token_account.to_account_info(),
+                        authority: asset_prize.to_account_info(),
+                    },
+                    &[prize_seeds],
+                ),
+                escrowed,
+                ctx.accounts.prize_mint.decimals,
+            )?;
+        }
+        token_interface::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.prize_vault.to_account_info(),
+                destination: ctx.accounts.creator.to_account_info(),
+                authority: asset_prize.to_account_info(),
+            },
+            &[prize_seeds],
+        ))?;
+        asset_prize.close(ctx.accounts.creator.to_account_info())?;
+        raffle.asset_round = false;
+
+        msg!("{} of {} returned to {}", escrowed, asset_prize.mint, asset_prize.creator);
+
+        Ok(())
+    }
+
+    /// Cancels the current round, which must have no tickets yet, stopping all
+    /// entries until `set_schedule` reopens the raffle.
+    pub fn cancel_round(ctx: Context<CancelRound>) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+        require!(raffle.tickets.is_empty(), RaffleError::RoundInProgress);
+        raffle.transition(RoundStatus::Cancelled)?;
+
+        msg!("Round ending at {} cancelled", raffle.end_time);
+
+        Ok(())
+    }
+
+    /// Adds `wallet` to the deny list, creating the list on first use. Its
+    /// tickets already in the round stay, but are redrawn if they win. The list
+    /// only changes while the round is open, so it is fixed before the draw's
+    /// entropy is known.
+    pub fn deny_wallet(ctx: Context<DenyWallet>, wallet: Pubkey) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+        require!(raffle.status == RoundStatus::Open, RaffleError::DenyListLocked);
+
+        ctx.accounts.deny_list.insert(wallet)?;
+
+        msg!("Wallet {} denied", wallet);
+
+        Ok(())
+    }
+
+    /// Removes `wallet` from the deny list, likewise only while the round is open.
+    pub fn allow_wallet(ctx: Context<AllowWallet>, wallet: Pubkey) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+        require!(raffle.status == RoundStatus::Open, RaffleError::DenyListLocked);
+
+        ctx.accounts.deny_list.remove(&wallet);
+
+        msg!("Wallet {} allowed again", wallet);
+
+        Ok(())
+    }
+
+    /// Sets the anti-sniping guards: sales close `sales_cutoff` seconds before the
+    /// end of each round, and the draw waits `min_draw_slots` slots after the last
+    /// entry. Zero turns a guard off.
+    pub fn set_draw_guards(ctx: Context<SetDrawGuards>, sales_cutoff: i64, min_draw_slots: u64) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+        require!(
+            sales_cutoff >= 0 && sales_cutoff < raffle.schedule.round_duration(),
+            RaffleError::InvalidDrawGuards
+        );
+
+        raffle.sales_cutoff = sales_cutoff;
+        raffle.min_draw_slots = min_draw_slots;
+
+        msg!("Sales close {}s before the draw, which waits {} slots after the last entry", sales_cutoff, min_draw_slots);
+
+        Ok(())
+    }
+
+    /// Chooses whether the house fee is taken from each entry or from the prize at
+    /// draw time. Only while the round has no tickets, so every entry of a round is
+    /// charged the same way.
+    pub fn set_fee_mode(ctx: Context<SetFeeMode>, fee_mode: FeeMode) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+        require!(raffle.tickets.is_empty(), RaffleError::RoundInProgress);
+
+        raffle.fee_mode = fee_mode;
+
+        msg!("Fee mode set to {:?}", fee_mode);
+
+        Ok(())
+    }
+
+    /// Chooses whether fees are sent to the treasury wallet as they are charged or
+    /// held in the vault until withdrawn through `queue_withdrawal`.
+    pub fn set_hold_fees(ctx: Context<SetHoldFees>, hold_fees: bool) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+
+        raffle.hold_fees = hold_fees;
+
+        msg!("Fees held in the vault: {}", hold_fees);
+
+        Ok(())
+    }
+
+    /// Queues a withdrawal of `amount` lamports of held fees to `destination`,
+    /// which can run once `WITHDRAWAL_DELAY` has passed.
+    pub fn queue_withdrawal(ctx: Context<QueueWithdrawal>, amount: u64, destination: Pubkey) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+        require!(amount > 0, RaffleError::IncorrectAmount);
+        require!(amount <= raffle.unpaid_fees, RaffleError::InsufficientFees);
+
+        let queued_at = Clock::get()?.unix_timestamp;
+        let unlock_time = queued_at
+            .checked_add(WITHDRAWAL_DELAY)
+            .ok_or(RaffleError::ArithmeticOverflow)?;
+        let withdrawal = &mut ctx.accounts.withdrawal;
+        withdrawal.amount = amount;
+        withdrawal.destination = destination;
+        withdrawal.queued_at = queued_at;
+        withdrawal.unlock_time = unlock_time;
+
+        emit!(WithdrawalQueued { amount, destination, unlock_time });
+
+        Ok(())
+    }
+
+    /// Pays out the queued withdrawal once its timelock has run out, returning the
+    /// withdrawal account's rent to the admin.
+    pub fn execute_withdrawal(ctx: Context<ExecuteWithdrawal>) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+
+        let withdrawal = &ctx.accounts.withdrawal;
+        require!(withdrawal.is_unlocked(Clock::get()?.unix_timestamp), RaffleError::WithdrawalLocked);
+        require!(withdrawal.amount <= raffle.unpaid_fees, RaffleError::InsufficientFees);
+
+        invoke_signed(
+            &system_instruction::transfer(
+                &ctx.accounts.vault.key(),
+                &withdrawal.destination,
+                withdrawal.amount,
+            ),
+            &[
+                ctx.accounts.vault.to_account_info(),
+                ctx.accounts.destination.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[&[VAULT_SEED, &[ctx.bumps.vault]]],
+        )?;
+        raffle.unpaid_fees -= withdrawal.amount;
+
+        emit!(WithdrawalExecuted {
+            amount: withdrawal.amount,
+            destination: withdrawal.destination,
+        });
+
+        Ok(())
+    }
+
+    /// Drops the queued withdrawal, leaving the fees in the vault.
+    pub fn cancel_withdrawal(ctx: Context<CancelWithdrawal>) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+
+        let withdrawal = &ctx.accounts.withdrawal;
+        emit!(WithdrawalCancelled {
+            amount: withdrawal.amount,
+            destination: withdrawal.destination,
+        });
+
+        Ok(())
+    }
+
+    /// Adds `amount` lamports to the reserve backing the minimum prize. Anyone can
+    /// fund it; the first funder also covers the reserve's rent-exempt minimum.
+    pub fn fund_reserve(ctx: Context<FundReserve>, amount: u64) -> Result<()> {
+        require!(amount > 0, RaffleError::IncorrectAmount);
+
+        let reserve_rent = Rent::get()?.minimum_balance(0);
+        let deposit = reserve_rent
+            .saturating_sub(ctx.accounts.reserve.lamports())
+            .checked_add(amount)
+            .ok_or(RaffleError::ArithmeticOverflow)?;
+        invoke(
+            &system_instruction::transfer(
+                &ctx.accounts.funder.key(),
+                &ctx.accounts.reserve.key(),
+                deposit,
+            ),
+            &[
+                ctx.accounts.funder.to_account_info(),
+                ctx.accounts.reserve.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        msg!(
+            "{} added {} lamports to the reserve, which now holds {}",
+            ctx.accounts.funder.key(),
+            amount,
+            ctx.accounts.reserve.lamports() - reserve_rent
+        );
+
+        Ok(())
+    }
+
+    /// Prices tickets at `ticket_price_cents` USD cents, converted at purchase time
+    /// with the SOL/USD price published in `price_feed`, a Pyth `PriceUpdateV2`
+    /// account such as the sponsored SOL/USD feed. Prices older than
+    /// `max_price_age` seconds or with a confidence interval wider than
+    /// `max_confidence_bps` of the price are refused. A zero price goes back to
+    /// the fixed `TICKET_PRICE`.
+    pub fn set_usd_pricing(
+        ctx: Context<SetUsdPricing>,
+        ticket_price_cents: u64,
+        price_feed: Pubkey,
+        max_price_age: i64,
+        max_confidence_bps: u16,
+    ) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+        require!(
+            max_price_age > 0 && max_confidence_bps <= MAX_CONFIDENCE_BPS,
+            RaffleError::InvalidPriceConfig
+        );
+
+        let config = &mut ctx.accounts.price_config;
+        config.ticket_price_cents = ticket_price_cents;
+        config.price_feed = price_feed;
+        config.max_price_age = max_price_age;
+        config.max_confidence_bps = max_confidence_bps;
+
+        msg!("Tickets priced at {} USD cents through feed {}", ticket_price_cents, price_feed);
+
+        Ok(())
+    }
+
+    /// Sets the smallest prize a lamport round pays out. When the jackpot falls
+    /// short at draw time the reserve covers the difference; zero turns it off.
+    pub fn set_min_prize(ctx: Context<SetMinPrize>, min_prize: u64) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+
+        raffle.min_prize = min_prize;
+
+        msg!("Minimum prize set to {} lamports", min_prize);
+
+        Ok(())
+    }
+
+    /// Sets the share of a cancelled ticket's refund, in percent, kept in the jackpot.
+    pub fn set_cancel_penalty(ctx: Context<SetCancelPenalty>, penalty_percent: u8) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+        require!(penalty_percent <= MAX_CANCEL_PENALTY_PERCENT, RaffleError::InvalidCancelPenalty);
+
+        raffle.cancel_penalty_percent = penalty_percent;
+
+        msg!("Cancelled tickets are refunded less a {}% penalty", penalty_percent);
+
+        Ok(())
+    }
+
+    /// Replaces the holder bonus tier table.
+    pub fn set_bonus_tiers(ctx: Context<SetBonusTiers>, tiers: [BonusTier; MAX_BONUS_TIERS]) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+
+        validate_bonus_tiers(&tiers)?;
+        raffle.bonus_tiers = tiers;
+
+        msg!("Bonus tiers updated: {:?}", tiers);
+
+        Ok(())
+    }
+
+    /// Replaces the round schedule. A round nobody has entered yet is re-timed
+    /// immediately, which also reopens a cancelled or settled raffle; otherwise
+    /// the schedule applies from the next round.
+    pub fn set_schedule(ctx: Context<SetSchedule>, schedule: Schedule) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle = &mut ctx.accounts.raffle;
+        require!(raffle.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle.version == RAFFLE_VERSION, RaffleError::AccountNeedsMigration);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        schedule.validate(current_time)?;
+        require!(raffle.sales_cutoff < schedule.round_duration(), RaffleError::InvalidDrawGuards);
+        raffle.schedule = schedule;
+        if raffle.tickets.is_empty() {
+            raffle.status.check_transition(RoundStatus::Open)?;
+            raffle.open_next_round(current_time)?;
+        }
+
+        msg!("Schedule updated: {:?}", schedule);
+
+        Ok(())
+    }
+
+    /// Rewrites the raffle account from any older layout into the current one,
+    /// reallocating it in place so the PDA and its jackpot are kept.
+    pub fn migrate(ctx: Context<Migrate>) -> Result<()> {
+        let (raffle_pda, _bump) = Pubkey::find_program_address(&[RAFFLE_SEED], ctx.program_id);
+
+        let raffle_info = ctx.accounts.raffle.to_account_info();
+        require!(raffle_info.key() == raffle_pda, RaffleError::InvalidRaffleAccount);
+        require!(raffle_info.owner == ctx.program_id, RaffleError::InvalidRaffleAccount);
+
+        let migrated = {
+            let data = raffle_info.try_borrow_data()?;
+            require!(
+                data.len() > 8 && data[..8] == Raffle::DISCRIMINATOR,
+                RaffleError::InvalidRaffleAccount
+            );
+
+            // Accounts from before versioning are recognised by their fixed size,
+            // every later layout starts with its version byte.
+            let version = if data.len() == LEGACY_RAFFLE_SPACE { 0 } else { data[8] };
+            match version {
+                0 => {
+                    let legacy = RaffleV0::deserialize(&mut &data[8..])?;
+                    Raffle {
+                        version: RAFFLE_VERSION,
+                        admin: ctx.accounts.authority.key(),
+                        jackpot: legacy.jackpot,
+                        unpaid_fees: 0,
+                        start_time: legacy.start_time,
+                        end_time: legacy.end_time,
+                        schedule: Schedule::default(),
+                        bonus_tiers: [BonusTier::default(); MAX_BONUS_TIERS],
+                        bonus_tickets: 0,
+                        sales_cutoff: 0,
+                        min_draw_slots: 0,
+                        last_entry_slot: 0,
+                        draw_slot: 0,
+                        fee_mode: FeeMode::default(),
+                        asset_round: false,
+                        cancel_penalty_percent: 0,
+                        min_prize: 0,
+                        status: RoundStatus::Open,
+                        hold_fees: false,
+                        reserved: [0; RAFFLE_RESERVED_BYTES],
+                        tickets: legacy.tickets,
+                    }
+                }
+                RAFFLE_VERSION => {
+                    msg!("Raffle already at version {}", RAFFLE_VERSION);
+                    return Ok(());
+                }
+                _ => return err!(RaffleError::UnsupportedAccountVersion),
+            }
+        };
+
+        // Top up rent for the new size before growing the account
+        let migrated_space = Raffle::space(migrated.tickets.len());
+        let rent_exempt_minimum = Rent::get()?.minimum_balance(migrated_space);
+        let rent_shortfall = rent_exempt_minimum.saturating_sub(raffle_info.lamports());
+        if rent_shortfall > 0 {
+            invoke(
+                &system_instruction::transfer(
+                    &ctx.accounts.authority.key(),
+                    &raffle_pda,
+                    rent_shortfall,
+                ),
+                &[
+                    ctx.accounts.authority.to_account_info(),
+                    raffle_info.clone(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+
+        // Legacy rounds never moved the jackpot into the vault, so the authority
+        // funds the vault for the jackpot carried over
+        let vault_required = Rent::get()?
+            .minimum_balance(0)
+            .checked_add(migrated.jackpot)
+            .ok_or(RaffleError::ArithmeticOverflow)?;
+        let vault_shortfall = vault_required.saturating_sub(ctx.accounts.vault.lamports());
+        if vault_shortfall > 0 {
+            invoke(
+                &system_instruction::transfer(
+                    &ctx.accounts.authority.key(),
+                    &ctx.accounts.vault.key(),
+                    vault_shortfall,
+                ),
+                &[
+                    ctx.accounts.authority.to_account_info(),
+                    ctx.accounts.vault.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+
+        raffle_info.realloc(migrated_space, false)?;
+        let mut data = raffle_info.try_borrow_mut_data()?;
+        data.fill(0);
+        migrated.try_serialize(&mut &mut data[..])?;
+
+        msg!("Raffle migrated to version {}", RAFFLE_VERSION);
+
+        Ok(())
+    }
+}
+
+/// Pays out the draw described by `transcript`, records it and opens the next
+/// round. Shared by `select_winner` and `finalize_draw`.
+fn settle_draw(ctx: Context<SelectWinner>, transcript: DrawTranscript) -> Result<()> {
+    let raffle = &mut ctx.accounts.raffle;
+    raffle.status.check_transition(RoundStatus::Open)?;
+    let current_time = Clock::get()?.unix_timestamp;
+    let entropy_slot = transcript.entropy_slot;
+    // Tickets of wallets denied since they entered are redrawn from the same transcript
+    let deny_list = load_deny_list(&ctx.accounts.deny_list, ctx.program_id)?;
+    let (redraws, paid_index) = transcript.eligible_draw(&raffle.tickets, &deny_list)?;
+    let winner = raffle.tickets[paid_index as usize];
+    require!(ctx.accounts.winner.key() == winner, RaffleError::InvalidWinnerAccount);
+
+    // Keep the transcript, the redraws and the deny list they skipped so anyone
+    // can replay the draw
+    let record = &mut ctx.accounts.draw_record;
+    record.round_end_time = raffle.end_time;
+    record.winner = winner;
+    record.transcript = transcript;
+    record.redraws = redraws;
+    record.paid_index = paid_index;
+    record.deny_list_hash = deny_list.hash();
+
+    // Transfer the prize to the winner and, when fees come out of winnings, the fee to the treasury
+    let treasury_wallet = Pubkey::from_str(TREASURY_WALLET).unwrap();
+    require!(ctx.accounts.treasury.key() == treasury_wallet, RaffleError::InvalidTreasuryWallet);
+    let (prize, house_fee) = raffle.split_payout()?;
+
+    // The reserve makes up any shortfall below the guaranteed minimum prize
+    let top_up = raffle.min_prize_top_up(prize);
+    if top_up > 0 {
+        let reserve = ctx.accounts.reserve.to_account_info();
+        let available = reserve
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(reserve.data_len()));
+        if available < top_up {
+            msg!("Reserve holds {} lamports, {} needed to guarantee the minimum prize", available, top_up);
+            return err!(RaffleError::InsufficientReserve);
+        }
+        invoke_signed(
+            &system_instruction::transfer(
+                &reserve.key(),
+                &winner,
+                top_up,
+            ),
+            &[
+                reserve,
+                ctx.accounts.winner.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[&[RESERVE_SEED, &[ctx.bumps.reserve]]],
+        )?;
+    }
+
+    let vault_bump = [ctx.bumps.vault];
+    invoke_signed(
+        &system_instruction::transfer(
+            &ctx.accounts.vault.key(),
+            &winner,
+            prize,
+        ),
+        &[
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.winner.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        ],
+        &[&[VAULT_SEED, &vault_bump]],
+    )?;
+    // A fee held for withdrawal simply stays in the vault
+    let pushed_fee = raffle.hold_fee(house_fee)?;
+    if pushed_fee > 0 {
+        invoke_signed(
+            &system_instruction::transfer(
+                &ctx.accounts.vault.key(),
+                &treasury_wallet,
+                pushed_fee,
+            ),
+            &[
+                ctx.accounts.vault.to_account_info(),
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[&[VAULT_SEED, &vault_bump]],
+        )?;
+    }
+    record.prize = prize + top_up;
+    record.house_fee = house_fee;
+
+    // Hand an asset prize to the winner's associated token account, returning
+    // the escrow's rent to the creator
+    if raffle.asset_round {
+        let (
+            Some(asset_prize),
+            Some(prize_vault),
+            Some(prize_mint),
+            Some(winner_token_account),
+            Some(creator),
+            Some(token_program),
+            Some(associated_token_program),
+        ) = (
+            &ctx.accounts.asset_prize,
+            &ctx.accounts.prize_vault,
+            &ctx.accounts.prize_mint,
+            &ctx.accounts.winner_token_account,
+            &ctx.accounts.creator,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+        )
+        else {
+            return err!(RaffleError::InvalidAssetPrize);
+        };
+        require!(prize_mint.key() == asset_prize.mint, RaffleError::InvalidAssetPrize);
+        require!(creator.key() == asset_prize.creator, RaffleError::InvalidCreatorAccount);
+
+        associated_token::create_idempotent(CpiContext::new(
+            associated_token_program.to_account_info(),
+            Create {
+                payer: ctx.accounts.payer.to_account_info(),
+                associated_token: winner_token_account.to_account_info(),
+                authority: ctx.accounts.winner.to_account_info(),
+                mint: prize_mint.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: token_program.to_account_info(),
+            },
+        ))?;
+
+        let prize_seeds: &[&[u8]] = &[ASSET_PRIZE_SEED, &[asset_prize.bump]];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                TransferChecked {
+                    from: prize_vault.to_account_info(),
+                    mint: prize_mint.to_account_info(),
+                    to: winner_token_account.to_account_info(),
+                    authority: asset_prize.to_account_info(),
+                },
+                &[prize_seeds],
+            ),
+            asset_prize.amount,
+            prize_mint.decimals,
+        )?;
+        token_interface::close_account(CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            CloseAccount {
+                account: prize_vault.to_account_info(),
+                destination: creator.to_account_info(),
+                authority: asset_prize.to_account_info(),
+            },
+            &[prize_seeds],
+        ))?;
+        asset_prize.close(creator.to_account_info())?;
+
+        msg!("Asset prize of {} {} sent to {}", asset_prize.amount, asset_prize.mint, winner);
+    }
+
+    // Reset raffle and open the next round from the schedule, settling the raffle
+    // if it has none
+    raffle.jackpot = 0;
+    raffle.bonus_tickets = 0;
+    raffle.tickets = vec![];
+    raffle.draw_slot = 0;
+    raffle.asset_round = false;
+    raffle.open_next_round(current_time + ROUND_GAP)?;
+
+    msg!(
+        "Winner selected: {} from the hash of slot {}, paid {} lamports ({} from the reserve, {} house fee)",
+        winner,
+        entropy_slot,
+        prize + top_up,
+        top_up,
+        house_fee
+    );
+
+    Ok(())
+}
+
+/// Reads the draw progress account, which doesn't exist until a batched draw
+/// has been begun.
+fn load_draw_progress(info: &AccountInfo, program_id: &Pubkey) -> Result<Option<DrawProgress>> {
+    if info.owner != program_id || info.data_is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(DrawProgress::try_deserialize(&mut &info.try_borrow_data()?[..])?))
+}
+
+/// Reads the USD pricing config, if the admin has ever set one.
+fn load_price_config(info: &AccountInfo, program_id: &Pubkey) -> Result<Option<PriceConfig>> {
+    if info.owner != program_id || info.data_is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(PriceConfig::try_deserialize(&mut &info.try_borrow_data()?[..])?))
+}
+
+/// Reads the deny list, which is empty until the admin first denies a wallet.
+fn load_deny_list(info: &AccountInfo, program_id: &Pubkey) -> Result<DenyList> {
+    if info.owner != program_id || info.data_is_empty() {
+        return Ok(DenyList::default());
+    }
+    DenyList::try_deserialize(&mut &info.try_borrow_data()?[..])
+}
+
+/// Grows the raffle account to fit a ledger of `ticket_count` tickets, `payer`
+/// covering the extra rent.
+fn grow_raffle<'info>(
+    raffle_info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    ticket_count: usize,
+) -> Result<()> {
+    let required_space = Raffle::space(ticket_count);
+    if raffle_info.data_len() >= required_space {
+        return Ok(());
+    }
+
+    let rent_shortfall = Rent::get()?
+        .minimum_balance(required_space)
+        .saturating_sub(raffle_info.lamports());
+    if rent_shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, raffle_info.key, rent_shortfall),
+            &[payer.clone(), raffle_info.clone(), system_program.clone()],
+        )?;
+    }
+    raffle_info.realloc(required_space, false)?;
+
+    Ok(())
+}
+
+/// Moves lamports out of an account owned by this program.
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let from_balance = from.lamports().checked_sub(amount).ok_or(RaffleError::InsufficientFunds)?;
+    let to_balance = to.lamports().checked_add(amount).ok_or(RaffleError::ArithmeticOverflow)?;
+    **from.try_borrow_mut_lamports()? = from_balance;
+    **to.try_borrow_mut_lamports()? = to_balance;
+    Ok(())
+}
+
+/// Closes an account owned by this program, sending its lamports to `destination`.
+fn close_account<'info>(info: &AccountInfo<'info>, destination: &AccountInfo<'info>) -> Result<()> {
+    move_lamports(info, destination, info.lamports())?;
+    info.assign(&System::id());
+    info.realloc(0, false)?;
+    Ok(())
+}
+
+/// Splits the cost of a purchase into the jackpot share and the treasury fee.
+/// The fee is rounded down and the jackpot takes the remainder, so no lamports are lost.
+pub fn split_payment(cost: u64) -> Result<(u64, u64)> {
+    let treasury_cut = cost
+        .checked_mul(TREASURY_FEE_PERCENT)
+        .ok_or(RaffleError::ArithmeticOverflow)?
+        / 100;
+    let jackpot_increment = cost
+        .checked_sub(treasury_cut)
+        .ok_or(RaffleError::ArithmeticOverflow)?;
+    Ok((jackpot_increment, treasury_cut))
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(init, payer = admin, space = Raffle::space(0), seeds = [RAFFLE_SEED], bump)]
+    pub raffle: Account<'info, Raffle>,               // PDA for Raffle
+    #[account(mut, seeds = [VAULT_SEED], bump)]
+    pub vault: SystemAccount<'info>,                  // PDA holding the jackpot
+    #[account(mut)]
+    pub admin: Signer<'info>,                         // Admin initializing the raffle
+    pub system_program: Program<'info, System>,       // System program for account creation
+}
+
+#[derive(Accounts)]
+pub struct BuyTicket<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,                         // Ticket buyer
+    #[account(mut)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account storing ticket details
+    #[account(mut, seeds = [VAULT_SEED], bump)]
+    pub vault: SystemAccount<'info>,                  // PDA holding the jackpot
+    #[account(mut)]
+    pub treasury: AccountInfo<'info>,                 // Treasury wallet receiving fees
+    pub system_program: Program<'info, System>,       // System program for funds transfer
+    pub holder_token_account: Option<InterfaceAccount<'info, TokenAccount>>, // Buyer's PSYDUK account, for bonus tickets
+    #[account(seeds = [ASSET_PRIZE_SEED], bump)]
+    pub asset_prize: Option<Account<'info, AssetPrize>>, // Prize of an asset prize round
+    #[account(mut)]
+    pub creator: Option<AccountInfo<'info>>,          // Creator of the asset prize, receiving the revenue
+    #[account(init_if_needed, payer = buyer, space = TicketReceipt::SPACE, seeds = [RECEIPT_SEED, buyer.key().as_ref()], bump)]
+    pub receipt: Option<Account<'info, TicketReceipt>>, // Buyer's record of the tickets it can cancel, when it keeps them
+    #[account(seeds = [DENY_LIST_SEED], bump)]
+    pub deny_list: AccountInfo<'info>,                // Wallets barred from the raffle, possibly not created yet
+    #[account(seeds = [PRICE_CONFIG_SEED], bump)]
+    pub price_config: AccountInfo<'info>,             // USD pricing config, possibly not created yet
+    pub price_feed: Option<AccountInfo<'info>>,       // SOL/USD feed named by the config, when tickets are priced in USD
+}
+
+#[derive(Accounts)]
+pub struct CancelTickets<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,                         // Wallet cancelling its tickets, receiving the refund
+    #[account(mut)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account storing ticket details
+    #[account(mut, seeds = [VAULT_SEED], bump)]
+    pub vault: SystemAccount<'info>,                  // PDA holding the jackpot
+    #[account(mut, seeds = [RECEIPT_SEED, buyer.key().as_ref()], bump)]
+    pub receipt: Account<'info, TicketReceipt>,       // Buyer's record of the tickets it can cancel
+    pub system_program: Program<'info, System>,       // System program for the refund
+}
+
+#[derive(Accounts)]
+pub struct LockRound<'info> {
+    #[account(mut)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account whose round is locked
+}
+
+#[derive(Accounts)]
+pub struct SelectWinner<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,                         // Crank paying for the draw record
+    #[account(mut)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account for winner selection
+    #[account(mut, seeds = [VAULT_SEED], bump)]
+    pub vault: SystemAccount<'info>,                  // PDA holding the jackpot
+    #[account(mut, seeds = [RESERVE_SEED], bump)]
+    pub reserve: SystemAccount<'info>,                // PDA topping the prize up to the minimum
+    #[account(mut)]
+    pub winner: AccountInfo<'info>,                   // Owner of the drawn ticket, after any redraws
+    #[account(mut)]
+    pub treasury: AccountInfo<'info>,                 // Treasury wallet receiving a fee on winnings
+    #[account(init, payer = payer, space = DrawRecord::SPACE, seeds = [DRAW_SEED, &raffle.end_time.to_le_bytes()], bump)]
+    pub draw_record: Account<'info, DrawRecord>,      // PDA recording how the round was drawn
+    #[account(address = anchor_lang::solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: AccountInfo<'info>,              // SlotHashes sysvar supplying the entropy
+    #[account(mut, seeds = [DRAW_PROGRESS_SEED], bump)]
+    pub draw_progress: AccountInfo<'info>,            // Progress of a batched draw, closed by finalize_draw
+    #[account(seeds = [DENY_LIST_SEED], bump)]
+    pub deny_list: AccountInfo<'info>,                // Wallets whose tickets are redrawn, possibly not created yet
+    pub system_program: Program<'info, System>,       // System program for winner payment
+    #[account(mut, seeds = [ASSET_PRIZE_SEED], bump)]
+    pub asset_prize: Option<Account<'info, AssetPrize>>, // Prize of an asset prize round, closed
+    #[account(mut, seeds = [PRIZE_VAULT_SEED], bump)]
+    pub prize_vault: Option<InterfaceAccount<'info, TokenAccount>>, // Token account escrowing the prize, closed
+    pub prize_mint: Option<InterfaceAccount<'info, Mint>>, // Mint of the prize
+    #[account(mut)]
+    pub winner_token_account: Option<AccountInfo<'info>>, // Winner's associated token account, created if missing
+    #[account(mut)]
+    pub creator: Option<AccountInfo<'info>>,          // Creator of the prize, receiving the escrow's rent
+    pub token_program: Option<Interface<'info, TokenInterface>>, // Token program of the prize mint
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>, // Creates the winner's token account
+}
+
+#[derive(Accounts)]
+pub struct BeginDraw<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,                         // Keeper paying for the progress account
+    #[account(mut)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account whose round is drawn
+    #[account(init_if_needed, payer = payer, space = DrawProgress::SPACE, seeds = [DRAW_PROGRESS_SEED], bump)]
+    pub draw_progress: Account<'info, DrawProgress>,  // PDA tracking the batched draw
+    #[account(address = anchor_lang::solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: AccountInfo<'info>,              // SlotHashes sysvar supplying the entropy
+    pub system_program: Program<'info, System>,       // System program for account creation
+}
+
+#[derive(Accounts)]
+pub struct AdvanceDraw<'info> {
+    #[account(mut)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account holding the ledger
+    #[account(mut, seeds = [DRAW_PROGRESS_SEED], bump)]
+    pub draw_progress: Account<'info, DrawProgress>,  // PDA tracking the batched draw
+}
+
+#[derive(Accounts)]
+pub struct Reconcile<'info> {
+    pub raffle: Account<'info, Raffle>,               // Raffle account holding the books
+    #[account(seeds = [VAULT_SEED], bump)]
+    pub vault: SystemAccount<'info>,                  // PDA holding the jackpot
+}
+
+#[derive(Accounts)]
+pub struct ViewRound<'info> {
+    pub raffle: Account<'info, Raffle>,               // Raffle account read by the query
+}
+
+
+#[derive(Accounts)]
+pub struct Subscribe<'info> {
+    #[account(mut)]
+    pub subscriber: Signer<'info>,                    // Wallet paying for and receiving the tickets
+    #[account(init, payer = subscriber, space = Subscription::SPACE, seeds = [SUBSCRIPTION_SEED, subscriber.key().as_ref()], bump)]
+    pub subscription: Account<'info, Subscription>,   // PDA escrowing the prepaid rounds
+    pub system_program: Program<'info, System>,       // System program for account creation
+    #[account(seeds = [PRICE_CONFIG_SEED], bump)]
+    pub price_config: AccountInfo<'info>,             // USD pricing config, possibly not created yet
+}
+
+#[derive(Accounts)]
+pub struct ApplySubscriptions<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,                         // Crank covering the ledger's extra rent
+    #[account(mut)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account storing ticket details
+    #[account(mut, seeds = [VAULT_SEED], bump)]
+    pub vault: SystemAccount<'info>,                  // PDA holding the jackpot
+    #[account(mut)]
+    pub treasury: AccountInfo<'info>,                 // Treasury wallet receiving fees
+    pub system_program: Program<'info, System>,       // System program for rent top-up
+    // Remaining accounts: writable Subscription PDAs to enter into the round
+}
+
+#[derive(Accounts)]
+pub struct Unsubscribe<'info> {
+    #[account(mut)]
+    pub subscriber: Signer<'info>,                    // Owner of the subscription, receiving the refund
+    #[account(mut, seeds = [SUBSCRIPTION_SEED, subscriber.key().as_ref()], bump, has_one = subscriber @ RaffleError::Unauthorized, close = subscriber)]
+    pub subscription: Account<'info, Subscription>,   // Subscription to close
+}
+
+#[derive(Accounts)]
+#[instruction(round_end_time: i64)]
+pub struct PublishFreeTickets<'info> {
+    #[account(has_one = admin @ RaffleError::Unauthorized)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account naming the admin
+    #[account(init, payer = admin, space = Promotion::SPACE, seeds = [PROMOTION_SEED, &round_end_time.to_le_bytes()], bump)]
+    pub promotion: Account<'info, Promotion>,         // PDA holding the round's Merkle root
+    #[account(mut)]
+    pub admin: Signer<'info>,                         // Raffle admin
+    pub system_program: Program<'info, System>,       // System program for account creation
+}
+
+#[derive(Accounts)]
+pub struct ClaimFreeTickets<'info> {
+    #[account(mut)]
+    pub claimant: Signer<'info>,                      // Wallet named in the grant
+    #[account(mut)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account storing ticket details
+    #[account(seeds = [PROMOTION_SEED, &promotion.round_end_time.to_le_bytes()], bump)]
+    pub promotion: Account<'info, Promotion>,         // Promotion of the current round
+    #[account(init, payer = claimant, space = FreeTicketClaim::SPACE, seeds = [CLAIM_SEED, promotion.key().as_ref(), claimant.key().as_ref()], bump)]
+    pub claim: Account<'info, FreeTicketClaim>,       // Receipt marking the grant as claimed
+    pub system_program: Program<'info, System>,       // System program for account creation
+}
+
+#[derive(Accounts)]
+pub struct InitializeStaking<'info> {
+    #[account(has_one = admin @ RaffleError::Unauthorized)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account naming the admin
+    #[account(init, payer = admin, space = StakePool::SPACE, seeds = [STAKE_POOL_SEED], bump)]
+    pub stake_pool: Account<'info, StakePool>,        // PDA holding the staking settings
+    #[account(init, payer = admin, seeds = [STAKE_VAULT_SEED], bump, token::mint = mint, token::authority = stake_pool, token::token_program = token_program)]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>, // Token account holding staked PSYDUK
+    #[account(address = psyduk_mint() @ RaffleError::InvalidStakeMint)]
+    pub mint: InterfaceAccount<'info, Mint>,          // PSYDUK mint
+    #[account(mut)]
+    pub admin: Signer<'info>,                         // Raffle admin
+    pub token_program: Interface<'info, TokenInterface>, // Token program of the mint
+    pub system_program: Program<'info, System>,       // System program for account creation
+}
+
+#[derive(Accounts)]
+pub struct Stake<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,                         // Wallet staking its tokens
+    #[account(mut, seeds = [STAKE_POOL_SEED], bump = stake_pool.bump)]
+    pub stake_pool: Account<'info, StakePool>,        // Staking settings and totals
+    #[account(init_if_needed, payer = owner, space = StakePosition::SPACE, seeds = [STAKE_SEED, owner.key().as_ref()], bump)]
+    pub stake_position: Account<'info, StakePosition>, // Owner's stake position
+    #[account(mut, seeds = [STAKE_VAULT_SEED], bump)]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>, // Token account holding staked PSYDUK
+    #[account(mut, token::mint = mint, token::authority = owner)]
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>, // Owner's PSYDUK account paying in
+    #[account(address = stake_pool.mint @ RaffleError::InvalidStakeMint)]
+    pub mint: InterfaceAccount<'info, Mint>,          // PSYDUK mint
+    pub token_program: Interface<'info, TokenInterface>, // Token program of the mint
+    pub system_program: Program<'info, System>,       // System program for account creation
+}
+
+#[derive(Accounts)]
+pub struct RequestUnstake<'info> {
+    pub owner: Signer<'info>,                         // Wallet that staked
+    #[account(mut, seeds = [STAKE_SEED, owner.key().as_ref()], bump, has_one = owner @ RaffleError::Unauthorized)]
+    pub stake_position: Account<'info, StakePosition>, // Owner's stake position
+}
+
+#[derive(Accounts)]
+pub struct Unstake<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,                         // Wallet that staked, receiving tokens and rent
+    #[account(mut, seeds = [STAKE_POOL_SEED], bump = stake_pool.bump)]
+    pub stake_pool: Account<'info, StakePool>,        // Staking settings and totals, vault authority
+    #[account(mut, seeds = [STAKE_SEED, owner.key().as_ref()], bump, has_one = owner @ RaffleError::Unauthorized, close = owner)]
+    pub stake_position: Account<'info, StakePosition>, // Owner's stake position, closed
+    #[account(mut, seeds = [STAKE_VAULT_SEED], bump)]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>, // Token account holding staked PSYDUK
+    #[account(mut, token::mint = mint, token::authority = owner)]
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>, // Owner's PSYDUK account paid back
+    #[account(address = stake_pool.mint @ RaffleError::InvalidStakeMint)]
+    pub mint: InterfaceAccount<'info, Mint>,          // PSYDUK mint
+    pub token_program: Interface<'info, TokenInterface>, // Token program of the mint
+}
+
+#[derive(Accounts)]
+pub struct AllocateStakeTickets<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,                         // Crank covering the ledger's extra rent
+    #[account(mut)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account storing ticket details
+    #[account(seeds = [STAKE_POOL_SEED], bump = stake_pool.bump)]
+    pub stake_pool: Account<'info, StakePool>,        // Staking settings
+    pub system_program: Program<'info, System>,       // System program for rent top-up
+    // Remaining accounts: writable StakePosition PDAs to enter into the round
+}
+
+#[derive(Accounts)]
+pub struct CancelRound<'info> {
+    #[account(mut, has_one = admin @ RaffleError::Unauthorized)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account whose round is cancelled
+    pub admin: Signer<'info>,                         // Raffle admin
+}
+
+#[derive(Accounts)]
+pub struct DenyWallet<'info> {
+    #[account(has_one = admin @ RaffleError::Unauthorized)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account of the admin
+    #[account(mut)]
+    pub admin: Signer<'info>,                         // Raffle admin, paying for the list on first use
+    #[account(init_if_needed, payer = admin, space = DenyList::SPACE, seeds = [DENY_LIST_SEED], bump)]
+    pub deny_list: Account<'info, DenyList>,          // Wallets barred from the raffle
+    pub system_program: Program<'info, System>,       // System program for account creation
+}
+
+#[derive(Accounts)]
+pub struct AllowWallet<'info> {
+    #[account(has_one = admin @ RaffleError::Unauthorized)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account of the admin
+    pub admin: Signer<'info>,                         // Raffle admin
+    #[account(mut, seeds = [DENY_LIST_SEED], bump)]
+    pub deny_list: Account<'info, DenyList>,          // Wallets barred from the raffle
+}
+
+#[derive(Accounts)]
+pub struct SetDrawGuards<'info> {
+    #[account(mut, has_one = admin @ RaffleError::Unauthorized)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account holding the guards
+    pub admin: Signer<'info>,                         // Raffle admin
+}
+
+#[derive(Accounts)]
+pub struct CreateAssetRaffle<'info> {
+    #[account(mut, has_one = admin @ RaffleError::Unauthorized)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account whose round gets the prize
+    pub admin: Signer<'info>,                         // Raffle admin
+    #[account(mut)]
+    pub creator: Signer<'info>,                       // Wallet escrowing the prize and paying for the escrow
+    #[account(init, payer = creator, space = AssetPrize::SPACE, seeds = [ASSET_PRIZE_SEED], bump)]
+    pub asset_prize: Account<'info, AssetPrize>,      // PDA describing the prize
+    #[account(init, payer = creator, seeds = [PRIZE_VAULT_SEED], bump, token::mint = prize_mint, token::authority = asset_prize, token::token_program = token_program)]
+    pub prize_vault: InterfaceAccount<'info, TokenAccount>, // Token account escrowing the prize
+    pub prize_mint: InterfaceAccount<'info, Mint>,    // Mint of the prize
+    pub token_program: Interface<'info, TokenInterface>, // Token program of the mint
+    pub system_program: Program<'info, System>,       // System program for account creation
+}
+
+#[derive(Accounts)]
+pub struct DepositPrize<'info> {
+    pub creator: Signer<'info>,                       // Creator of the prize
+    #[account(mut, seeds = [ASSET_PRIZE_SEED], bump = asset_prize.bump, has_one = creator @ RaffleError::Unauthorized)]
+    pub asset_prize: Account<'info, AssetPrize>,      // PDA describing the prize
+    #[account(mut, seeds = [PRIZE_VAULT_SEED], bump)]
+    pub prize_vault: InterfaceAccount<'info, TokenAccount>, // Token account escrowing the prize
+    #[account(mut, token::mint = prize_mint, token::authority = creator)]
+    pub creator_token_account: InterfaceAccount<'info, TokenAccount>, // Creator's account paying in the prize
+    #[account(address = asset_prize.mint @ RaffleError::InvalidAssetPrize)]
+    pub prize_mint: InterfaceAccount<'info, Mint>,    // Mint of the prize
+    pub token_program: Interface<'info, TokenInterface>, // Token program of the mint
+}
+
+#[derive(Accounts)]
+pub struct ReclaimPrize<'info> {
+    #[account(mut)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account whose round loses the prize
+    pub authority: Signer<'info>,                     // Creator of the prize or raffle admin
+    #[account(mut, seeds = [ASSET_PRIZE_SEED], bump = asset_prize.bump, has_one = creator @ RaffleError::InvalidCreatorAccount)]
+    pub asset_prize: Account<'info, AssetPrize>,      // PDA describing the prize, closed
+    #[account(mut, seeds = [PRIZE_VAULT_SEED], bump)]
+    pub prize_vault: InterfaceAccount<'info, TokenAccount>, // Token account escrowing the prize, closed
+    #[account(mut, token::mint = prize_mint, token::authority = creator)]
+    pub creator_token_account: InterfaceAccount<'info, TokenAccount>, // Creator's account receiving the prize back
+    #[account(mut)]
+    pub creator: SystemAccount<'info>,                // Creator of the prize, refunded the escrow's rent
+    #[account(address = asset_prize.mint @ RaffleError::InvalidAssetPrize)]
+    pub prize_mint: InterfaceAccount<'info, Mint>,    // Mint of the prize
+    pub token_program: Interface<'info, TokenInterface>, // Token program of the mint
+}
+
+#[derive(Accounts)]
+pub struct SetFeeMode<'info> {
+    #[account(mut, has_one = admin @ RaffleError::Unauthorized)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account holding the fee mode
+    pub admin: Signer<'info>,                         // Raffle admin
+}
+
+#[derive(Accounts)]
+pub struct SetHoldFees<'info> {
+    #[account(mut, has_one = admin @ RaffleError::Unauthorized)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account holding the fee custody flag
+    pub admin: Signer<'info>,                         // Raffle admin
+}
+
+#[derive(Accounts)]
+pub struct QueueWithdrawal<'info> {
+    #[account(has_one = admin @ RaffleError::Unauthorized)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account booking the held fees
+    #[account(mut)]
+    pub admin: Signer<'info>,                         // Raffle admin, paying for the withdrawal account
+    #[account(init, payer = admin, space = Withdrawal::SPACE, seeds = [WITHDRAWAL_SEED], bump)]
+    pub withdrawal: Account<'info, Withdrawal>,       // The queued withdrawal
+    pub system_program: Program<'info, System>,       // System program for account creation
+}
+
+#[derive(Accounts)]
+pub struct ExecuteWithdrawal<'info> {
+    #[account(mut, has_one = admin @ RaffleError::Unauthorized)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account booking the held fees
+    #[account(mut)]
+    pub admin: Signer<'info>,                         // Raffle admin, refunded the withdrawal account's rent
+    #[account(mut, seeds = [VAULT_SEED], bump)]
+    pub vault: SystemAccount<'info>,                  // PDA holding the fees
+    #[account(mut, seeds = [WITHDRAWAL_SEED], bump, close = admin)]
+    pub withdrawal: Account<'info, Withdrawal>,       // The queued withdrawal
+    #[account(mut, address = withdrawal.destination @ RaffleError::InvalidWithdrawalDestination)]
+    pub destination: AccountInfo<'info>,              // Wallet the withdrawal was queued for
+    pub system_program: Program<'info, System>,       // System program for the payout
+}
+
+#[derive(Accounts)]
+pub struct CancelWithdrawal<'info> {
+    #[account(has_one = admin @ RaffleError::Unauthorized)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account of the admin
+    #[account(mut)]
+    pub admin: Signer<'info>,                         // Raffle admin, refunded the withdrawal account's rent
+    #[account(mut, seeds = [WITHDRAWAL_SEED], bump, close = admin)]
+    pub withdrawal: Account<'info, Withdrawal>,       // The queued withdrawal
+}
+
+#[derive(Accounts)]
+pub struct FundReserve<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,                        // Wallet paying into the reserve
+    #[account(mut, seeds = [RESERVE_SEED], bump)]
+    pub reserve: SystemAccount<'info>,                // PDA topping the prize up to the minimum
+    pub system_program: Program<'info, System>,       // System program for the deposit
+}
+
+#[derive(Accounts)]
+pub struct SetUsdPricing<'info> {
+    #[account(has_one = admin @ RaffleError::Unauthorized)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account of the admin
+    #[account(mut)]
+    pub admin: Signer<'info>,                         // Raffle admin, paying for the config the first time
+    #[account(init_if_needed, payer = admin, space = PriceConfig::SPACE, seeds = [PRICE_CONFIG_SEED], bump)]
+    pub price_config: Account<'info, PriceConfig>,    // USD pricing config
+    pub system_program: Program<'info, System>,       // System program for account creation
+}
+
+#[derive(Accounts)]
+pub struct SetMinPrize<'info> {
+    #[account(mut, has_one = admin @ RaffleError::Unauthorized)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account holding the minimum prize
+    pub admin: Signer<'info>,                         // Raffle admin
+}
+
+#[derive(Accounts)]
+pub struct SetCancelPenalty<'info> {
+    #[account(mut, has_one = admin @ RaffleError::Unauthorized)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account holding the penalty
+    pub admin: Signer<'info>,                         // Raffle admin
+}
+
+#[derive(Accounts)]
+pub struct SetBonusTiers<'info> {
+    #[account(mut, has_one = admin @ RaffleError::Unauthorized)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account holding the tier table
+    pub admin: Signer<'info>,                         // Raffle admin
+}
+
+#[derive(Accounts)]
+pub struct SetSchedule<'info> {
+    #[account(mut, has_one = admin @ RaffleError::Unauthorized)]
+    pub raffle: Account<'info, Raffle>,               // Raffle account to reschedule
+    pub admin: Signer<'info>,                         // Raffle admin
+}
+
+#[derive(Accounts)]
+pub struct Migrate<'info> {
+    #[account(mut)]
+    pub raffle: AccountInfo<'info>,                   // Raffle account in any known layout
+    #[account(mut, seeds = [VAULT_SEED], bump)]
+    pub vault: SystemAccount<'info>,                  // PDA funded for the jackpot carried over
+    #[account(mut)]
+    pub authority: Signer<'info>,                     // Upgrade authority of the program, funding the vault
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ RaffleError::Unauthorized)]
+    pub program: Program<'info, program::Raffle>,     // This program, to locate its program data
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ RaffleError::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,    // Program data holding the upgrade authority
+    pub system_program: Program<'info, System>,       // System program for rent top-up
+}
+
+
+#[account]
+pub struct Raffle {
+    pub version: u8,           // Layout version, see RAFFLE_VERSION
+    pub admin: Pubkey,         // Authority for admin instructions
+    pub jackpot: u64,          // Total prize pool
+    pub unpaid_fees: u64,      // Treasury fees held in the vault, not yet paid out
+    pub start_time: i64,       // Raffle start timestamp
+    pub end_time: i64,         // Raffle end timestamp
+    pub schedule: Schedule,    // When rounds open and close
+    pub bonus_tiers: [BonusTier; MAX_BONUS_TIERS], // Holder bonus table, see set_bonus_tiers
+    pub bonus_tickets: u64,    // Bonus tickets in this round's ledger, on top of the paid ones
+    pub sales_cutoff: i64,     // Seconds before end_time at which entries close
+    pub min_draw_slots: u64,   // Slots the draw must wait after the last entry
+    pub last_entry_slot: u64,  // Slot of the latest entry of any kind
+    pub draw_slot: u64,        // Slot fixed by lock_round whose successor draws the round, 0 until locked
+    pub fee_mode: FeeMode,     // When the house fee is taken
+    pub asset_round: bool,     // Whether the current round's prize is the escrowed AssetPrize
+    pub cancel_penalty_percent: u8, // Share of a cancelled ticket's refund kept in the jackpot
+    pub min_prize: u64,        // Smallest prize of a lamport round, topped up from the reserve
+    pub status: RoundStatus,   // Where the current round is in its lifecycle
+    pub hold_fees: bool,       // Whether fees are held in the vault instead of sent to the treasury
+    pub reserved: [u8; RAFFLE_RESERVED_BYTES], // Zeroed padding for future fields
+    pub tickets: Vec<Pubkey>,  // List of participants' public keys
+}
+
+impl Raffle {
+    /// Account size needed to hold a ledger of `ticket_count` tickets:
+    /// discriminator + fixed fields + reserved padding + ticket ledger.
+    pub fn space(ticket_count: usize) -> usize {
+        8 + 1 + 32 + 8 + 8 + 8 + 8
+            + Schedule::SIZE
+            + BonusTier::SIZE * MAX_BONUS_TIERS + 8
+            + 8 + 8 + 8 + 8 + 1 + 1 + 1 + 8 + 1 + 1
+            + RAFFLE_RESERVED_BYTES
+            + 4 + 32 * ticket_count
+    }
+
+    /// Makes sure a round is open for entries at `now`, first rolling a round that
+    /// ended without tickets forward to the schedule's current window. Entries
+    /// close `sales_cutoff` seconds before the round ends.
+    pub fn require_open_round(&mut self, now: i64) -> Result<()> {
+        self.status.require_open()?;
+        if self.tickets.is_empty() && now >= self.end_time {
+            self.open_next_round(now)?;
+        }
+        require!(self.start_time != NO_ROUND, RaffleError::NoRoundScheduled);
+        require!(now >= self.start_time, RaffleError::RoundNotStarted);
+        require!(now < self.end_time.saturating_sub(self.sales_cutoff), RaffleError::SalesClosed);
+        Ok(())
+    }
+
+    /// Splits the cost of an entry into the jackpot share and the fee paid to the
+    /// treasury now, which is nothing when the fee comes out of the winnings.
+    pub fn split_entry(&self, cost: u64) -> Result<(u64, u64)> {
+        match self.fee_mode {
+            FeeMode::Upfront => split_payment(cost),
+            FeeMode::OnWinnings => Ok((cost, 0)),
+        }
+    }
+
+    /// Splits the jackpot into the winner's prize and the house fee taken at draw time.
+    pub fn split_payout(&self) -> Result<(u64, u64)> {
+        match self.fee_mode {
+            FeeMode::Upfront => Ok((self.jackpot, 0)),
+            FeeMode::OnWinnings => split_payment(self.jackpot),
+        }
+    }
+
+    /// Lamports the reserve adds to `prize` to reach the minimum prize. Asset
+    /// prize rounds pay out the asset, so they are never topped up.
+    pub fn min_prize_top_up(&self, prize: u64) -> u64 {
+        if self.asset_round {
+            return 0;
+        }
+        self.min_prize.saturating_sub(prize)
+    }
+
+    /// Removes the last `count` tickets owned by `wallet` from the ledger, keeping
+    /// the order of every other entry.
+    pub fn remove_tickets(&mut self, wallet: &Pubkey, count: u64) -> Result<()> {
+        let owned = self.tickets.iter().filter(|ticket| *ticket == wallet).count() as u64;
+        let mut keep = owned.checked_sub(count).ok_or(RaffleError::NotEnoughTickets)?;
+        self.tickets.retain(|ticket| {
+            if ticket != wallet {
+                return true;
+            }
+            let kept = keep > 0;
+            keep = keep.saturating_sub(1);
+            kept
+        });
+        Ok(())
+    }
+
+    /// Opens the first round the schedule allows after `after`, or parks the
+    /// raffle at `NO_ROUND`, settled, once the schedule has no rounds left.
+    pub fn open_next_round(&mut self, after: i64) -> Result<()> {
+        let (start_time, end_time, status) = match self.schedule.next_window(after)? {
+            Some((start_time, end_time)) => (start_time, end_time, RoundStatus::Open),
+            None => (NO_ROUND, NO_ROUND, RoundStatus::Settled),
+        };
+        self.start_time = start_time;
+        self.end_time = end_time;
+        self.status = status;
+        Ok(())
+    }
+
+    /// Books `fee` as held in the vault when fees are held for withdrawal,
+    /// returning the part of it to send to the treasury now.
+    pub fn hold_fee(&mut self, fee: u64) -> Result<u64> {
+        if !self.hold_fees {
+            return Ok(fee);
+        }
+        self.unpaid_fees = self.unpaid_fees.checked_add(fee).ok_or(RaffleError::ArithmeticOverflow)?;
+        Ok(0)
+    }
+
+    /// Moves the round to `status` if its current status allows it.
+    pub fn transition(&mut self, status: RoundStatus) -> Result<()> {
+        self.status.check_transition(status)?;
+        self.status = status;
+        Ok(())
+    }
+}
+
+/// When the `TREASURY_FEE_PERCENT` house fee is charged.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FeeMode {
+    /// Taken from every entry, the rest going into the jackpot.
+    #[default]
+    Upfront,
+    /// Entries go into the jackpot in full and the fee is taken from the prize.
+    OnWinnings,
+}
+
+/// Where the current round is in its lifecycle. Only the moves allowed by
+/// `check_transition` are made.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RoundStatus {
+    /// Taking entries until the sales cutoff.
+    #[default]
+    Open,
+    /// Locked by `lock_round`, waiting for the draw.
+    SalesClosed,
+    /// Entropy fixed, the ticket ledger being hashed.
+    Drawing,
+    /// Winner drawn, the prize not yet paid.
+    PayingOut,
+    /// Stopped by the admin before any entry, until the raffle is rescheduled.
+    Cancelled,
+    /// The last scheduled round has been paid out.
+    Settled,
+}
+
+impl RoundStatus {
+    /// Checks that a round in this status may move to `to`, failing with the
+    /// error that names what stands in the way.
+    pub fn check_transition(self, to: RoundStatus) -> Result<()> {
+        use RoundStatus::*;
+        match (self, to) {
+            (Open, Open | SalesClosed | Cancelled)
+            | (SalesClosed, SalesClosed | Drawing)
+            | (Drawing, PayingOut)
+            | (PayingOut, Open | Settled)
+            | (Cancelled | Settled, Open) => Ok(()),
+            (Open, Drawing) => err!(RaffleError::RoundNotLocked),
+            (Open, PayingOut | Settled) | (SalesClosed, Open | PayingOut | Settled) => err!(RaffleError::DrawNotStarted),
+            (SalesClosed | Drawing | PayingOut, Cancelled) => err!(RaffleError::RoundInProgress),
+            (Drawing | PayingOut, SalesClosed | Drawing) => err!(RaffleError::DrawAlreadyStarted),
+            (Drawing, Open | Settled) => err!(RaffleError::LedgerNotHashed),
+            (PayingOut, PayingOut) => err!(RaffleError::LedgerAlreadyHashed),
+            (Cancelled, _) => err!(RaffleError::RoundCancelled),
+            (Settled, _) => err!(RaffleError::NoRoundScheduled),
+        }
+    }
+
+    /// Checks that the round takes entries.
+    pub fn require_open(self) -> Result<()> {
+        match self {
+            RoundStatus::Open => Ok(()),
+            RoundStatus::SalesClosed | RoundStatus::Drawing | RoundStatus::PayingOut => err!(RaffleError::SalesClosed),
+            RoundStatus::Cancelled => err!(RaffleError::RoundCancelled),
+            RoundStatus::Settled => err!(RaffleError::NoRoundScheduled),
+        }
+    }
+}
+
+#[account]
+pub struct Subscription {
+    pub subscriber: Pubkey,       // Wallet the tickets are entered for
+    pub tickets_per_round: u64,   // Tickets entered into each round
+    pub rounds_remaining: u64,    // Prepaid rounds not yet entered
+    pub last_round_end: i64,      // End time of the last round entered, to enter each round once
+}
+
+impl Subscription {
+    pub const SPACE: usize = 8 + 32 + 8 + 8 + 8;
+}
+
+#[account]
+pub struct Promotion {
+    pub round_end_time: i64,      // End time of the round the free tickets are for
+    pub merkle_root: [u8; 32],    // Root of the (wallet, ticket_count) grants
+}
+
+impl Promotion {
+    pub const SPACE: usize = 8 + 8 + 32;
+}
+
+#[account]
+pub struct FreeTicketClaim {
+    pub wallet: Pubkey,           // Wallet that claimed
+    pub tickets: u64,             // Free tickets entered
+}
+
+impl FreeTicketClaim {
+    pub const SPACE: usize = 8 + 32 + 8;
+}
+
+/// Layout of `Raffle` accounts created before the version byte existed.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RaffleV0 {
+    pub jackpot: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub tickets: Vec<Pubkey>,
+}
+
+
+#[event]
+pub struct TicketsPurchased {
+    pub payer: Pubkey,        // Wallet that signed and paid
+    pub beneficiary: Pubkey,  // Wallet the tickets were credited to
+    pub tickets: u64,         // Number of tickets bought
+    pub bonus_tickets: u64,   // Holder bonus tickets credited on top
+    pub cost: u64,            // Lamports charged for the tickets
+    pub round_end_time: i64,  // End of the round the tickets entered
+}
+
+#[event]
+pub struct TicketsCancelled {
+    pub wallet: Pubkey,       // Wallet that cancelled, losing its latest tickets
+    pub tickets: u64,         // Paid tickets cancelled
+    pub bonus_tickets: u64,   // Bonus tickets forfeited with them
+    pub refund: u64,          // Lamports refunded from the vault
+    pub penalty: u64,         // Part of the cost kept as the cancellation penalty
+    pub fee_retained: u64,    // Fee already paid to the treasury, kept beyond the penalty
+    pub round_end_time: i64,  // End of the round the tickets left
+}
+
+#[event]
+pub struct FreeTicketsClaimed {
+    pub wallet: Pubkey,       // Wallet the free tickets were entered for
+    pub tickets: u64,         // Number of free tickets
+    pub round_end_time: i64,  // End of the round the tickets entered
+}
+
+#[event]
+pub struct StakeTicketsAllocated {
+    pub wallet: Pubkey,       // Staker the tickets were entered for
+    pub tickets: u64,         // Number of tickets earned by the stake
+    pub round_end_time: i64,  // End of the round the tickets entered
+}
+
+#[event]
+pub struct WithdrawalQueued {
+    pub amount: u64,          // Lamports of held fees to withdraw
+    pub destination: Pubkey,  // Wallet receiving them
+    pub unlock_time: i64,     // Earliest time the withdrawal can run
+}
+
+#[event]
+pub struct WithdrawalExecuted {
+    pub amount: u64,          // Lamports paid out of the held fees
+    pub destination: Pubkey,  // Wallet that received them
+}
+
+#[event]
+pub struct WithdrawalCancelled {
+    pub amount: u64,          // Lamports the withdrawal would have paid
+    pub destination: Pubkey,  // Wallet it was queued for
+}
+
+#[error_code]
+pub enum RaffleError {
+    #[msg("The amount sent is insufficient to buy a ticket.")]
+    InsufficientFunds,
+
+    #[msg("The amount sent does not match the ticket price.")]
+    IncorrectAmount,
+
+    #[msg("The provided treasury wallet is invalid.")]
+    InvalidTreasuryWallet,
+
+    #[msg("Failed to transfer funds.")]
+    TransferFailed,
+
+    #[msg("The raffle is still active. Cannot end the raffle before the end time.")]
+    RaffleStillActive,
+
+    #[msg("No tickets have been purchased for this raffle.")]
+    NoTickets,
+
+    #[msg("Invalid raffle account.")]
+    InvalidRaffleAccount,
+
+    #[msg("Arithmetic overflow in lamport accounting.")]
+    ArithmeticOverflow,
+
+    #[msg("The vault balance does not cover the jackpot, unpaid fees and rent-exempt minimum.")]
+    VaultImbalance,
+
+    #[msg("The winner account does not match the drawn ticket.")]
+    InvalidWinnerAccount,
+
+    #[msg("The raffle account uses an old layout and must be migrated.")]
+    AccountNeedsMigration,
+
+    #[msg("The raffle account layout version is not supported.")]
+    UnsupportedAccountVersion,
+
+    #[msg("The signer is not allowed to perform this action.")]
+    Unauthorized,
+
+    #[msg("The raffle has no room for that many tickets this round.")]
+    RaffleFull,
+
+    #[msg("The schedule is invalid.")]
+    InvalidSchedule,
+
+    #[msg("The current round has not started yet.")]
+    RoundNotStarted,
+
+    #[msg("No further round is scheduled.")]
+    NoRoundScheduled,
+
+    #[msg("The account is not a subscription of this program.")]
+    InvalidSubscriptionAccount,
+
+    #[msg("The promotion is not for the current round.")]
+    InvalidPromotion,
+
+    #[msg("The Merkle proof does not match the published root.")]
+    InvalidMerkleProof,
+
+    #[msg("The bonus tier table is invalid.")]
+    InvalidBonusTiers,
+
+    #[msg("The token account is not the buyer's PSYDUK account.")]
+    InvalidHolderTokenAccount,
+
+    #[msg("Only PSYDUK can be staked.")]
+    InvalidStakeMint,
+
+    #[msg("The lock duration is out of range.")]
+    InvalidLockDuration,
+
+    #[msg("The stake is still locked.")]
+    StakeLocked,
+
+    #[msg("The stake is in its unstake cooldown.")]
+    StakeCoolingDown,
+
+    #[msg("The account is not a stake position of this program.")]
+    InvalidStakeAccount,
+
+    #[msg("Sales for this round have closed.")]
+    SalesClosed,
+
+    #[msg("Too few slots have passed since the round was locked to draw.")]
+    DrawTooSoon,
+
+    #[msg("The sales cutoff must be shorter than a round.")]
+    InvalidDrawGuards,
+
+    #[msg("The round must be locked before it is drawn.")]
+    RoundNotLocked,
+
+    #[msg("The round is already locked.")]
+    RoundAlreadyLocked,
+
+    #[msg("The draw slot's hash is no longer available; lock the round again.")]
+    DrawSlotExpired,
+
+    #[msg("The SlotHashes sysvar data is malformed.")]
+    InvalidSlotHashes,
+
+    #[msg("The draw transcript does not match the ticket ledger or its own inputs.")]
+    InvalidDrawTranscript,
+
+    #[msg("The round already has tickets.")]
+    RoundInProgress,
+
+    #[msg("The asset prize accounts are missing or don't match the round's prize.")]
+    InvalidAssetPrize,
+
+    #[msg("The creator account does not match the asset prize's creator.")]
+    InvalidCreatorAccount,
+
+    #[msg("The asset prize has not been deposited yet.")]
+    PrizeNotDeposited,
+
+    #[msg("The asset prize has already been deposited.")]
+    PrizeAlreadyDeposited,
+
+    #[msg("The wallet has fewer cancellable tickets this round than requested.")]
+    NotEnoughTickets,
+
+    #[msg("The cancellation penalty cannot exceed 100%.")]
+    InvalidCancelPenalty,
+
+    #[msg("The reserve cannot cover the guaranteed minimum prize.")]
+    InsufficientReserve,
+
+    #[msg("A batched draw of this round has already begun.")]
+    DrawAlreadyStarted,
+
+    #[msg("No batched draw of this round has begun.")]
+    DrawNotStarted,
+
+    #[msg("The draw has not hashed the whole ticket ledger yet.")]
+    LedgerNotHashed,
+
+    #[msg("The draw has already hashed the whole ticket ledger.")]
+    LedgerAlreadyHashed,
+
+    #[msg("The round has been cancelled.")]
+    RoundCancelled,
+
+    #[msg("The vault holds fewer unpaid fees than requested.")]
+    InsufficientFees,
+
+    #[msg("The withdrawal is still timelocked.")]
+    WithdrawalLocked,
+
+    #[msg("The destination does not match the queued withdrawal.")]
+    InvalidWithdrawalDestination,
+
+    #[msg("The wallet is on the deny list.")]
+    WalletDenied,
+
+    #[msg("The deny list is full.")]
+    DenyListFull,
+
+    #[msg("Every redraw picked a ticket of a denied wallet.")]
+    NoEligibleWinner,
+
+    #[msg("The price feed is missing, not the configured one or unreadable.")]
+    InvalidPriceFeed,
+
+    #[msg("The price feed has not been updated recently enough.")]
+    StalePrice,
+
+    #[msg("The price feed's confidence interval is too wide.")]
+    PriceTooUncertain,
+
+    #[msg("The USD pricing settings are out of range.")]
+    InvalidPriceConfig,
+
+    #[msg("The asset prize can only be reclaimed once its round is cancelled or has ended unsold.")]
+    PrizeNotReclaimable,
+
+    #[msg("Tickets in an asset prize round can't be cancelled.")]
+    AssetTicketsNotCancellable,
+
+    #[msg("The buyer's ticket receipt is required for tickets it keeps.")]
+    MissingReceipt,
+
+    #[msg("The deny list can only change while the round is open.")]
+    DenyListLocked,
+
+    #[msg("Subscriptions are unavailable while tickets are priced in USD.")]
+    SubscriptionsUnavailable,
+
+    #[msg("The price feed's publish time is in the future.")]
+    FuturePrice,
+
+    #[msg("An instruction can add at most MAX_TICKETS_PER_INSTRUCTION tickets to the round.")]
+    TooManyTicketsPerInstruction,
 }
@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::keccak;
+
+const LEAF_PREFIX: &[u8] = &[0]; // Domain separation so a leaf can never pass as an inner node
+const NODE_PREFIX: &[u8] = &[1];
+
+/// Leaf of a free ticket tree granting `ticket_count` tickets to `wallet`.
+pub fn free_ticket_leaf(wallet: &Pubkey, ticket_count: u64) -> [u8; 32] {
+    keccak::hashv(&[LEAF_PREFIX, wallet.as_ref(), &ticket_count.to_le_bytes()]).to_bytes()
+}
+
+/// Parent of two nodes. The pair is sorted first, so proofs need no left/right flags.
+pub fn merkle_parent(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    keccak::hashv(&[NODE_PREFIX, left, right]).to_bytes()
+}
+
+/// Whether `proof` links `leaf` to `root`.
+pub fn verify_merkle_proof(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    proof.iter().fold(leaf, |node, sibling| merkle_parent(&node, sibling)) == *root
+}
@@ -0,0 +1,121 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use std::str::FromStr;
+
+use crate::RaffleError;
+
+pub const PRICE_CONFIG_SEED: &[u8] = b"price_config"; // Fixed seed for the USD pricing config PDA
+pub const MAX_CONFIDENCE_BPS: u16 = 10_000; // Upper bound of the accepted confidence interval
+pub const MAX_PRICE_EXPONENT: i32 = 18; // Largest power of ten a feed price may be scaled by, either way
+pub const PYTH_RECEIVER_PROGRAM: &str = "rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ"; // Pyth program owning PriceUpdateV2 accounts
+
+const LAMPORTS_PER_SOL: u128 = 1_000_000_000;
+const CENTS_PER_USD: u128 = 100;
+
+/// USD ticket pricing, set with `set_usd_pricing`. The account only exists once
+/// the admin has configured it, and a zero price leaves tickets at `TICKET_PRICE`.
+#[account]
+pub struct PriceConfig {
+    pub ticket_price_cents: u64,  // Ticket price in USD cents, 0 for the fixed lamport price
+    pub price_feed: Pubkey,       // Pyth PriceUpdateV2 account publishing the SOL/USD price
+    pub max_price_age: i64,       // Seconds after which a published price is stale
+    pub max_confidence_bps: u16,  // Widest confidence interval accepted, in basis points of the price
+}
+
+impl PriceConfig {
+    pub const SPACE: usize = 8 + 8 + 32 + 8 + 2;
+
+    /// Lamports one ticket costs at `feed`'s price, rounded up so a ticket never
+    /// sells below its USD price. Fails if the price is older than
+    /// `max_price_age` at `now`, claims to be published after `now` or is less
+    /// certain than `max_confidence_bps` allows.
+    pub fn ticket_price(&self, feed: &PriceFeed, now: i64) -> Result<u64> {
+        require!(
+            feed.price > 0 && feed.exponent.abs() <= MAX_PRICE_EXPONENT,
+            RaffleError::InvalidPriceFeed
+        );
+        require!(feed.publish_time <= now, RaffleError::FuturePrice);
+        let age = now.checked_sub(feed.publish_time).ok_or(RaffleError::StalePrice)?;
+        require!(age <= self.max_price_age, RaffleError::StalePrice);
+        let price = feed.price as u128;
+        require!(
+            feed.confidence as u128 * MAX_CONFIDENCE_BPS as u128 <= price * self.max_confidence_bps as u128,
+            RaffleError::PriceTooUncertain
+        );
+
+        // cents * LAMPORTS_PER_SOL / (CENTS_PER_USD * price * 10^exponent)
+        let mut numerator = self.ticket_price_cents as u128 * LAMPORTS_PER_SOL;
+        let mut denominator = CENTS_PER_USD * price;
+        let scale = 10u128.pow(feed.exponent.unsigned_abs());
+        if feed.exponent < 0 {
+            numerator = numerator.checked_mul(scale).ok_or(RaffleError::ArithmeticOverflow)?;
+        } else {
+            denominator = denominator.checked_mul(scale).ok_or(RaffleError::ArithmeticOverflow)?;
+        }
+        let lamports = numerator.div_ceil(denominator);
+        require!(lamports > 0, RaffleError::InvalidPriceFeed);
+        u64::try_from(lamports).map_err(|_| error!(RaffleError::ArithmeticOverflow))
+    }
+}
+
+/// Address of the Pyth receiver program, which owns the price accounts the
+/// raffle reads.
+pub fn pyth_receiver_program() -> Pubkey {
+    Pubkey::from_str(PYTH_RECEIVER_PROGRAM).unwrap()
+}
+
+/// The SOL/USD price published in a Pyth price account.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PriceFeed {
+    pub price: i64,               // USD price of one SOL, scaled by 10^exponent
+    pub confidence: u64,          // Confidence interval around the price, same scale
+    pub exponent: i32,            // Power of ten the price is scaled by
+    pub publish_time: i64,        // When the price was published
+}
+
+impl PriceFeed {
+    /// Decodes the data of a Pyth `PriceUpdateV2` account, checking its
+    /// discriminator. Only updates whose Wormhole signatures were fully verified
+    /// are accepted.
+    pub fn read(data: &[u8]) -> Result<Self> {
+        let discriminator = hash(b"account:PriceUpdateV2").to_bytes();
+        require!(data.get(..8) == Some(&discriminator[..8]), RaffleError::InvalidPriceFeed);
+        let update = PriceUpdateV2::deserialize(&mut &data[8..]).map_err(|_| error!(RaffleError::InvalidPriceFeed))?;
+        require!(update.verification_level == VerificationLevel::Full, RaffleError::InvalidPriceFeed);
+        let message = update.price_message;
+        Ok(Self {
+            price: message.price,
+            confidence: message.conf,
+            exponent: message.exponent,
+            publish_time: message.publish_time,
+        })
+    }
+}
+
+/// Layout of a Pyth `PriceUpdateV2` account after its discriminator.
+#[derive(AnchorDeserialize)]
+struct PriceUpdateV2 {
+    _write_authority: Pubkey,
+    verification_level: VerificationLevel,
+    price_message: PriceFeedMessage,
+    _posted_slot: u64,
+}
+
+/// How many guardian signatures of the Wormhole message were checked.
+#[derive(AnchorDeserialize, PartialEq, Eq)]
+enum VerificationLevel {
+    Partial { _num_signatures: u8 },
+    Full,
+}
+
+#[derive(AnchorDeserialize)]
+struct PriceFeedMessage {
+    _feed_id: [u8; 32],
+    price: i64,
+    conf: u64,
+    exponent: i32,
+    publish_time: i64,
+    _prev_publish_time: i64,
+    _ema_price: i64,
+    _ema_conf: u64,
+}
@@ -0,0 +1,77 @@
+use anchor_lang::prelude::*;
+
+use crate::{RaffleError, RAFFLE_DURATION};
+
+pub const ROUND_GAP: i64 = 10; // Seconds between a draw and the next rolling round
+pub const NO_ROUND: i64 = i64::MAX; // Start and end time while no further round is scheduled
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScheduleKind {
+    /// Each round lasts `RAFFLE_DURATION` from when it was opened, as before schedules existed.
+    #[default]
+    Rolling,
+    /// Rounds close at every `anchor + k * period` and open `duration` seconds before.
+    Recurring,
+    /// A single round closing at `anchor`, open `duration` seconds before.
+    OneOff,
+}
+
+/// When rounds open and close. Every field is fixed size so the schedule can live
+/// in the Raffle header, and the zeroed value is the rolling schedule.
+///
+/// Every 15 minutes on the clock: `Recurring`, anchor 0, period 900, duration 900.
+/// Daily draw at 20:00 UTC: `Recurring`, anchor 72_000, period 86_400, duration 86_400.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Schedule {
+    pub kind: ScheduleKind,
+    pub anchor: i64,   // Unix timestamp of one round's close
+    pub period: i64,   // Seconds between closes of recurring rounds
+    pub duration: i64, // Seconds a round is open before it closes
+}
+
+impl Schedule {
+    pub const SIZE: usize = 1 + 8 + 8 + 8;
+
+    pub fn validate(&self, now: i64) -> Result<()> {
+        let valid = match self.kind {
+            ScheduleKind::Rolling => true,
+            ScheduleKind::Recurring => self.period > 0 && self.duration > 0 && self.duration <= self.period,
+            ScheduleKind::OneOff => self.duration > 0 && self.anchor > now,
+        };
+        require!(valid, RaffleError::InvalidSchedule);
+        Ok(())
+    }
+
+    /// How long each round is open.
+    pub fn round_duration(&self) -> i64 {
+        match self.kind {
+            ScheduleKind::Rolling => RAFFLE_DURATION,
+            ScheduleKind::Recurring | ScheduleKind::OneOff => self.duration,
+        }
+    }
+
+    /// Start and end of the first round closing after `after`, or `None` once a
+    /// one-off round has passed.
+    pub fn next_window(&self, after: i64) -> Result<Option<(i64, i64)>> {
+        let end = match self.kind {
+            ScheduleKind::Rolling => {
+                let end = after.checked_add(RAFFLE_DURATION).ok_or(RaffleError::ArithmeticOverflow)?;
+                return Ok(Some((after, end)));
+            }
+            ScheduleKind::Recurring => {
+                let periods = after
+                    .checked_sub(self.anchor)
+                    .map(|elapsed| elapsed.div_euclid(self.period) + 1)
+                    .ok_or(RaffleError::ArithmeticOverflow)?;
+                periods
+                    .checked_mul(self.period)
+                    .and_then(|offset| offset.checked_add(self.anchor))
+                    .ok_or(RaffleError::ArithmeticOverflow)?
+            }
+            ScheduleKind::OneOff if self.anchor > after => self.anchor,
+            ScheduleKind::OneOff => return Ok(None),
+        };
+        let start = end.checked_sub(self.duration).ok_or(RaffleError::ArithmeticOverflow)?;
+        Ok(Some((start, end)))
+    }
+}
@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+pub const STAKE_POOL_SEED: &[u8] = b"stake_pool"; // Fixed seed for the stake pool PDA, authority of the vault
+pub const STAKE_VAULT_SEED: &[u8] = b"stake_vault"; // Fixed seed for the token account holding staked PSYDUK
+pub const STAKE_SEED: &[u8] = b"stake"; // Seed prefix for per-wallet stake position PDAs
+pub const MAX_STAKE_LOCK: i64 = 365 * 24 * 60 * 60; // Longest lock, earning double tickets
+pub const UNSTAKE_COOLDOWN: i64 = 7 * 24 * 60 * 60; // Wait between requesting an unstake and withdrawing
+
+#[account]
+pub struct StakePool {
+    pub mint: Pubkey,                 // Staked token
+    pub tokens_per_ticket: u64,       // Staked base units earning one ticket per round, before the lock bonus
+    pub max_tickets_per_stake: u64,   // Cap on the tickets one position earns per round
+    pub total_staked: u64,            // Tokens held in the stake vault
+    pub bump: u8,                     // Bump of this PDA, which signs for the vault
+}
+
+impl StakePool {
+    pub const SPACE: usize = 8 + 32 + 8 + 8 + 8 + 1;
+
+    /// Tickets a position earns each round at `now`. Staked tokens buy tickets at
+    /// `tokens_per_ticket`, boosted linearly by the lock still to run up to double
+    /// at `MAX_STAKE_LOCK`, and capped at `max_tickets_per_stake`. The boost runs
+    /// down with the lock and is gone once it lapses.
+    pub fn tickets_for(&self, position: &StakePosition, now: i64) -> u64 {
+        let base = position.amount / self.tokens_per_ticket;
+        let lock = position.locked_until.saturating_sub(now).clamp(0, MAX_STAKE_LOCK) as u128;
+        let boosted = base as u128 * (MAX_STAKE_LOCK as u128 + lock) / MAX_STAKE_LOCK as u128;
+        (boosted as u64).min(self.max_tickets_per_stake)
+    }
+}
+
+#[account]
+pub struct StakePosition {
+    pub owner: Pubkey,                // Wallet that staked
+    pub amount: u64,                  // Tokens staked
+    pub lock_duration: i64,           // Longest lock committed to
+    pub locked_until: i64,            // Earliest time an unstake can be requested, the time left sets the ticket bonus
+    pub cooldown_ends: i64,           // When the tokens can be withdrawn, 0 while staked
+    pub last_round_end: i64,          // End time of the last round entered, to enter each round once
+}
+
+impl StakePosition {
+    pub const SPACE: usize = 8 + 32 + 8 + 8 + 8 + 8 + 8;
+
+    /// Whether the position still earns tickets, i.e. no unstake has been requested.
+    pub fn is_active(&self) -> bool {
+        self.cooldown_ends == 0
+    }
+}
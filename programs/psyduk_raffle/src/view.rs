@@ -0,0 +1,66 @@
+use anchor_lang::prelude::*;
+
+use crate::{FeeMode, Raffle, RoundStatus};
+
+const PARTS_PER_MILLION: u128 = 1_000_000;
+
+/// The current round at a glance, as returned by `get_round_summary`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RoundSummary {
+    pub status: RoundStatus,      // Where the round is in its lifecycle
+    pub start_time: i64,          // Round start timestamp
+    pub end_time: i64,            // Round end timestamp
+    pub sales_close_time: i64,    // Timestamp from which entries are refused
+    pub jackpot: u64,             // Lamports in the prize pool
+    pub prize: u64,               // Lamports the winner would take now, after fees and top-up
+    pub tickets: u64,             // Entries in the ledger, bonus tickets included
+    pub bonus_tickets: u64,       // Holder bonus tickets among them
+    pub draw_slot: u64,           // Target slot of the draw, 0 until locked
+    pub fee_mode: FeeMode,        // When the house fee is taken
+    pub asset_round: bool,        // Whether the prize is an escrowed asset
+}
+
+impl RoundSummary {
+    pub fn of(raffle: &Raffle) -> Result<Self> {
+        let (prize, _) = raffle.split_payout()?;
+        Ok(Self {
+            status: raffle.status,
+            start_time: raffle.start_time,
+            end_time: raffle.end_time,
+            sales_close_time: raffle.end_time.saturating_sub(raffle.sales_cutoff),
+            jackpot: raffle.jackpot,
+            prize: prize + raffle.min_prize_top_up(prize),
+            tickets: raffle.tickets.len() as u64,
+            bonus_tickets: raffle.bonus_tickets,
+            draw_slot: raffle.draw_slot,
+            fee_mode: raffle.fee_mode,
+            asset_round: raffle.asset_round,
+        })
+    }
+}
+
+/// A wallet's chance of winning the current round, as returned by `get_win_probability`.
+/// The exact odds are `tickets / total_tickets`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WinProbability {
+    pub tickets: u64,             // Tickets owned by the wallet
+    pub total_tickets: u64,       // Tickets in the round
+    pub parts_per_million: u64,   // The odds in millionths, rounded down
+}
+
+impl WinProbability {
+    pub fn of(raffle: &Raffle, wallet: &Pubkey) -> Self {
+        let tickets = tickets_of(raffle, wallet);
+        let total_tickets = raffle.tickets.len() as u64;
+        let parts_per_million = match total_tickets {
+            0 => 0,
+            total => (tickets as u128 * PARTS_PER_MILLION / total as u128) as u64,
+        };
+        Self { tickets, total_tickets, parts_per_million }
+    }
+}
+
+/// Number of tickets `wallet` holds in the current round.
+pub fn tickets_of(raffle: &Raffle, wallet: &Pubkey) -> u64 {
+    raffle.tickets.iter().filter(|ticket| *ticket == wallet).count() as u64
+}
@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+pub const WITHDRAWAL_SEED: &[u8] = b"withdrawal"; // Fixed seed for the queued withdrawal PDA
+pub const WITHDRAWAL_DELAY: i64 = 2 * 24 * 60 * 60; // Seconds a queued withdrawal waits before it can run
+
+/// A withdrawal of held treasury fees waiting out its timelock. Only one can be
+/// queued at a time, and it stays on-chain for anyone to inspect until it is
+/// executed or cancelled.
+#[account]
+pub struct Withdrawal {
+    pub amount: u64,              // Lamports to take out of the held fees
+    pub destination: Pubkey,      // Wallet receiving them
+    pub queued_at: i64,           // When the withdrawal was queued
+    pub unlock_time: i64,         // Earliest time it can be executed
+}
+
+impl Withdrawal {
+    pub const SPACE: usize = 8 + 8 + 32 + 8 + 8;
+
+    /// Whether the timelock has run out at `now`.
+    pub fn is_unlocked(&self, now: i64) -> bool {
+        now >= self.unlock_time
+    }
+}
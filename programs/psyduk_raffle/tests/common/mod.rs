@@ -0,0 +1,633 @@
+//! Minimal in-process runtime for running the raffle program without a validator.
+//!
+//! Instructions are serialized into the same input buffer layout the BPF loader
+//! uses and handed to the program's real `entry`, or the mock price feed's for
+//! feed updates. Syscalls are served by the stubs below, which emulate the
+//! sysvars and the system program CPIs the programs make.
+//! The SlotHashes sysvar account is kept in step with the clock.
+//! Token and associated token program CPIs run their real processors.
+//! After each instruction the runtime checks mirror the ones a validator enforces:
+//! balanced lamports, no spending or rewriting of accounts the program doesn't own,
+//! no changes to read-only accounts and rent-exempt balances at the end of a transaction.
+
+#![allow(dead_code)]
+
+use anchor_lang::solana_program::{
+    account_info::AccountInfo,
+    bpf_loader_upgradeable,
+    clock::Clock,
+    entrypoint::{deserialize, ProgramResult, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER},
+    hash::{hashv, Hash},
+    instruction::Instruction,
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    program_utils::limited_deserialize,
+    pubkey::Pubkey,
+    rent::Rent,
+    slot_hashes::MAX_ENTRIES,
+    system_instruction::{SystemError, SystemInstruction},
+    system_program, sysvar,
+};
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_spl::associated_token::spl_associated_token_account;
+use anchor_spl::token::spl_token;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Once;
+
+/// Starting clock for every runtime, an arbitrary but realistic timestamp.
+pub const GENESIS_TIMESTAMP: i64 = 1_735_689_600;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Account {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+}
+
+pub struct Svm {
+    accounts: HashMap<Pubkey, Account>,
+    pub clock: Clock,
+    pub rent: Rent,
+    pub logs: Vec<String>,
+    pub event_data: Vec<Vec<u8>>,
+    pub return_data: Option<(Pubkey, Vec<u8>)>,
+    pub upgrade_authority: Pubkey,
+}
+
+/// State shared with the syscall stubs while an instruction executes.
+struct InvokeContext {
+    program_id: Pubkey,
+    clock: Clock,
+    rent: Rent,
+    logs: Vec<String>,
+    event_data: Vec<Vec<u8>>,
+    return_data: Option<(Pubkey, Vec<u8>)>,
+    // Net lamport changes and data rewrites performed through CPIs
+    cpi_lamports: HashMap<Pubkey, i128>,
+    cpi_data: HashSet<Pubkey>,
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Option<InvokeContext>> = const { RefCell::new(None) };
+}
+
+fn with_context<R>(f: impl FnOnce(&mut InvokeContext) -> R) -> R {
+    CONTEXT.with(|context| f(context.borrow_mut().as_mut().expect("no instruction executing")))
+}
+
+impl Svm {
+    pub fn new() -> Self {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(Stubs));
+        });
+
+        let mut svm = Self {
+            accounts: HashMap::new(),
+            clock: Clock {
+                slot: 1,
+                unix_timestamp: GENESIS_TIMESTAMP,
+                ..Clock::default()
+            },
+            rent: Rent::default(),
+            logs: vec![],
+            event_data: vec![],
+            return_data: None,
+            upgrade_authority: Pubkey::new_unique(),
+        };
+
+        svm.set_account(
+            system_program::id(),
+            Account {
+                lamports: 1,
+                owner: Pubkey::from_str("NativeLoader1111111111111111111111111111111").unwrap(),
+                executable: true,
+                ..Account::default()
+            },
+        );
+
+        for program in [spl_token::id(), spl_associated_token_account::id(), mock_price_feed::ID] {
+            svm.set_account(
+                program,
+                Account {
+                    lamports: 1,
+                    owner: bpf_loader_upgradeable::id(),
+                    executable: true,
+                    ..Account::default()
+                },
+            );
+        }
+
+        // Deploy the program behind the upgradeable loader so `migrate` can find its authority
+        let program_data = Pubkey::find_program_address(&[psyduk_raffle::ID.as_ref()], &bpf_loader_upgradeable::id()).0;
+        let mut program = vec![2, 0, 0, 0];
+        program.extend_from_slice(program_data.as_ref());
+        svm.set_account(
+            psyduk_raffle::ID,
+            Account {
+                lamports: svm.rent.minimum_balance(program.len()),
+                data: program,
+                owner: bpf_loader_upgradeable::id(),
+                executable: true,
+            },
+        );
+        let mut header = vec![3, 0, 0, 0];
+        header.extend_from_slice(&0u64.to_le_bytes());
+        header.push(1);
+        header.extend_from_slice(svm.upgrade_authority.as_ref());
+        svm.set_account(
+            program_data,
+            Account {
+                lamports: svm.rent.minimum_balance(header.len()),
+                data: header,
+                owner: bpf_loader_upgradeable::id(),
+                executable: false,
+            },
+        );
+        svm.update_slot_hashes();
+
+        svm
+    }
+
+    pub fn account(&self, key: &Pubkey) -> Option<&Account> {
+        self.accounts.get(key)
+    }
+
+    pub fn set_account(&mut self, key: Pubkey, account: Account) {
+        self.accounts.insert(key, account);
+    }
+
+    pub fn lamports(&self, key: &Pubkey) -> u64 {
+        self.accounts.get(key).map_or(0, |account| account.lamports)
+    }
+
+    pub fn total_lamports(&self) -> u128 {
+        self.accounts.values().map(|account| account.lamports as u128).sum()
+    }
+
+    /// Creates a funded system account and returns its address.
+    pub fn new_wallet(&mut self, lamports: u64) -> Pubkey {
+        let wallet = Pubkey::new_unique();
+        self.airdrop(&wallet, lamports);
+        wallet
+    }
+
+    /// Creates an SPL token mint at `key` whose supply no one can grow.
+    pub fn new_mint(&mut self, key: Pubkey, decimals: u8) {
+        let mut data = vec![0; spl_token::state::Mint::LEN];
+        spl_token::state::Mint {
+            decimals,
+            is_initialized: true,
+            ..Default::default()
+        }
+        .pack_into_slice(&mut data);
+        self.set_account(
+            key,
+            Account {
+                lamports: self.rent.minimum_balance(data.len()),
+                data,
+                owner: spl_token::id(),
+                executable: false,
+            },
+        );
+    }
+
+    /// Creates an SPL token account holding `amount` of `mint` for `owner`.
+    pub fn new_token_account(&mut self, owner: &Pubkey, mint: &Pubkey, amount: u64) -> Pubkey {
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        spl_token::state::Account {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        }
+        .pack_into_slice(&mut data);
+        let key = Pubkey::new_unique();
+        self.set_account(
+            key,
+            Account {
+                lamports: self.rent.minimum_balance(data.len()),
+                data,
+                owner: spl_token::id(),
+                executable: false,
+            },
+        );
+        key
+    }
+
+    /// Token amount held by the SPL token account at `key`.
+    pub fn token_balance(&self, key: &Pubkey) -> u64 {
+        spl_token::state::Account::unpack(&self.accounts[key].data).unwrap().amount
+    }
+
+    pub fn airdrop(&mut self, key: &Pubkey, lamports: u64) {
+        self.accounts.entry(*key).or_default().lamports += lamports;
+    }
+
+    /// Moves the clock forward, one slot per 400ms like mainnet.
+    pub fn advance_time(&mut self, seconds: i64) {
+        self.clock.unix_timestamp += seconds;
+        self.clock.slot += (seconds.max(0) as u64 * 5).div_ceil(2);
+        self.update_slot_hashes();
+    }
+
+    pub fn advance_slots(&mut self, slots: u64) {
+        self.clock.slot += slots;
+        self.update_slot_hashes();
+    }
+
+    /// Hash the runtime gives `slot`, derived from the slot number alone.
+    pub fn slot_hash(slot: u64) -> Hash {
+        hashv(&[b"slot", &slot.to_le_bytes()])
+    }
+
+    /// Rewrites the SlotHashes sysvar to hold every slot before the current one,
+    /// newest first, up to the sysvar's capacity.
+    fn update_slot_hashes(&mut self) {
+        let slots: Vec<u64> = (self.clock.slot.saturating_sub(MAX_ENTRIES as u64)..self.clock.slot).rev().collect();
+        let mut data = (slots.len() as u64).to_le_bytes().to_vec();
+        for slot in slots {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(Self::slot_hash(slot).as_ref());
+        }
+        self.set_account(
+            sysvar::slot_hashes::id(),
+            Account {
+                // Sized for a full sysvar so the balance never changes
+                lamports: self.rent.minimum_balance(8 + MAX_ENTRIES * 40),
+                data,
+                owner: sysvar::id(),
+                executable: false,
+            },
+        );
+    }
+
+    /// Decodes every event of type `T` the program has emitted so far.
+    pub fn events<T: anchor_lang::Event>(&self) -> Vec<T> {
+        self.event_data
+            .iter()
+            .filter(|data| data.starts_with(&T::DISCRIMINATOR))
+            .map(|data| T::try_from_slice(&data[T::DISCRIMINATOR.len()..]).unwrap())
+            .collect()
+    }
+
+    /// Runs the instructions as one atomic transaction signed by `signers`.
+    pub fn process(&mut self, instructions: &[Instruction], signers: &[Pubkey]) -> Result<(), ProgramError> {
+        let mut working = self.accounts.clone();
+        let mut writable = HashSet::new();
+
+        for instruction in instructions {
+            for meta in &instruction.accounts {
+                if meta.is_signer && !signers.contains(&meta.pubkey) {
+                    return Err(ProgramError::MissingRequiredSignature);
+                }
+                if meta.is_writable {
+                    writable.insert(meta.pubkey);
+                }
+            }
+            self.process_instruction(&mut working, instruction)?;
+        }
+
+        for key in &writable {
+            if let Some(account) = working.get(key) {
+                if account.lamports > 0 && account.lamports < self.rent.minimum_balance(account.data.len()) {
+                    return Err(ProgramError::AccountNotRentExempt);
+                }
+            }
+        }
+        working.retain(|_, account| account.lamports > 0);
+        self.accounts = working;
+
+        Ok(())
+    }
+
+    fn process_instruction(&mut self, working: &mut HashMap<Pubkey, Account>, instruction: &Instruction) -> ProgramResult {
+        let entry = match instruction.program_id {
+            program_id if program_id == psyduk_raffle::ID => psyduk_raffle::entry,
+            program_id if program_id == mock_price_feed::ID => mock_price_feed::entry,
+            program_id => panic!("only the raffle and mock price feed programs can be invoked, not {}", program_id),
+        };
+
+        // Collapse duplicate metas the way the runtime does
+        let mut keys: Vec<Pubkey> = vec![];
+        let mut privileges: HashMap<Pubkey, (bool, bool)> = HashMap::new();
+        for meta in &instruction.accounts {
+            let entry = privileges.entry(meta.pubkey).or_insert_with(|| {
+                keys.push(meta.pubkey);
+                (false, false)
+            });
+            entry.0 |= meta.is_signer;
+            entry.1 |= meta.is_writable;
+        }
+        let pre: HashMap<Pubkey, Account> = keys
+            .iter()
+            .map(|key| (*key, working.get(key).cloned().unwrap_or_default()))
+            .collect();
+
+        let mut buffer = serialize(instruction, &pre, &privileges);
+        CONTEXT.with(|context| {
+            *context.borrow_mut() = Some(InvokeContext {
+                program_id: instruction.program_id,
+                clock: self.clock.clone(),
+                rent: self.rent,
+                logs: vec![],
+                event_data: vec![],
+                return_data: None,
+                cpi_lamports: HashMap::new(),
+                cpi_data: HashSet::new(),
+            });
+        });
+
+        let (result, post) = unsafe {
+            let (program_id, account_infos, data) = deserialize(buffer.as_mut_ptr() as *mut u8);
+            let result = entry(program_id, &account_infos, data);
+            let mut post = HashMap::new();
+            for info in &account_infos {
+                post.entry(*info.key).or_insert_with(|| Account {
+                    lamports: info.lamports(),
+                    data: info.data.borrow().to_vec(),
+                    owner: *info.owner,
+                    executable: info.executable,
+                });
+            }
+            (result, post)
+        };
+
+        let context = CONTEXT.with(|context| context.borrow_mut().take()).unwrap();
+        self.logs.extend(context.logs);
+        self.event_data.extend(context.event_data);
+        self.return_data = context.return_data;
+        result?;
+
+        let program_id = instruction.program_id;
+        let pre_total: u128 = pre.values().map(|account| account.lamports as u128).sum();
+        let post_total: u128 = post.values().map(|account| account.lamports as u128).sum();
+        assert_eq!(pre_total, post_total, "instruction created or destroyed lamports");
+
+        for key in &keys {
+            let (before, after) = (&pre[key], &post[key]);
+            if before == after {
+                continue;
+            }
+            if !privileges[key].1 {
+                // Read-only account modified
+                return Err(ProgramError::InvalidArgument);
+            }
+            let cpi_delta = context.cpi_lamports.get(key).copied().unwrap_or_default();
+            let program_delta = after.lamports as i128 - before.lamports as i128 - cpi_delta;
+            if program_delta < 0 && before.owner != program_id {
+                return Err(ProgramError::IllegalOwner);
+            }
+            let rewritten = before.data != after.data || before.owner != after.owner;
+            if rewritten && before.owner != program_id && !context.cpi_data.contains(key) {
+                return Err(ProgramError::IllegalOwner);
+            }
+        }
+
+        working.extend(post);
+        Ok(())
+    }
+}
+
+/// Lays out accounts and instruction data exactly as the BPF loader does.
+fn serialize(
+    instruction: &Instruction,
+    accounts: &HashMap<Pubkey, Account>,
+    privileges: &HashMap<Pubkey, (bool, bool)>,
+) -> Vec<u64> {
+    let mut bytes: Vec<u8> = vec![];
+    let mut positions: HashMap<Pubkey, u8> = HashMap::new();
+
+    // Every meta is serialized, repeats as references to the first occurrence
+    let metas: Vec<Pubkey> = instruction.accounts.iter().map(|meta| meta.pubkey).collect();
+    bytes.extend_from_slice(&(metas.len() as u64).to_le_bytes());
+    for (index, key) in metas.iter().enumerate() {
+        if let Some(position) = positions.get(key) {
+            bytes.push(*position);
+            bytes.extend_from_slice(&[0; 7]);
+            continue;
+        }
+        positions.insert(*key, index as u8);
+
+        let account = &accounts[key];
+        let (is_signer, is_writable) = privileges[key];
+        bytes.push(NON_DUP_MARKER);
+        bytes.push(is_signer as u8);
+        bytes.push(is_writable as u8);
+        bytes.push(account.executable as u8);
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(key.as_ref());
+        bytes.extend_from_slice(account.owner.as_ref());
+        bytes.extend_from_slice(&account.lamports.to_le_bytes());
+        bytes.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&account.data);
+        bytes.resize(bytes.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+        bytes.resize(bytes.len().next_multiple_of(8), 0);
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+    }
+    bytes.extend_from_slice(&(instruction.data.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&instruction.data);
+    bytes.extend_from_slice(instruction.program_id.as_ref());
+
+    let mut buffer = vec![0u64; bytes.len().div_ceil(8)];
+    unsafe {
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer.as_mut_ptr() as *mut u8, bytes.len());
+    }
+    buffer
+}
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_log(&self, message: &str) {
+        with_context(|context| context.logs.push(message.to_string()));
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        with_context(|context| {
+            context.logs.push(format!("Program data: {:?}", fields));
+            context.event_data.push(fields.concat());
+        });
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = with_context(|context| context.clock.clone());
+        unsafe { *(var_addr as *mut Clock) = clock };
+        0
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let rent = with_context(|context| context.rent);
+        unsafe { *(var_addr as *mut Rent) = rent };
+        0
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        with_context(|context| {
+            context.return_data = (!data.is_empty()).then(|| (context.program_id, data.to_vec()));
+        });
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        with_context(|context| context.return_data.clone())
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let program_id = with_context(|context| context.program_id);
+        let find = |key: &Pubkey| {
+            account_infos
+                .iter()
+                .find(|info| info.key == key)
+                .ok_or(ProgramError::NotEnoughAccountKeys)
+        };
+
+        // The caller can only pass on privileges it holds or signs for with its PDAs
+        for meta in &instruction.accounts {
+            let info = find(&meta.pubkey)?;
+            let pda_signed = signers_seeds
+                .iter()
+                .any(|seeds| Pubkey::create_program_address(seeds, &program_id) == Ok(meta.pubkey));
+            if meta.is_signer && !info.is_signer && !pda_signed {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            if meta.is_writable && !info.is_writable {
+                // Privilege escalation
+                return Err(ProgramError::InvalidArgument);
+            }
+        }
+
+        if instruction.program_id == system_program::id() {
+            let accounts = instruction
+                .accounts
+                .iter()
+                .map(|meta| find(&meta.pubkey))
+                .collect::<Result<Vec<_>, _>>()?;
+            return system_program_processor(instruction, &accounts);
+        }
+
+        if instruction.program_id == spl_token::id() {
+            // Run the real token program on views of the accounts carrying the CPI's privileges
+            let infos = instruction
+                .accounts
+                .iter()
+                .map(|meta| {
+                    let mut info = find(&meta.pubkey)?.clone();
+                    info.is_signer = meta.is_signer;
+                    info.is_writable = meta.is_writable;
+                    Ok(info)
+                })
+                .collect::<Result<Vec<_>, ProgramError>>()?;
+            let before: Vec<(u64, Vec<u8>)> = infos
+                .iter()
+                .map(|info| (info.lamports(), info.data.borrow().to_vec()))
+                .collect();
+            as_program(spl_token::id(), || {
+                spl_token::processor::Processor::process(&spl_token::id(), &infos, &instruction.data)
+            })?;
+            let mut seen = HashSet::new();
+            with_context(|context| {
+                for (info, (lamports, data)) in infos.iter().zip(before) {
+                    if !seen.insert(*info.key) {
+                        continue;
+                    }
+                    *context.cpi_lamports.entry(*info.key).or_default() += info.lamports() as i128 - lamports as i128;
+                    if info.data.borrow()[..] != data[..] {
+                        context.cpi_data.insert(*info.key);
+                    }
+                }
+            });
+            return Ok(());
+        }
+
+        if instruction.program_id == spl_associated_token_account::id() {
+            // Its own CPIs into the system and token programs come back through this stub
+            let infos = instruction
+                .accounts
+                .iter()
+                .map(|meta| find(&meta.pubkey).cloned())
+                .collect::<Result<Vec<_>, ProgramError>>()?;
+            return as_program(instruction.program_id, || {
+                spl_associated_token_account::processor::process_instruction(
+                    &instruction.program_id,
+                    &infos,
+                    &instruction.data,
+                )
+            });
+        }
+
+        panic!("CPI into unsupported program {}", instruction.program_id);
+    }
+}
+
+/// Runs `f` as `program_id`, which then signs with its PDAs and owns any return data it sets.
+fn as_program<R>(program_id: Pubkey, f: impl FnOnce() -> R) -> R {
+    let caller = with_context(|context| std::mem::replace(&mut context.program_id, program_id));
+    let result = f();
+    with_context(|context| context.program_id = caller);
+    result
+}
+
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    if from.lamports() < lamports {
+        return Err(ProgramError::Custom(SystemError::ResultWithNegativeLamports as u32));
+    }
+    **from.try_borrow_mut_lamports()? -= lamports;
+    **to.try_borrow_mut_lamports()? += lamports;
+    with_context(|context| {
+        *context.cpi_lamports.entry(*from.key).or_default() -= lamports as i128;
+        *context.cpi_lamports.entry(*to.key).or_default() += lamports as i128;
+    });
+    Ok(())
+}
+
+fn allocate(account: &AccountInfo, space: u64) -> ProgramResult {
+    if !account.data_is_empty() || *account.owner != system_program::id() {
+        return Err(ProgramError::Custom(SystemError::AccountAlreadyInUse as u32));
+    }
+    account.realloc(space as usize, true)?;
+    with_context(|context| context.cpi_data.insert(*account.key));
+    Ok(())
+}
+
+fn system_program_processor(instruction: &Instruction, accounts: &[&AccountInfo]) -> ProgramResult {
+    let system_instruction: SystemInstruction =
+        limited_deserialize(&instruction.data, 1232).map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    match system_instruction {
+        SystemInstruction::Transfer { lamports } => {
+            let (from, to) = (accounts[0], accounts[1]);
+            if !from.data_is_empty() || *from.owner != system_program::id() {
+                return Err(ProgramError::InvalidArgument);
+            }
+            move_lamports(from, to, lamports)
+        }
+        SystemInstruction::CreateAccount { lamports, space, owner } => {
+            let (from, to) = (accounts[0], accounts[1]);
+            if to.lamports() > 0 {
+                return Err(ProgramError::Custom(SystemError::AccountAlreadyInUse as u32));
+            }
+            allocate(to, space)?;
+            to.assign(&owner);
+            move_lamports(from, to, lamports)
+        }
+        SystemInstruction::Allocate { space } => allocate(accounts[0], space),
+        SystemInstruction::Assign { owner } => {
+            if *accounts[0].owner != system_program::id() {
+                return Err(ProgramError::Custom(SystemError::InvalidProgramId as u32));
+            }
+            accounts[0].assign(&owner);
+            with_context(|context| context.cpi_data.insert(*accounts[0].key));
+            Ok(())
+        }
+        other => panic!("unsupported system instruction {:?}", other),
+    }
+}
@@ -0,0 +1,737 @@
+//! Property tests driving random sequences of raffle operations and checking the
+//! accounting invariants after every step.
+//!
+//! The model only counts tickets and tracks settings. Lamports are checked
+//! against what was observed to flow in and out: the vault must hold exactly
+//! what entries paid in less what the treasury, winners and refunds took out,
+//! and the raffle's books must match the vault.
+
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar;
+use common::Svm;
+use proptest::prelude::*;
+use psyduk_raffle::{
+    psyduk_mint, FeeMode, Raffle, RaffleError, Schedule, Subscription, MAX_TICKETS, MAX_TICKETS_PER_INSTRUCTION,
+    RAFFLE_DURATION, SLOT_HASH_WINDOW, TICKET_PRICE, TREASURY_FEE_PERCENT,
+};
+use psyduk_raffle_client::{instructions, merkle::FreeTicketTree, pda, state, treasury_wallet};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+const SOL: u64 = 1_000_000_000;
+const WALLETS: usize = 4;
+const CANCEL_PENALTY_PERCENT: u64 = 10;
+const TOKENS_PER_TICKET: u64 = 1_000_000;
+
+/// Error the system program returns when `init` meets an existing account.
+const ACCOUNT_ALREADY_IN_USE: ProgramError = ProgramError::Custom(0);
+
+#[derive(Clone, Debug)]
+enum Op {
+    Buy { wallet: usize, amount: u64 },
+    Cancel { wallet: usize, count: u64 },
+    Subscribe { wallet: usize, tickets_per_round: u64, rounds: u64 },
+    Unsubscribe { wallet: usize },
+    ApplySubscriptions,
+    PublishFreeTickets { grants: [u64; WALLETS] },
+    ClaimFreeTickets { wallet: usize },
+    Stake { wallet: usize, tickets: u64 },
+    RequestUnstake { wallet: usize },
+    AllocateStakeTickets,
+    Deny { wallet: usize },
+    Allow { wallet: usize },
+    AdvanceTime(i64),
+    Draw,
+    Reconcile,
+    SetFeeMode(FeeMode),
+    SetHoldFees(bool),
+    SetDrawGuards { sales_cutoff: i64, min_draw_slots: u64 },
+}
+
+/// Ticket counts, small or around the per-instruction cap.
+fn ticket_count() -> impl Strategy<Value = u64> {
+    let max = MAX_TICKETS_PER_INSTRUCTION as u64;
+    prop_oneof![3 => 1..=25u64, 1 => max - 40..=max + 10]
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        6 => (0..WALLETS, 0..=25 * TICKET_PRICE).prop_map(|(wallet, amount)| Op::Buy { wallet, amount }),
+        1 => (0..WALLETS, prop_oneof![
+            Just(TICKET_PRICE - 1),
+            Just(TICKET_PRICE),
+            ticket_count().prop_map(|tickets| tickets * TICKET_PRICE),
+            Just(u64::MAX),
+        ])
+            .prop_map(|(wallet, amount)| Op::Buy { wallet, amount }),
+        2 => (0..WALLETS, 0..=30u64).prop_map(|(wallet, count)| Op::Cancel { wallet, count }),
+        2 => (0..WALLETS, ticket_count(), 1..=3u64)
+            .prop_map(|(wallet, tickets_per_round, rounds)| Op::Subscribe { wallet, tickets_per_round, rounds }),
+        1 => (0..WALLETS).prop_map(|wallet| Op::Unsubscribe { wallet }),
+        2 => Just(Op::ApplySubscriptions),
+        1 => prop::array::uniform4(ticket_count()).prop_map(|grants| Op::PublishFreeTickets { grants }),
+        2 => (0..WALLETS).prop_map(|wallet| Op::ClaimFreeTickets { wallet }),
+        2 => (0..WALLETS, ticket_count()).prop_map(|(wallet, tickets)| Op::Stake { wallet, tickets }),
+        1 => (0..WALLETS).prop_map(|wallet| Op::RequestUnstake { wallet }),
+        2 => Just(Op::AllocateStakeTickets),
+        1 => (0..WALLETS).prop_map(|wallet| Op::Deny { wallet }),
+        1 => (0..WALLETS).prop_map(|wallet| Op::Allow { wallet }),
+        3 => (0..2 * RAFFLE_DURATION).prop_map(Op::AdvanceTime),
+        3 => Just(Op::Draw),
+        1 => Just(Op::Reconcile),
+        1 => prop_oneof![Just(FeeMode::Upfront), Just(FeeMode::OnWinnings)].prop_map(Op::SetFeeMode),
+        1 => any::<bool>().prop_map(Op::SetHoldFees),
+        1 => (-1..=RAFFLE_DURATION, 0..=8u64)
+            .prop_map(|(sales_cutoff, min_draw_slots)| Op::SetDrawGuards { sales_cutoff, min_draw_slots }),
+    ]
+}
+
+/// The paid tickets a wallet bought for itself in one round.
+#[derive(Clone, Copy, Default)]
+struct Receipt {
+    round_end_time: i64,
+    paid_tickets: u64,
+    fees_paid_out: bool, // Whether a purchase sent its fee to the treasury straight away
+}
+
+#[derive(Clone, Copy)]
+struct ModelSubscription {
+    tickets_per_round: u64,
+    rounds_remaining: u64,
+    last_round_end: i64,
+}
+
+#[derive(Clone, Copy)]
+struct ModelStake {
+    amount: u64,
+    active: bool,
+    last_round_end: i64,
+}
+
+/// What the ledger and settings should be, tracked independently of the program,
+/// and the lamports seen moving in and out of the raffle.
+#[derive(Default)]
+struct Model {
+    tickets: HashMap<Pubkey, usize>,
+    receipts: HashMap<Pubkey, Receipt>,
+    subscriptions: HashMap<Pubkey, ModelSubscription>,
+    stakes: HashMap<Pubkey, ModelStake>,
+    promotions: HashMap<i64, [u64; WALLETS]>,
+    claims: HashSet<(i64, Pubkey)>,
+    denied: BTreeSet<Pubkey>,
+    deny_list_created: bool,
+    fee_mode: FeeMode,
+    hold_fees: bool,
+    sales_cutoff: i64,
+    min_draw_slots: u64,
+    last_entry_slot: u64,
+    draw_slot: u64,
+    paid_in: u64,  // Lamports entries paid into the raffle
+    prizes: u64,   // Lamports winners received
+    refunds: u64,  // Lamports cancellations returned
+}
+
+impl Model {
+    fn ticket_count(&self) -> usize {
+        self.tickets.values().sum()
+    }
+
+    fn add_tickets(&mut self, wallet: Pubkey, count: u64, slot: u64) {
+        *self.tickets.entry(wallet).or_default() += count as usize;
+        self.last_entry_slot = slot;
+    }
+
+    /// The error an entry made at `now` should fail with, or the end time of the
+    /// round it goes into once a round that ended without tickets rolls forward.
+    fn entry_round(&self, current: &Raffle, now: i64) -> std::result::Result<i64, RaffleError> {
+        if self.draw_slot != 0 {
+            // Locked and waiting for its draw
+            return Err(RaffleError::SalesClosed);
+        }
+        if self.ticket_count() == 0 && now >= current.end_time {
+            return Ok(now + RAFFLE_DURATION);
+        }
+        if now < current.start_time {
+            // Between a draw and the opening of the next round
+            Err(RaffleError::RoundNotStarted)
+        } else if now >= current.end_time - self.sales_cutoff {
+            Err(RaffleError::SalesClosed)
+        } else {
+            Ok(current.end_time)
+        }
+    }
+
+    /// The stake tickets a position earns, never locked so never boosted.
+    fn stake_tickets(stake: &ModelStake) -> u64 {
+        (stake.amount / TOKENS_PER_TICKET).min(MAX_TICKETS_PER_INSTRUCTION as u64)
+    }
+}
+
+fn expect_error(result: std::result::Result<(), ProgramError>, error: RaffleError) -> std::result::Result<(), TestCaseError> {
+    prop_assert_eq!(result, Err(ProgramError::Custom(error.into())));
+    Ok(())
+}
+
+fn expect_anchor_error(result: std::result::Result<(), ProgramError>, error: ErrorCode) -> std::result::Result<(), TestCaseError> {
+    prop_assert_eq!(result, Err(ProgramError::Custom(error.into())));
+    Ok(())
+}
+
+/// Treasury fee on `cost` lamports of entries.
+fn fee_on(cost: u64) -> u64 {
+    cost * TREASURY_FEE_PERCENT / 100
+}
+
+/// Lamports in the raffle's books before and after an operation.
+struct Books {
+    vault: u64,
+    treasury: u64,
+    unpaid_fees: u64,
+    jackpot: u64,
+}
+
+struct Harness {
+    svm: Svm,
+    admin: Pubkey,
+    crank: Pubkey,
+    wallets: Vec<Pubkey>,
+    token_accounts: Vec<Pubkey>,
+    model: Model,
+}
+
+type Check = std::result::Result<(), TestCaseError>;
+
+impl Harness {
+    fn new() -> Self {
+        let mut svm = Svm::new();
+        let admin = svm.new_wallet(SOL);
+        svm.process(&[instructions::initialize(&admin, Schedule::default())], &[admin]).unwrap();
+        svm.process(&[instructions::set_cancel_penalty(&admin, CANCEL_PENALTY_PERCENT as u8)], &[admin]).unwrap();
+        svm.new_mint(psyduk_mint(), 6);
+        let max_tickets_per_stake = MAX_TICKETS_PER_INSTRUCTION as u64;
+        svm.process(&[instructions::initialize_staking(&admin, TOKENS_PER_TICKET, max_tickets_per_stake)], &[admin])
+            .unwrap();
+        let wallets: Vec<Pubkey> = (0..WALLETS).map(|_| svm.new_wallet(1_000 * SOL)).collect();
+        let token_accounts = wallets
+            .iter()
+            .map(|wallet| svm.new_token_account(wallet, &psyduk_mint(), u64::MAX / 2))
+            .collect();
+        let crank = svm.new_wallet(100 * SOL);
+        Self { svm, admin, crank, wallets, token_accounts, model: Model::default() }
+    }
+
+    fn raffle(&self) -> Raffle {
+        state::decode_raffle(&self.svm.account(&pda::raffle_address()).unwrap().data).unwrap()
+    }
+
+    fn books(&self) -> Books {
+        let raffle = self.raffle();
+        Books {
+            vault: self.svm.lamports(&pda::vault_address()),
+            treasury: self.svm.lamports(&treasury_wallet()),
+            unpaid_fees: raffle.unpaid_fees,
+            jackpot: raffle.jackpot,
+        }
+    }
+
+    /// Checks the fee charged on `cost` lamports of entries between `before` and
+    /// now: the house share up front, or nothing when it comes out of the winnings,
+    /// held in the vault or sent to the treasury as configured.
+    fn check_entry_fee(&self, before: &Books, cost: u64) -> Check {
+        let after = self.books();
+        let (pushed, held) = (after.treasury - before.treasury, after.unpaid_fees - before.unpaid_fees);
+        let fee = match self.model.fee_mode {
+            FeeMode::Upfront => fee_on(cost),
+            FeeMode::OnWinnings => 0,
+        };
+        prop_assert_eq!(pushed + held, fee);
+        prop_assert_eq!(if self.model.hold_fees { pushed } else { held }, 0);
+        prop_assert_eq!(after.jackpot - before.jackpot, cost - fee);
+        Ok(())
+    }
+
+    fn buy(&mut self, wallet: usize, amount: u64) -> Check {
+        let buyer = self.wallets[wallet];
+        let current = self.raffle();
+        let before = self.books();
+        let result = self.svm.process(&[instructions::buy_ticket(&buyer, amount)], &[buyer]);
+        let tickets = amount / TICKET_PRICE;
+        let model = &mut self.model;
+        match model.entry_round(&current, self.svm.clock.unix_timestamp) {
+            Err(error) => expect_error(result, error)?,
+            Ok(_) if model.denied.contains(&buyer) => expect_error(result, RaffleError::WalletDenied)?,
+            Ok(_) if amount < TICKET_PRICE => expect_error(result, RaffleError::InsufficientFunds)?,
+            // Far beyond any wallet, either the fee math or the transfer must refuse it
+            Ok(_) if amount.checked_mul(100).is_none() => prop_assert!(result.is_err()),
+            Ok(_) if tickets > MAX_TICKETS_PER_INSTRUCTION as u64 => {
+                expect_error(result, RaffleError::TooManyTicketsPerInstruction)?
+            }
+            Ok(_) if model.ticket_count() + tickets as usize > MAX_TICKETS => expect_error(result, RaffleError::RaffleFull)?,
+            Ok(round_end_time) => {
+                prop_assert_eq!(result, Ok(()));
+                let cost = tickets * TICKET_PRICE;
+                model.paid_in += cost;
+                model.add_tickets(buyer, tickets, self.svm.clock.slot);
+                let fees_paid_out = model.fee_mode == FeeMode::Upfront && !model.hold_fees;
+                let receipt = model.receipts.entry(buyer).or_default();
+                if receipt.round_end_time != round_end_time {
+                    *receipt = Receipt { round_end_time, ..Receipt::default() };
+                }
+                receipt.paid_tickets += tickets;
+                receipt.fees_paid_out |= fees_paid_out;
+                self.check_entry_fee(&before, cost)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn cancel(&mut self, wallet: usize, count: u64) -> Check {
+        let buyer = self.wallets[wallet];
+        let current = self.raffle();
+        let balance_before = self.svm.lamports(&buyer);
+        let result = self.svm.process(&[instructions::cancel_tickets(&buyer, count)], &[buyer]);
+        let model = &mut self.model;
+        let Some(receipt) = model.receipts.get(&buyer).copied() else {
+            // Nothing was ever bought, so there's no receipt to cancel against
+            return expect_anchor_error(result, ErrorCode::AccountNotInitialized);
+        };
+        match model.entry_round(&current, self.svm.clock.unix_timestamp) {
+            Err(error) => expect_error(result, error)?,
+            Ok(_) if count == 0 => expect_error(result, RaffleError::IncorrectAmount)?,
+            Ok(round_end_time) if receipt.round_end_time != round_end_time || count > receipt.paid_tickets => {
+                expect_error(result, RaffleError::NotEnoughTickets)?
+            }
+            Ok(_) => {
+                prop_assert_eq!(result, Ok(()));
+                let refund = self.svm.lamports(&buyer) - balance_before;
+                model.refunds += refund;
+                *model.tickets.get_mut(&buyer).unwrap() -= count as usize;
+                model.receipts.get_mut(&buyer).unwrap().paid_tickets -= count;
+                model.last_entry_slot = self.svm.clock.slot;
+
+                // The cost comes back less the penalty, and only a fee already
+                // paid out to the treasury can keep the refund below that
+                let cost = count * TICKET_PRICE;
+                let penalty = cost * CANCEL_PENALTY_PERCENT / 100;
+                if receipt.fees_paid_out {
+                    prop_assert!(refund <= cost - penalty, "refund {} above cost {} less penalty", refund, cost);
+                    prop_assert!(refund + 1 >= cost - penalty - fee_on(cost), "refund {} keeps more than the fee", refund);
+                } else {
+                    prop_assert_eq!(refund, cost - penalty);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn subscribe(&mut self, wallet: usize, tickets_per_round: u64, rounds: u64) -> Check {
+        let subscriber = self.wallets[wallet];
+        let balance_before = self.svm.lamports(&subscriber);
+        let result = self.svm.process(&[instructions::subscribe(&subscriber, tickets_per_round, rounds)], &[subscriber]);
+        if self.model.subscriptions.contains_key(&subscriber) {
+            prop_assert_eq!(result, Err(ACCOUNT_ALREADY_IN_USE));
+        } else if tickets_per_round > MAX_TICKETS_PER_INSTRUCTION as u64 {
+            expect_error(result, RaffleError::TooManyTicketsPerInstruction)?;
+        } else {
+            prop_assert_eq!(result, Ok(()));
+            let escrow_rent = self.svm.rent.minimum_balance(Subscription::SPACE);
+            let escrow = tickets_per_round * rounds * TICKET_PRICE;
+            prop_assert_eq!(balance_before - self.svm.lamports(&subscriber), escrow_rent + escrow);
+            let subscription = ModelSubscription { tickets_per_round, rounds_remaining: rounds, last_round_end: 0 };
+            self.model.subscriptions.insert(subscriber, subscription);
+        }
+        Ok(())
+    }
+
+    fn unsubscribe(&mut self, wallet: usize) -> Check {
+        let subscriber = self.wallets[wallet];
+        let balance_before = self.svm.lamports(&subscriber);
+        let result = self.svm.process(&[instructions::unsubscribe(&subscriber)], &[subscriber]);
+        match self.model.subscriptions.remove(&subscriber) {
+            None => expect_anchor_error(result, ErrorCode::AccountNotInitialized)?,
+            Some(subscription) => {
+                prop_assert_eq!(result, Ok(()));
+                let escrow_rent = self.svm.rent.minimum_balance(Subscription::SPACE);
+                let escrow = subscription.tickets_per_round * subscription.rounds_remaining * TICKET_PRICE;
+                prop_assert_eq!(self.svm.lamports(&subscriber) - balance_before, escrow_rent + escrow);
+            }
+        }
+        Ok(())
+    }
+
+    /// Enters as many of `pending`, in order, as fit in the round and in one
+    /// instruction's ledger growth, returning the tickets each one adds.
+    fn batch_entries(&self, pending: Vec<(Pubkey, u64)>) -> Vec<(Pubkey, u64)> {
+        let mut ticket_count = self.model.ticket_count();
+        let mut added = 0;
+        pending
+            .into_iter()
+            .filter(|(_, tickets)| {
+                let tickets = *tickets as usize;
+                let fits = ticket_count + tickets <= MAX_TICKETS && added + tickets <= MAX_TICKETS_PER_INSTRUCTION;
+                if fits {
+                    ticket_count += tickets;
+                    added += tickets;
+                }
+                fits
+            })
+            .collect()
+    }
+
+    fn apply_subscriptions(&mut self) -> Check {
+        let subscribers: Vec<Pubkey> =
+            self.wallets.iter().copied().filter(|wallet| self.model.subscriptions.contains_key(wallet)).collect();
+        let current = self.raffle();
+        let before = self.books();
+        let result = self.svm.process(&[instructions::apply_subscriptions(&self.crank, &subscribers)], &[self.crank]);
+        let round_end_time = match self.model.entry_round(&current, self.svm.clock.unix_timestamp) {
+            Err(error) => return expect_error(result, error),
+            Ok(round_end_time) => round_end_time,
+        };
+        prop_assert_eq!(result, Ok(()));
+
+        let pending = subscribers
+            .iter()
+            .map(|subscriber| (*subscriber, self.model.subscriptions[subscriber]))
+            .filter(|(_, subscription)| subscription.rounds_remaining > 0 && subscription.last_round_end != round_end_time)
+            .map(|(subscriber, subscription)| (subscriber, subscription.tickets_per_round))
+            .collect();
+        let mut cost = 0;
+        for (subscriber, tickets) in self.batch_entries(pending) {
+            let subscription = self.model.subscriptions.get_mut(&subscriber).unwrap();
+            subscription.rounds_remaining -= 1;
+            subscription.last_round_end = round_end_time;
+            cost += tickets * TICKET_PRICE;
+            self.model.add_tickets(subscriber, tickets, self.svm.clock.slot);
+        }
+        self.model.paid_in += cost;
+        self.check_entry_fee(&before, cost)
+    }
+
+    fn publish_free_tickets(&mut self, grants: [u64; WALLETS]) -> Check {
+        let round_end_time = self.raffle().end_time;
+        let tree = FreeTicketTree::new(&self.grants(grants));
+        let result = self.svm.process(
+            &[instructions::publish_free_tickets(&self.admin, round_end_time, tree.root())],
+            &[self.admin],
+        );
+        if self.model.promotions.contains_key(&round_end_time) {
+            prop_assert_eq!(result, Err(ACCOUNT_ALREADY_IN_USE));
+        } else if round_end_time <= self.svm.clock.unix_timestamp {
+            expect_error(result, RaffleError::InvalidPromotion)?;
+        } else {
+            prop_assert_eq!(result, Ok(()));
+            self.model.promotions.insert(round_end_time, grants);
+        }
+        Ok(())
+    }
+
+    fn grants(&self, grants: [u64; WALLETS]) -> Vec<(Pubkey, u64)> {
+        self.wallets.iter().copied().zip(grants).collect()
+    }
+
+    fn claim_free_tickets(&mut self, wallet: usize) -> Check {
+        let claimant = self.wallets[wallet];
+        let current = self.raffle();
+        let Some(grants) = self.model.promotions.get(&current.end_time).copied() else {
+            // The instruction can't even be built with a valid proof, so claim nothing
+            let result = self.svm.process(
+                &[instructions::claim_free_tickets(&claimant, current.end_time, 1, vec![])],
+                &[claimant],
+            );
+            return expect_anchor_error(result, ErrorCode::AccountNotInitialized);
+        };
+        let tickets = grants[wallet];
+        let proof = FreeTicketTree::new(&self.grants(grants)).proof(wallet);
+        let result = self.svm.process(
+            &[instructions::claim_free_tickets(&claimant, current.end_time, tickets, proof)],
+            &[claimant],
+        );
+        let model = &mut self.model;
+        if model.claims.contains(&(current.end_time, claimant)) {
+            prop_assert_eq!(result, Err(ACCOUNT_ALREADY_IN_USE));
+            return Ok(());
+        }
+        match model.entry_round(&current, self.svm.clock.unix_timestamp) {
+            Err(error) => expect_error(result, error)?,
+            Ok(round_end_time) if round_end_time != current.end_time => {
+                expect_error(result, RaffleError::InvalidPromotion)?
+            }
+            Ok(_) if tickets > MAX_TICKETS_PER_INSTRUCTION as u64 => {
+                expect_error(result, RaffleError::TooManyTicketsPerInstruction)?
+            }
+            Ok(_) if model.ticket_count() + tickets as usize > MAX_TICKETS => expect_error(result, RaffleError::RaffleFull)?,
+            Ok(_) => {
+                prop_assert_eq!(result, Ok(()));
+                model.claims.insert((current.end_time, claimant));
+                model.add_tickets(claimant, tickets, self.svm.clock.slot);
+            }
+        }
+        Ok(())
+    }
+
+    fn stake(&mut self, wallet: usize, tickets: u64) -> Check {
+        let owner = self.wallets[wallet];
+        let amount = tickets * TOKENS_PER_TICKET;
+        // Never locked, so every stake earns exactly its base tickets
+        let instruction = instructions::stake(&owner, &self.token_accounts[wallet], amount, 0);
+        let result = self.svm.process(&[instruction], &[owner]);
+        let stake = self.model.stakes.entry(owner).or_insert(ModelStake { amount: 0, active: true, last_round_end: 0 });
+        if !stake.active {
+            expect_error(result, RaffleError::StakeCoolingDown)?;
+        } else {
+            prop_assert_eq!(result, Ok(()));
+            stake.amount += amount;
+        }
+        Ok(())
+    }
+
+    fn request_unstake(&mut self, wallet: usize) -> Check {
+        let owner = self.wallets[wallet];
+        let result = self.svm.process(&[instructions::request_unstake(&owner)], &[owner]);
+        match self.model.stakes.get_mut(&owner) {
+            None => expect_anchor_error(result, ErrorCode::AccountNotInitialized)?,
+            Some(stake) if !stake.active => expect_error(result, RaffleError::StakeCoolingDown)?,
+            Some(stake) => {
+                prop_assert_eq!(result, Ok(()));
+                stake.active = false;
+            }
+        }
+        Ok(())
+    }
+
+    fn allocate_stake_tickets(&mut self) -> Check {
+        let owners: Vec<Pubkey> = self.wallets.iter().copied().filter(|wallet| self.model.stakes.contains_key(wallet)).collect();
+        let current = self.raffle();
+        let before = self.books();
+        let result = self.svm.process(&[instructions::allocate_stake_tickets(&self.crank, &owners)], &[self.crank]);
+        let round_end_time = match self.model.entry_round(&current, self.svm.clock.unix_timestamp) {
+            Err(error) => return expect_error(result, error),
+            Ok(round_end_time) => round_end_time,
+        };
+        prop_assert_eq!(result, Ok(()));
+
+        let pending = owners
+            .iter()
+            .map(|owner| (*owner, self.model.stakes[owner]))
+            .filter(|(_, stake)| stake.active && stake.last_round_end != round_end_time)
+            .map(|(owner, stake)| (owner, Model::stake_tickets(&stake)))
+            .filter(|(_, tickets)| *tickets > 0)
+            .collect();
+        for (owner, tickets) in self.batch_entries(pending) {
+            self.model.stakes.get_mut(&owner).unwrap().last_round_end = round_end_time;
+            self.model.add_tickets(owner, tickets, self.svm.clock.slot);
+        }
+        // Stake tickets are free
+        prop_assert_eq!(self.books().vault, before.vault);
+        Ok(())
+    }
+
+    fn deny(&mut self, wallet: usize, deny: bool) -> Check {
+        let wallet = self.wallets[wallet];
+        let instruction = if deny {
+            instructions::deny_wallet(&self.admin, &wallet)
+        } else {
+            instructions::allow_wallet(&self.admin, &wallet)
+        };
+        let result = self.svm.process(&[instruction], &[self.admin]);
+        if !deny && !self.model.deny_list_created {
+            expect_anchor_error(result, ErrorCode::AccountNotInitialized)?;
+        } else if self.model.draw_slot != 0 {
+            expect_error(result, RaffleError::DenyListLocked)?;
+        } else {
+            prop_assert_eq!(result, Ok(()));
+            self.model.deny_list_created = true;
+            if deny {
+                self.model.denied.insert(wallet);
+            } else {
+                self.model.denied.remove(&wallet);
+            }
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self) -> Check {
+        let mut current = self.raffle();
+        let now = self.svm.clock.unix_timestamp;
+        // Lock the round, or renew a lock whose slot has aged out
+        let model = &mut self.model;
+        if model.draw_slot == 0 || self.svm.clock.slot > model.draw_slot + SLOT_HASH_WINDOW {
+            let result = self.svm.process(&[instructions::lock_round()], &[]);
+            if now < current.end_time - model.sales_cutoff {
+                expect_error(result, RaffleError::RaffleStillActive)?;
+            } else if model.ticket_count() == 0 {
+                expect_error(result, RaffleError::NoTickets)?;
+            } else {
+                prop_assert_eq!(result, Ok(()));
+                model.draw_slot = self.svm.clock.slot.max(model.last_entry_slot + model.min_draw_slots);
+                current = self.raffle();
+            }
+        }
+        self.svm.advance_slots(2);
+        if self.model.draw_slot == 0 {
+            return Ok(());
+        }
+
+        let deny_list = self
+            .svm
+            .account(&pda::deny_list_address())
+            .map(|account| state::decode_deny_list(&account.data).unwrap())
+            .unwrap_or_default();
+        let slot_hashes = &self.svm.account(&sysvar::slot_hashes::id()).unwrap().data;
+        let predicted = state::drawn_winner(&current, slot_hashes, &deny_list).ok();
+        let winner = predicted.unwrap_or_default();
+        let winner_before = self.svm.lamports(&winner);
+        let before = self.books();
+        let result = self.svm.process(&[instructions::select_winner(&self.crank, current.end_time, &winner)], &[self.crank]);
+        let slot = self.svm.clock.slot;
+        let draw_slot = self.model.draw_slot;
+        if now < current.end_time {
+            expect_error(result, RaffleError::RaffleStillActive)?;
+        } else if slot - 1 <= draw_slot {
+            // The first slot after the lock target hasn't been produced yet
+            expect_error(result, RaffleError::DrawTooSoon)?;
+        } else if slot.saturating_sub(SLOT_HASH_WINDOW) > draw_slot {
+            expect_error(result, RaffleError::DrawSlotExpired)?;
+        } else if predicted.is_none() {
+            // Every redraw landed on a denied wallet
+            expect_error(result, RaffleError::NoEligibleWinner)?;
+        } else {
+            prop_assert_eq!(result, Ok(()));
+            let after = self.books();
+            let prize = self.svm.lamports(&winner) - winner_before;
+            let model = &mut self.model;
+            prop_assert!(model.tickets.get(&winner).copied().unwrap_or_default() > 0, "winner holds no ticket");
+            prop_assert!(!model.denied.contains(&winner), "denied wallet won");
+
+            // The jackpot goes to the winner, less the house fee when it is taken now
+            let (pushed, held) = (after.treasury - before.treasury, after.unpaid_fees - before.unpaid_fees);
+            let fee = match model.fee_mode {
+                FeeMode::Upfront => 0,
+                FeeMode::OnWinnings => fee_on(before.jackpot),
+            };
+            prop_assert_eq!(prize, before.jackpot - fee);
+            prop_assert_eq!(pushed + held, fee);
+            prop_assert_eq!(if model.hold_fees { pushed } else { held }, 0);
+            prop_assert_eq!(after.jackpot, 0);
+            model.prizes += prize;
+            model.tickets.clear();
+            model.draw_slot = 0;
+        }
+        Ok(())
+    }
+
+    fn set_fee_mode(&mut self, fee_mode: FeeMode) -> Check {
+        let result = self.svm.process(&[instructions::set_fee_mode(&self.admin, fee_mode)], &[self.admin]);
+        if self.model.ticket_count() > 0 {
+            expect_error(result, RaffleError::RoundInProgress)?;
+        } else {
+            prop_assert_eq!(result, Ok(()));
+            self.model.fee_mode = fee_mode;
+        }
+        Ok(())
+    }
+
+    fn set_draw_guards(&mut self, sales_cutoff: i64, min_draw_slots: u64) -> Check {
+        let instruction = instructions::set_draw_guards(&self.admin, sales_cutoff, min_draw_slots);
+        let result = self.svm.process(&[instruction], &[self.admin]);
+        if !(0..RAFFLE_DURATION).contains(&sales_cutoff) {
+            expect_error(result, RaffleError::InvalidDrawGuards)?;
+        } else {
+            prop_assert_eq!(result, Ok(()));
+            self.model.sales_cutoff = sales_cutoff;
+            self.model.min_draw_slots = min_draw_slots;
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, op: Op) -> Check {
+        match op {
+            Op::Buy { wallet, amount } => self.buy(wallet, amount),
+            Op::Cancel { wallet, count } => self.cancel(wallet, count),
+            Op::Subscribe { wallet, tickets_per_round, rounds } => self.subscribe(wallet, tickets_per_round, rounds),
+            Op::Unsubscribe { wallet } => self.unsubscribe(wallet),
+            Op::ApplySubscriptions => self.apply_subscriptions(),
+            Op::PublishFreeTickets { grants } => self.publish_free_tickets(grants),
+            Op::ClaimFreeTickets { wallet } => self.claim_free_tickets(wallet),
+            Op::Stake { wallet, tickets } => self.stake(wallet, tickets),
+            Op::RequestUnstake { wallet } => self.request_unstake(wallet),
+            Op::AllocateStakeTickets => self.allocate_stake_tickets(),
+            Op::Deny { wallet } => self.deny(wallet, true),
+            Op::Allow { wallet } => self.deny(wallet, false),
+            Op::AdvanceTime(seconds) => {
+                self.svm.advance_time(seconds);
+                Ok(())
+            }
+            Op::Draw => self.draw(),
+            Op::Reconcile => {
+                prop_assert_eq!(self.svm.process(&[instructions::reconcile()], &[]), Ok(()));
+                Ok(())
+            }
+            Op::SetFeeMode(fee_mode) => self.set_fee_mode(fee_mode),
+            Op::SetHoldFees(hold_fees) => {
+                let result = self.svm.process(&[instructions::set_hold_fees(&self.admin, hold_fees)], &[self.admin]);
+                prop_assert_eq!(result, Ok(()));
+                self.model.hold_fees = hold_fees;
+                Ok(())
+            }
+            Op::SetDrawGuards { sales_cutoff, min_draw_slots } => self.set_draw_guards(sales_cutoff, min_draw_slots),
+        }
+    }
+}
+
+fn run(ops: Vec<Op>) -> Check {
+    let mut harness = Harness::new();
+    let total_lamports = harness.svm.total_lamports();
+    let vault_rent = harness.svm.rent.minimum_balance(0);
+    let treasury_start = harness.svm.lamports(&treasury_wallet());
+
+    for op in ops {
+        harness.apply(op)?;
+        let svm = &harness.svm;
+        let model = &harness.model;
+
+        // Lamports are only ever moved, never created or lost
+        prop_assert_eq!(svm.total_lamports(), total_lamports);
+
+        // The ledger holds exactly the tickets each wallet entered this round
+        let current = harness.raffle();
+        prop_assert_eq!(current.tickets.len(), model.ticket_count());
+        for (wallet, count) in &model.tickets {
+            prop_assert_eq!(state::tickets_of(&current, wallet), *count);
+        }
+        prop_assert_eq!(current.draw_slot, model.draw_slot);
+
+        // The vault holds what entries paid in less what the treasury, winners and
+        // refunds took out, and the books account for every lamport of it
+        let vault = svm.lamports(&pda::vault_address());
+        let treasury_received = svm.lamports(&treasury_wallet()) - treasury_start;
+        prop_assert_eq!(
+            vault as u128 + treasury_received as u128 + model.prizes as u128 + model.refunds as u128,
+            vault_rent as u128 + model.paid_in as u128
+        );
+        prop_assert_eq!(current.jackpot + current.unpaid_fees, vault - vault_rent);
+
+        // Subscription escrows hold the rounds still to be entered
+        let escrow_rent = svm.rent.minimum_balance(Subscription::SPACE);
+        for (subscriber, subscription) in &model.subscriptions {
+            prop_assert_eq!(
+                svm.lamports(&pda::subscription_address(subscriber)),
+                escrow_rent + subscription.tickets_per_round * subscription.rounds_remaining * TICKET_PRICE
+            );
+        }
+    }
+
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn accounting_invariants_hold(ops in prop::collection::vec(op(), 1..100)) {
+        run(ops)?;
+    }
+}
@@ -0,0 +1,1729 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{instruction::Instruction, system_program, sysvar};
+use anchor_lang::{Discriminator, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address;
+use common::{Account, Svm};
+use psyduk_raffle::{
+    BonusTier, DenyList, DrawRecord, DrawTranscript, FeeMode, Raffle, RaffleError, RaffleV0, RoundStatus, RoundSummary, Schedule, ScheduleKind, TicketsCancelled,
+    TicketsPurchased, WinProbability, LEGACY_RAFFLE_SPACE, MAX_STAKE_LOCK, MAX_TICKETS_PER_INSTRUCTION, NO_ROUND, RAFFLE_DURATION, SLOT_HASH_WINDOW,
+    TICKET_PRICE, UNSTAKE_COOLDOWN, WITHDRAWAL_DELAY,
+};
+use psyduk_raffle_client::{instructions, merkle::FreeTicketTree, pda, state, treasury_wallet};
+
+const SOL: u64 = 1_000_000_000;
+
+fn raffle_error(error: RaffleError) -> ProgramError {
+    ProgramError::Custom(error.into())
+}
+
+fn anchor_error(error: ErrorCode) -> ProgramError {
+    ProgramError::Custom(error.into())
+}
+
+fn raffle(svm: &Svm) -> Raffle {
+    state::decode_raffle(&svm.account(&pda::raffle_address()).unwrap().data).unwrap()
+}
+
+/// A runtime with the raffle initialized by a fresh admin.
+fn setup() -> (Svm, Pubkey) {
+    let mut svm = Svm::new();
+    let admin = svm.new_wallet(10 * SOL);
+    svm.process(&[instructions::initialize(&admin, Schedule::default())], &[admin]).unwrap();
+    (svm, admin)
+}
+
+/// A runtime with the raffle initialized on `schedule`.
+fn setup_with_schedule(schedule: Schedule) -> (Svm, Pubkey) {
+    let mut svm = Svm::new();
+    let admin = svm.new_wallet(10 * SOL);
+    svm.process(&[instructions::initialize(&admin, schedule)], &[admin]).unwrap();
+    (svm, admin)
+}
+
+fn recurring(anchor: i64, period: i64, duration: i64) -> Schedule {
+    Schedule { kind: ScheduleKind::Recurring, anchor, period, duration }
+}
+
+fn buy(svm: &mut Svm, buyer: &Pubkey, amount: u64) -> std::result::Result<(), ProgramError> {
+    svm.process(&[instructions::buy_ticket(buyer, amount)], &[*buyer])
+}
+
+fn deny_list(svm: &Svm) -> DenyList {
+    svm.account(&pda::deny_list_address())
+        .map(|account| state::decode_deny_list(&account.data).unwrap())
+        .unwrap_or_default()
+}
+
+fn slot_hashes(svm: &Svm) -> Vec<u8> {
+    svm.account(&sysvar::slot_hashes::id()).unwrap().data.clone()
+}
+
+/// Locks the round unless it already is, lets the slot after the target pass and
+/// draws from its hash.
+fn draw(svm: &mut Svm) -> std::result::Result<Pubkey, ProgramError> {
+    if raffle(svm).draw_slot == 0 {
+        svm.process(&[instructions::lock_round()], &[])?;
+    }
+    svm.advance_slots(2);
+    let current = raffle(svm);
+    let winner = state::drawn_winner(&current, &slot_hashes(svm), &deny_list(svm)).unwrap_or_default();
+    let payer = svm.new_wallet(SOL);
+    svm.process(&[instructions::select_winner(&payer, current.end_time, &winner)], &[payer])?;
+    Ok(winner)
+}
+
+#[test]
+fn initialize_opens_round_and_funds_vault() {
+    let (svm, admin) = setup();
+
+    let raffle = raffle(&svm);
+    assert_eq!(raffle.version, psyduk_raffle::RAFFLE_VERSION);
+    assert_eq!(raffle.admin, admin);
+    assert_eq!(raffle.jackpot, 0);
+    assert_eq!(raffle.start_time, common::GENESIS_TIMESTAMP);
+    assert_eq!(raffle.end_time, common::GENESIS_TIMESTAMP + RAFFLE_DURATION);
+    assert!(raffle.tickets.is_empty());
+    assert_eq!(svm.lamports(&pda::vault_address()), svm.rent.minimum_balance(0));
+}
+
+#[test]
+fn initialize_twice_fails() {
+    let (mut svm, _) = setup();
+    let admin = svm.new_wallet(SOL);
+
+    assert!(svm.process(&[instructions::initialize(&admin, Schedule::default())], &[admin]).is_err());
+}
+
+#[test]
+fn initialize_rejects_non_pda_raffle() {
+    let mut svm = Svm::new();
+    let admin = svm.new_wallet(SOL);
+    let mut instruction = instructions::initialize(&admin, Schedule::default());
+    instruction.accounts[0].pubkey = Pubkey::new_unique();
+
+    assert_eq!(
+        svm.process(&[instruction], &[admin]),
+        Err(anchor_error(ErrorCode::ConstraintSeeds))
+    );
+}
+
+#[test]
+fn buy_ticket_splits_payment_and_keeps_leftover() {
+    let (mut svm, _) = setup();
+    let buyer = svm.new_wallet(SOL);
+    let treasury_before = svm.lamports(&treasury_wallet());
+    let vault_before = svm.lamports(&pda::vault_address());
+    let raffle_before = svm.lamports(&pda::raffle_address());
+
+    buy(&mut svm, &buyer, 2 * TICKET_PRICE + TICKET_PRICE / 2).unwrap();
+
+    let raffle_account = raffle(&svm);
+    assert_eq!(raffle_account.tickets, vec![buyer, buyer]);
+    assert_eq!(raffle_account.jackpot, 2 * TICKET_PRICE * 80 / 100);
+    assert_eq!(svm.lamports(&pda::vault_address()) - vault_before, raffle_account.jackpot);
+    assert_eq!(svm.lamports(&treasury_wallet()) - treasury_before, 2 * TICKET_PRICE * 20 / 100);
+
+    // Only whole tickets, the ledger's extra rent and the buyer's receipt are charged
+    let rent_growth = svm.lamports(&pda::raffle_address()) - raffle_before;
+    let receipt_rent = svm.lamports(&pda::receipt_address(&buyer));
+    assert_eq!(svm.lamports(&buyer), SOL - 2 * TICKET_PRICE - rent_growth - receipt_rent);
+}
+
+#[test]
+fn one_purchase_adds_at_most_the_tickets_the_ledger_can_grow_by() {
+    let (mut svm, _) = setup();
+    let buyer = svm.new_wallet(10 * SOL);
+    let max = MAX_TICKETS_PER_INSTRUCTION as u64;
+
+    assert_eq!(
+        buy(&mut svm, &buyer, (max + 1) * TICKET_PRICE),
+        Err(raffle_error(RaffleError::TooManyTicketsPerInstruction))
+    );
+    buy(&mut svm, &buyer, max * TICKET_PRICE).unwrap();
+    buy(&mut svm, &buyer, max * TICKET_PRICE).unwrap();
+    assert_eq!(raffle(&svm).tickets.len(), 2 * MAX_TICKETS_PER_INSTRUCTION);
+}
+
+#[test]
+fn gifted_tickets_and_winnings_belong_to_the_beneficiary() {
+    let (mut svm, _) = setup();
+    let payer = svm.new_wallet(SOL);
+    let friend = svm.new_wallet(SOL);
+
+    let instruction = instructions::buy_ticket_for(&payer, 2 * TICKET_PRICE, Some(friend), None);
+    svm.process(&[instruction], &[payer]).unwrap();
+
+    assert_eq!(raffle(&svm).tickets, vec![friend, friend]);
+    assert_eq!(svm.lamports(&friend), SOL);
+    let events = svm.events::<TicketsPurchased>();
+    assert_eq!(events.len(), 1);
+    assert_eq!((events[0].payer, events[0].beneficiary), (payer, friend));
+    assert_eq!((events[0].tickets, events[0].cost), (2, 2 * TICKET_PRICE));
+
+    // Gifts can't be cancelled, so the payer pays for no receipt, though its own
+    // tickets need one
+    assert!(svm.account(&pda::receipt_address(&payer)).is_none());
+    let mut instruction = instructions::buy_ticket(&payer, TICKET_PRICE);
+    let receipt = pda::receipt_address(&payer);
+    for meta in instruction.accounts.iter_mut().filter(|meta| meta.pubkey == receipt) {
+        *meta = AccountMeta::new_readonly(psyduk_raffle::ID, false);
+    }
+    assert_eq!(svm.process(&[instruction], &[payer]), Err(raffle_error(RaffleError::MissingReceipt)));
+
+    // The payer holds no ticket, so the jackpot can only go to the friend
+    svm.advance_time(RAFFLE_DURATION);
+    let jackpot = raffle(&svm).jackpot;
+    assert_eq!(draw(&mut svm), Ok(friend));
+    assert_eq!(svm.lamports(&friend), SOL + jackpot);
+}
+
+#[test]
+fn cancelled_tickets_are_refunded_their_cost_less_the_penalty() {
+    let (mut svm, admin) = setup();
+    svm.process(&[instructions::set_cancel_penalty(&admin, 10)], &[admin]).unwrap();
+    svm.process(&[instructions::set_hold_fees(&admin, true)], &[admin]).unwrap();
+    let buyer = svm.new_wallet(SOL);
+    let other = svm.new_wallet(SOL);
+    buy(&mut svm, &buyer, 3 * TICKET_PRICE).unwrap();
+    buy(&mut svm, &other, TICKET_PRICE).unwrap();
+    buy(&mut svm, &buyer, TICKET_PRICE).unwrap();
+    let balance_before = svm.lamports(&buyer);
+
+    svm.process(&[instructions::cancel_tickets(&buyer, 2)], &[buyer]).unwrap();
+
+    // The buyer's latest tickets leave the ledger and everyone else keeps their place.
+    // Their held fees are refunded with their jackpot share, the penalty joining the jackpot.
+    let share = TICKET_PRICE * 80 / 100;
+    let fee = TICKET_PRICE - share;
+    let penalty = 2 * TICKET_PRICE / 10;
+    let refund = 2 * TICKET_PRICE - penalty;
+    let raffle = raffle(&svm);
+    assert_eq!(raffle.tickets, vec![buyer, buyer, other]);
+    assert_eq!(raffle.jackpot, 3 * share + penalty);
+    assert_eq!(raffle.unpaid_fees, 3 * fee);
+    assert_eq!(svm.lamports(&buyer), balance_before + refund);
+    svm.process(&[instructions::reconcile()], &[]).unwrap();
+    let events = svm.events::<TicketsCancelled>();
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        (events[0].tickets, events[0].refund, events[0].penalty, events[0].fee_retained),
+        (2, refund, penalty, 0)
+    );
+
+    let receipt = state::decode_ticket_receipt(&svm.account(&pda::receipt_address(&buyer)).unwrap().data).unwrap();
+    assert_eq!(
+        (receipt.paid_tickets, receipt.cost_paid, receipt.jackpot_paid, receipt.fees_held),
+        (2, 2 * TICKET_PRICE, 2 * share, 2 * fee)
+    );
+}
+
+#[test]
+fn fees_paid_to_the_treasury_count_towards_the_penalty() {
+    let (mut svm, admin) = setup();
+    svm.process(&[instructions::set_cancel_penalty(&admin, 10)], &[admin]).unwrap();
+    let buyer = svm.new_wallet(SOL);
+    buy(&mut svm, &buyer, 2 * TICKET_PRICE).unwrap();
+    let balance_before = svm.lamports(&buyer);
+
+    // The fee left with the purchase, so only the jackpot share comes back
+    svm.process(&[instructions::cancel_tickets(&buyer, 2)], &[buyer]).unwrap();
+    let share = TICKET_PRICE * 80 / 100;
+    let penalty = 2 * TICKET_PRICE / 10;
+    assert_eq!(svm.lamports(&buyer), balance_before + 2 * share);
+    assert_eq!(raffle(&svm).jackpot, 0);
+    svm.process(&[instructions::reconcile()], &[]).unwrap();
+    let events = svm.events::<TicketsCancelled>();
+    assert_eq!(
+        (events[0].refund, events[0].penalty, events[0].fee_retained),
+        (2 * share, penalty, 2 * TICKET_PRICE - penalty - 2 * share)
+    );
+}
+
+#[test]
+fn only_own_paid_tickets_can_be_cancelled_before_sales_close() {
+    let (mut svm, admin) = setup();
+    set_bonus_tiers(&mut svm, &admin, &[(1, 50)]).unwrap();
+    let holder = svm.new_wallet(SOL);
+    let friend = svm.new_wallet(SOL);
+    let holder_tokens = svm.new_token_account(&holder, &psyduk_mint(), 1);
+    let instruction = instructions::buy_ticket_for(&holder, 4 * TICKET_PRICE, None, Some(holder_tokens));
+    svm.process(&[instruction], &[holder]).unwrap();
+    let instruction = instructions::buy_ticket_for(&holder, TICKET_PRICE, Some(friend), None);
+    svm.process(&[instruction], &[holder]).unwrap();
+
+    // Gifts are final and bonus tickets go with the purchases that earned them
+    assert_eq!(
+        svm.process(&[instructions::cancel_tickets(&friend, 1)], &[friend]),
+        Err(anchor_error(ErrorCode::AccountNotInitialized))
+    );
+    assert_eq!(
+        svm.process(&[instructions::cancel_tickets(&holder, 5)], &[holder]),
+        Err(raffle_error(RaffleError::NotEnoughTickets))
+    );
+    svm.process(&[instructions::cancel_tickets(&holder, 2)], &[holder]).unwrap();
+    let current = raffle(&svm);
+    assert_eq!(state::tickets_of(&current, &holder), 3);
+    assert_eq!(state::tickets_of(&current, &friend), 1);
+    assert_eq!(current.bonus_tickets, 1);
+
+    // Sales closed, then a receipt from an earlier round
+    svm.process(&[instructions::set_draw_guards(&admin, 60, 0)], &[admin]).unwrap();
+    svm.advance_time(RAFFLE_DURATION - 60);
+    assert_eq!(
+        svm.process(&[instructions::cancel_tickets(&holder, 1)], &[holder]),
+        Err(raffle_error(RaffleError::SalesClosed))
+    );
+    svm.advance_time(60);
+    draw(&mut svm).unwrap();
+    svm.advance_time(10);
+    buy(&mut svm, &friend, TICKET_PRICE).unwrap();
+    assert_eq!(
+        svm.process(&[instructions::cancel_tickets(&holder, 1)], &[holder]),
+        Err(raffle_error(RaffleError::NotEnoughTickets))
+    );
+}
+
+#[test]
+fn set_cancel_penalty_is_admin_only_and_bounded() {
+    let (mut svm, admin) = setup();
+    let stranger = svm.new_wallet(SOL);
+
+    assert_eq!(
+        svm.process(&[instructions::set_cancel_penalty(&stranger, 10)], &[stranger]),
+        Err(raffle_error(RaffleError::Unauthorized))
+    );
+    assert_eq!(
+        svm.process(&[instructions::set_cancel_penalty(&admin, 101)], &[admin]),
+        Err(raffle_error(RaffleError::InvalidCancelPenalty))
+    );
+    svm.process(&[instructions::set_cancel_penalty(&admin, 100)], &[admin]).unwrap();
+    assert_eq!(raffle(&svm).cancel_penalty_percent, 100);
+}
+
+/// Runs a query instruction and decodes what it returned.
+fn query<T: AnchorDeserialize>(svm: &mut Svm, instruction: Instruction) -> T {
+    svm.process(&[instruction], &[]).unwrap();
+    let (program_id, data) = svm.return_data.clone().unwrap();
+    assert_eq!(program_id, psyduk_raffle::ID);
+    state::decode_return_data(&data).unwrap()
+}
+
+#[test]
+fn queries_return_the_round_and_a_wallets_odds() {
+    let (mut svm, admin) = setup();
+    svm.process(&[instructions::set_draw_guards(&admin, 60, 0)], &[admin]).unwrap();
+    let alice = svm.new_wallet(SOL);
+    let bob = svm.new_wallet(SOL);
+    buy(&mut svm, &alice, 2 * TICKET_PRICE).unwrap();
+    buy(&mut svm, &bob, TICKET_PRICE).unwrap();
+
+    let current = raffle(&svm);
+    let summary: RoundSummary = query(&mut svm, instructions::get_round_summary());
+    assert_eq!(
+        summary,
+        RoundSummary {
+            status: RoundStatus::Open,
+            start_time: current.start_time,
+            end_time: current.end_time,
+            sales_close_time: current.end_time - 60,
+            jackpot: current.jackpot,
+            prize: current.jackpot,
+            tickets: 3,
+            bonus_tickets: 0,
+            draw_slot: 0,
+            fee_mode: FeeMode::Upfront,
+            asset_round: false,
+        }
+    );
+    assert_eq!(query::<u64>(&mut svm, instructions::get_tickets_of(&alice)), 2);
+    assert_eq!(query::<u64>(&mut svm, instructions::get_tickets_of(&admin)), 0);
+    assert_eq!(
+        query::<WinProbability>(&mut svm, instructions::get_win_probability(&alice)),
+        WinProbability { tickets: 2, total_tickets: 3, parts_per_million: 666_666 }
+    );
+}
+
+fn psyduk_mint() -> Pubkey {
+    psyduk_raffle::PSYDUK_MINT.parse().unwrap()
+}
+
+fn set_bonus_tiers(svm: &mut Svm, admin: &Pubkey, tiers: &[(u64, u16)]) -> std::result::Result<(), ProgramError> {
+    let mut table = [BonusTier::default(); psyduk_raffle::MAX_BONUS_TIERS];
+    for (slot, (min_balance, bonus_percent)) in table.iter_mut().zip(tiers) {
+        *slot = BonusTier { min_balance: *min_balance, bonus_percent: *bonus_percent };
+    }
+    svm.process(&[instructions::set_bonus_tiers(admin, table)], &[*admin])
+}
+
+#[test]
+fn holders_earn_bonus_tickets_by_tier() {
+    let (mut svm, admin) = setup();
+    set_bonus_tiers(&mut svm, &admin, &[(1_000_000, 10), (5_000_000, 50)]).unwrap();
+    let holder = svm.new_wallet(SOL);
+    let whale = svm.new_wallet(SOL);
+    let holder_tokens = svm.new_token_account(&holder, &psyduk_mint(), 2_000_000);
+    let whale_tokens = svm.new_token_account(&whale, &psyduk_mint(), 5_000_000);
+
+    let instruction = instructions::buy_ticket_for(&holder, 10 * TICKET_PRICE, None, Some(holder_tokens));
+    svm.process(&[instruction], &[holder]).unwrap();
+    let instruction = instructions::buy_ticket_for(&whale, 4 * TICKET_PRICE, None, Some(whale_tokens));
+    svm.process(&[instruction], &[whale]).unwrap();
+
+    let raffle = raffle(&svm);
+    assert_eq!(state::tickets_of(&raffle, &holder), 11);
+    assert_eq!(state::tickets_of(&raffle, &whale), 6);
+    assert_eq!(raffle.bonus_tickets, 3);
+    assert_eq!(raffle.jackpot, 14 * TICKET_PRICE * 80 / 100);
+    let events = svm.events::<TicketsPurchased>();
+    assert_eq!((events[0].tickets, events[0].bonus_tickets), (10, 1));
+}
+
+#[test]
+fn bonus_requires_the_buyers_psyduk_account() {
+    let (mut svm, admin) = setup();
+    set_bonus_tiers(&mut svm, &admin, &[(1, 100)]).unwrap();
+    let buyer = svm.new_wallet(SOL);
+    let whale = svm.new_wallet(SOL);
+    let borrowed = svm.new_token_account(&whale, &psyduk_mint(), 1_000_000_000);
+    let other_mint = svm.new_token_account(&buyer, &Pubkey::new_unique(), 1_000_000_000);
+
+    for token_account in [borrowed, other_mint] {
+        let instruction = instructions::buy_ticket_for(&buyer, TICKET_PRICE, None, Some(token_account));
+        assert_eq!(
+            svm.process(&[instruction], &[buyer]),
+            Err(raffle_error(RaffleError::InvalidHolderTokenAccount))
+        );
+    }
+}
+
+#[test]
+fn set_bonus_tiers_validates_the_table() {
+    let (mut svm, admin) = setup();
+
+    assert_eq!(
+        set_bonus_tiers(&mut svm, &admin, &[(5_000_000, 10), (1_000_000, 20)]),
+        Err(raffle_error(RaffleError::InvalidBonusTiers))
+    );
+    assert_eq!(
+        set_bonus_tiers(&mut svm, &admin, &[(1_000_000, 101)]),
+        Err(raffle_error(RaffleError::InvalidBonusTiers))
+    );
+    let stranger = svm.new_wallet(SOL);
+    assert_eq!(
+        set_bonus_tiers(&mut svm, &stranger, &[(1_000_000, 10)]),
+        Err(raffle_error(RaffleError::Unauthorized))
+    );
+}
+
+#[test]
+fn buy_ticket_below_price_fails() {
+    let (mut svm, _) = setup();
+    let buyer = svm.new_wallet(SOL);
+
+    assert_eq!(
+        buy(&mut svm, &buyer, TICKET_PRICE - 1),
+        Err(raffle_error(RaffleError::InsufficientFunds))
+    );
+}
+
+#[test]
+fn buy_ticket_rejects_wrong_treasury() {
+    let (mut svm, _) = setup();
+    let buyer = svm.new_wallet(SOL);
+    let mut instruction = instructions::buy_ticket(&buyer, TICKET_PRICE);
+    instruction.accounts[3].pubkey = Pubkey::new_unique();
+
+    assert_eq!(
+        svm.process(&[instruction], &[buyer]),
+        Err(raffle_error(RaffleError::InvalidTreasuryWallet))
+    );
+}
+
+#[test]
+fn buy_ticket_rejects_non_pda_raffle() {
+    let (mut svm, _) = setup();
+    let buyer = svm.new_wallet(SOL);
+
+    // A well-formed raffle account that lives at the wrong address
+    let impostor = Pubkey::new_unique();
+    let account = svm.account(&pda::raffle_address()).unwrap().clone();
+    svm.set_account(impostor, account);
+    let mut instruction = instructions::buy_ticket(&buyer, TICKET_PRICE);
+    instruction.accounts[1].pubkey = impostor;
+
+    assert_eq!(
+        svm.process(&[instruction], &[buyer]),
+        Err(raffle_error(RaffleError::InvalidRaffleAccount))
+    );
+}
+
+#[test]
+fn buy_ticket_rejects_wrong_vault() {
+    let (mut svm, _) = setup();
+    let buyer = svm.new_wallet(SOL);
+    let mut instruction = instructions::buy_ticket(&buyer, TICKET_PRICE);
+    instruction.accounts[2].pubkey = Pubkey::new_unique();
+
+    assert_eq!(
+        svm.process(&[instruction], &[buyer]),
+        Err(anchor_error(ErrorCode::ConstraintSeeds))
+    );
+}
+
+#[test]
+fn select_winner_before_end_time_fails() {
+    let (mut svm, _) = setup();
+    let buyer = svm.new_wallet(SOL);
+    buy(&mut svm, &buyer, TICKET_PRICE).unwrap();
+    svm.advance_time(RAFFLE_DURATION - 1);
+
+    assert_eq!(draw(&mut svm), Err(raffle_error(RaffleError::RaffleStillActive)));
+}
+
+#[test]
+fn sales_close_at_the_cutoff() {
+    let (mut svm, admin) = setup();
+    let buyer = svm.new_wallet(SOL);
+    buy(&mut svm, &buyer, TICKET_PRICE).unwrap();
+
+    // Without a cutoff sales run until the end of the round
+    svm.advance_time(RAFFLE_DURATION);
+    assert_eq!(buy(&mut svm, &buyer, TICKET_PRICE), Err(raffle_error(RaffleError::SalesClosed)));
+    draw(&mut svm).unwrap();
+
+    svm.process(&[instructions::set_draw_guards(&admin, 60, 0)], &[admin]).unwrap();
+    svm.advance_time(10);
+    svm.advance_time(RAFFLE_DURATION - 61);
+    buy(&mut svm, &buyer, TICKET_PRICE).unwrap();
+    svm.advance_time(1);
+    assert_eq!(buy(&mut svm, &buyer, TICKET_PRICE), Err(raffle_error(RaffleError::SalesClosed)));
+}
+
+#[test]
+fn draw_waits_for_slots_after_the_last_entry() {
+    let (mut svm, admin) = setup();
+    svm.process(&[instructions::set_draw_guards(&admin, 0, 150)], &[admin]).unwrap();
+    let buyer = svm.new_wallet(SOL);
+    svm.advance_time(RAFFLE_DURATION - 10);
+    buy(&mut svm, &buyer, TICKET_PRICE).unwrap();
+    svm.advance_time(10);
+
+    assert_eq!(draw(&mut svm), Err(raffle_error(RaffleError::DrawTooSoon)));
+    svm.advance_slots(150);
+    assert_eq!(draw(&mut svm), Ok(buyer));
+}
+
+#[test]
+fn set_draw_guards_is_admin_only_and_bounded() {
+    let (mut svm, admin) = setup();
+    let stranger = svm.new_wallet(SOL);
+
+    assert_eq!(
+        svm.process(&[instructions::set_draw_guards(&stranger, 60, 10)], &[stranger]),
+        Err(raffle_error(RaffleError::Unauthorized))
+    );
+    for cutoff in [-1, RAFFLE_DURATION] {
+        assert_eq!(
+            svm.process(&[instructions::set_draw_guards(&admin, cutoff, 10)], &[admin]),
+            Err(raffle_error(RaffleError::InvalidDrawGuards))
+        );
+    }
+}
+
+#[test]
+fn select_winner_pays_jackpot_and_opens_next_round() {
+    let (mut svm, _) = setup();
+    let alice = svm.new_wallet(SOL);
+    let bob = svm.new_wallet(SOL);
+    buy(&mut svm, &alice, 3 * TICKET_PRICE).unwrap();
+    buy(&mut svm, &bob, 2 * TICKET_PRICE).unwrap();
+    svm.advance_time(RAFFLE_DURATION);
+
+    let jackpot = raffle(&svm).jackpot;
+    let before = svm.lamports(&alice) + svm.lamports(&bob);
+    let winner = draw(&mut svm).unwrap();
+
+    assert!(winner == alice || winner == bob);
+    assert_eq!(svm.lamports(&alice) + svm.lamports(&bob), before + jackpot);
+    assert_eq!(svm.lamports(&pda::vault_address()), svm.rent.minimum_balance(0));
+
+    let raffle = raffle(&svm);
+    assert_eq!(raffle.jackpot, 0);
+    assert!(raffle.tickets.is_empty());
+    assert_eq!(raffle.start_time, svm.clock.unix_timestamp + 10);
+    assert_eq!(raffle.end_time, raffle.start_time + RAFFLE_DURATION);
+}
+
+#[test]
+fn reserve_tops_the_prize_up_to_the_minimum() {
+    let (mut svm, admin) = setup();
+    let stranger = svm.new_wallet(SOL);
+    assert_eq!(
+        svm.process(&[instructions::set_min_prize(&stranger, SOL)], &[stranger]),
+        Err(raffle_error(RaffleError::Unauthorized))
+    );
+    let min_prize = 10 * TICKET_PRICE;
+    svm.process(&[instructions::set_min_prize(&admin, min_prize)], &[admin]).unwrap();
+    let buyer = svm.new_wallet(SOL);
+    buy(&mut svm, &buyer, TICKET_PRICE).unwrap();
+    svm.advance_time(RAFFLE_DURATION);
+
+    // The draw fails until the reserve covers the shortfall
+    let shortfall = min_prize - raffle(&svm).jackpot;
+    let sponsor = svm.new_wallet(SOL);
+    svm.process(&[instructions::fund_reserve(&sponsor, shortfall - 1)], &[sponsor]).unwrap();
+    assert_eq!(draw(&mut svm), Err(raffle_error(RaffleError::InsufficientReserve)));
+    svm.process(&[instructions::fund_reserve(&sponsor, 1 + TICKET_PRICE)], &[sponsor]).unwrap();
+
+    let end_time = raffle(&svm).end_time;
+    let buyer_before = svm.lamports(&buyer);
+    assert_eq!(draw(&mut svm), Ok(buyer));
+    assert_eq!(svm.lamports(&buyer), buyer_before + min_prize);
+    assert_eq!(svm.lamports(&pda::reserve_address()), svm.rent.minimum_balance(0) + TICKET_PRICE);
+    let record = state::decode_draw_record(&svm.account(&pda::draw_record_address(end_time)).unwrap().data).unwrap();
+    assert_eq!(record.prize, min_prize);
+    assert_eq!(svm.process(&[instructions::reconcile()], &[]), Ok(()));
+}
+
+#[test]
+fn fee_on_winnings_is_taken_at_the_draw() {
+    let (mut svm, admin) = setup();
+    svm.process(&[instructions::set_fee_mode(&admin, FeeMode::OnWinnings)], &[admin]).unwrap();
+    let buyer = svm.new_wallet(SOL);
+    let treasury_before = svm.lamports(&treasury_wallet());
+
+    buy(&mut svm, &buyer, 10 * TICKET_PRICE).unwrap();
+    assert_eq!(raffle(&svm).jackpot, 10 * TICKET_PRICE);
+    assert_eq!(svm.lamports(&treasury_wallet()), treasury_before);
+    assert_eq!(
+        svm.process(&[instructions::set_fee_mode(&admin, FeeMode::Upfront)], &[admin]),
+        Err(raffle_error(RaffleError::RoundInProgress))
+    );
+
+    svm.advance_time(RAFFLE_DURATION);
+    let end_time = raffle(&svm).end_time;
+    let buyer_before = svm.lamports(&buyer);
+    assert_eq!(draw(&mut svm), Ok(buyer));
+
+    let (prize, house_fee) = (8 * TICKET_PRICE, 2 * TICKET_PRICE);
+    assert_eq!(svm.lamports(&buyer), buyer_before + prize);
+    assert_eq!(svm.lamports(&treasury_wallet()), treasury_before + house_fee);
+    let record = state::decode_draw_record(&svm.account(&pda::draw_record_address(end_time)).unwrap().data).unwrap();
+    assert_eq!((record.prize, record.house_fee), (prize, house_fee));
+    assert_eq!(svm.process(&[instructions::reconcile()], &[]), Ok(()));
+}
+
+/// Creates a mock SOL/USD feed written by a fresh authority, returning both.
+fn create_price_feed(svm: &mut Svm) -> (Pubkey, Pubkey) {
+    let authority = svm.new_wallet(SOL);
+    let feed = Pubkey::new_unique();
+    let instruction = Instruction {
+        program_id: mock_price_feed::ID,
+        accounts: mock_price_feed::accounts::CreateFeed {
+            feed,
+            write_authority: authority,
+            system_program: system_program::id(),
+        }
+        .to_account_metas(None),
+        data: mock_price_feed::instruction::CreateFeed {}.data(),
+    };
+    svm.process(&[instruction], &[authority, feed]).unwrap();
+    (feed, authority)
+}
+
+/// Publishes a SOL/USD price in millionths of a dollar, as of now.
+fn set_feed_price(svm: &mut Svm, feed: &Pubkey, authority: &Pubkey, price: i64, confidence: u64) {
+    let now = svm.clock.unix_timestamp;
+    publish_feed_price(svm, feed, authority, price, confidence, now);
+}
+
+/// Publishes a SOL/USD price in millionths of a dollar, as of `publish_time`.
+fn publish_feed_price(svm: &mut Svm, feed: &Pubkey, authority: &Pubkey, price: i64, confidence: u64, publish_time: i64) {
+    let instruction = Instruction {
+        program_id: mock_price_feed::ID,
+        accounts: mock_price_feed::accounts::SetPrice { feed: *feed, write_authority: *authority }.to_account_metas(None),
+        data: mock_price_feed::instruction::SetPrice {
+            price,
+            confidence,
+            exponent: -6,
+            publish_time,
+        }
+        .data(),
+    };
+    svm.process(&[instruction], &[*authority]).unwrap();
+}
+
+#[test]
+fn usd_priced_tickets_are_converted_at_the_feed_price() {
+    let (mut svm, admin) = setup();
+    let (feed, authority) = create_price_feed(&mut svm);
+    // SOL at $100 known to within 5 cents
+    set_feed_price(&mut svm, &feed, &authority, 100_000_000, 50_000);
+
+    let stranger = svm.new_wallet(SOL);
+    assert_eq!(
+        svm.process(&[instructions::set_usd_pricing(&stranger, 150, &feed, 60, 10)], &[stranger]),
+        Err(raffle_error(RaffleError::Unauthorized))
+    );
+    assert_eq!(
+        svm.process(&[instructions::set_usd_pricing(&admin, 150, &feed, 60, 10_001)], &[admin]),
+        Err(raffle_error(RaffleError::InvalidPriceConfig))
+    );
+    svm.process(&[instructions::set_usd_pricing(&admin, 150, &feed, 60, 10)], &[admin]).unwrap();
+
+    // $1.50 at $100 per SOL is 0.015 SOL
+    let ticket_price = 15_000_000;
+    let buyer = svm.new_wallet(SOL);
+    assert_eq!(buy(&mut svm, &buyer, SOL / 10), Err(raffle_error(RaffleError::InvalidPriceFeed)));
+    let other_feed = create_price_feed(&mut svm).0;
+    assert_eq!(
+        svm.process(&[instructions::buy_ticket_in_usd(&buyer, SOL / 10, None, None, &other_feed)], &[buyer]),
+        Err(raffle_error(RaffleError::InvalidPriceFeed))
+    );
+    svm.process(&[instructions::buy_ticket_in_usd(&buyer, 2 * ticket_price + 1, None, None, &feed)], &[buyer]).unwrap();
+    assert_eq!(raffle(&svm).tickets, vec![buyer, buyer]);
+    assert_eq!(svm.events::<TicketsPurchased>().last().unwrap().cost, 2 * ticket_price);
+
+    svm.advance_time(61);
+    assert_eq!(
+        svm.process(&[instructions::buy_ticket_in_usd(&buyer, ticket_price, None, None, &feed)], &[buyer]),
+        Err(raffle_error(RaffleError::StalePrice))
+    );
+    publish_feed_price(&mut svm, &feed, &authority, 100_000_000, 50_000, i64::MIN);
+    assert_eq!(
+        svm.process(&[instructions::buy_ticket_in_usd(&buyer, ticket_price, None, None, &feed)], &[buyer]),
+        Err(raffle_error(RaffleError::StalePrice))
+    );
+    let now = svm.clock.unix_timestamp;
+    publish_feed_price(&mut svm, &feed, &authority, 100_000_000, 50_000, now + 1);
+    assert_eq!(
+        svm.process(&[instructions::buy_ticket_in_usd(&buyer, ticket_price, None, None, &feed)], &[buyer]),
+        Err(raffle_error(RaffleError::FuturePrice))
+    );
+    set_feed_price(&mut svm, &feed, &authority, 100_000_000, 200_000);
+    assert_eq!(
+        svm.process(&[instructions::buy_ticket_in_usd(&buyer, ticket_price, None, None, &feed)], &[buyer]),
+        Err(raffle_error(RaffleError::PriceTooUncertain))
+    );
+
+    // Subscriptions prepay the fixed lamport price, so they wait for it to return
+    assert_eq!(
+        svm.process(&[instructions::subscribe(&buyer, 1, 2)], &[buyer]),
+        Err(raffle_error(RaffleError::SubscriptionsUnavailable))
+    );
+
+    // A zero price switches back to the fixed lamport price
+    svm.process(&[instructions::set_usd_pricing(&admin, 0, &feed, 60, 10)], &[admin]).unwrap();
+    buy(&mut svm, &buyer, TICKET_PRICE).unwrap();
+    assert_eq!(raffle(&svm).tickets.len(), 3);
+    svm.process(&[instructions::subscribe(&buyer, 1, 2)], &[buyer]).unwrap();
+}
+
+#[test]
+fn usd_prices_come_only_from_verified_pyth_accounts() {
+    let (mut svm, admin) = setup();
+    let buyer = svm.new_wallet(SOL);
+    let (feed, authority) = create_price_feed(&mut svm);
+    set_feed_price(&mut svm, &feed, &authority, 100_000_000, 50_000);
+
+    // The same price in an account some other program owns is refused
+    let mut copy = svm.account(&feed).unwrap().clone();
+    copy.owner = Pubkey::new_unique();
+    let impostor = Pubkey::new_unique();
+    svm.set_account(impostor, copy);
+    svm.process(&[instructions::set_usd_pricing(&admin, 150, &impostor, 60, 10)], &[admin]).unwrap();
+    assert_eq!(
+        svm.process(&[instructions::buy_ticket_in_usd(&buyer, SOL / 10, None, None, &impostor)], &[buyer]),
+        Err(raffle_error(RaffleError::InvalidPriceFeed))
+    );
+
+    // So is a price whose guardian signatures were only partly verified
+    svm.process(&[instructions::set_usd_pricing(&admin, 150, &feed, 60, 10)], &[admin]).unwrap();
+    let partial = Instruction {
+        program_id: mock_price_feed::ID,
+        accounts: mock_price_feed::accounts::SetPrice { feed, write_authority: authority }.to_account_metas(None),
+        data: mock_price_feed::instruction::SetVerificationLevel {
+            verification_level: mock_price_feed::VerificationLevel::Partial { num_signatures: 5 },
+        }
+        .data(),
+    };
+    svm.process(&[partial], &[authority]).unwrap();
+    assert_eq!(
+        svm.process(&[instructions::buy_ticket_in_usd(&buyer, SOL / 10, None, None, &feed)], &[buyer]),
+        Err(raffle_error(RaffleError::InvalidPriceFeed))
+    );
+}
+
+#[test]
+fn set_fee_mode_is_admin_only() {
+    let (mut svm, _) = setup();
+    let stranger = svm.new_wallet(SOL);
+
+    assert_eq!(
+        svm.process(&[instructions::set_fee_mode(&stranger, FeeMode::OnWinnings)], &[stranger]),
+        Err(raffle_error(RaffleError::Unauthorized))
+    );
+}
+
+#[test]
+fn held_fees_are_withdrawn_after_the_timelock() {
+    let (mut svm, admin) = setup();
+    let stranger = svm.new_wallet(SOL);
+    assert_eq!(
+        svm.process(&[instructions::set_hold_fees(&stranger, true)], &[stranger]),
+        Err(raffle_error(RaffleError::Unauthorized))
+    );
+    svm.process(&[instructions::set_hold_fees(&admin, true)], &[admin]).unwrap();
+    let buyer = svm.new_wallet(SOL);
+    let treasury_before = svm.lamports(&treasury_wallet());
+
+    // Fees charged on entry and at the draw both stay in the vault
+    buy(&mut svm, &buyer, 10 * TICKET_PRICE).unwrap();
+    assert_eq!(svm.lamports(&treasury_wallet()), treasury_before);
+    assert_eq!(raffle(&svm).unpaid_fees, 2 * TICKET_PRICE);
+    svm.advance_time(RAFFLE_DURATION);
+    draw(&mut svm).unwrap();
+    svm.advance_time(10);
+    svm.process(&[instructions::set_fee_mode(&admin, FeeMode::OnWinnings)], &[admin]).unwrap();
+    buy(&mut svm, &buyer, 10 * TICKET_PRICE).unwrap();
+    svm.advance_time(RAFFLE_DURATION);
+    draw(&mut svm).unwrap();
+    let fees = 4 * TICKET_PRICE;
+    assert_eq!(svm.lamports(&treasury_wallet()), treasury_before);
+    assert_eq!(raffle(&svm).unpaid_fees, fees);
+    assert_eq!(svm.process(&[instructions::reconcile()], &[]), Ok(()));
+
+    let destination = svm.new_wallet(SOL);
+    assert_eq!(
+        svm.process(&[instructions::queue_withdrawal(&stranger, fees, &destination)], &[stranger]),
+        Err(raffle_error(RaffleError::Unauthorized))
+    );
+    assert_eq!(
+        svm.process(&[instructions::queue_withdrawal(&admin, fees + 1, &destination)], &[admin]),
+        Err(raffle_error(RaffleError::InsufficientFees))
+    );
+    svm.process(&[instructions::queue_withdrawal(&admin, fees, &destination)], &[admin]).unwrap();
+    let withdrawal = state::decode_withdrawal(&svm.account(&pda::withdrawal_address()).unwrap().data).unwrap();
+    assert_eq!((withdrawal.amount, withdrawal.destination), (fees, destination));
+    assert_eq!(withdrawal.unlock_time, svm.clock.unix_timestamp + WITHDRAWAL_DELAY);
+
+    assert_eq!(
+        svm.process(&[instructions::execute_withdrawal(&admin, &destination)], &[admin]),
+        Err(raffle_error(RaffleError::WithdrawalLocked))
+    );
+    svm.advance_time(WITHDRAWAL_DELAY);
+    assert_eq!(
+        svm.process(&[instructions::execute_withdrawal(&admin, &stranger)], &[admin]),
+        Err(raffle_error(RaffleError::InvalidWithdrawalDestination))
+    );
+    svm.process(&[instructions::execute_withdrawal(&admin, &destination)], &[admin]).unwrap();
+
+    assert_eq!(svm.lamports(&destination), SOL + fees);
+    assert_eq!(raffle(&svm).unpaid_fees, 0);
+    assert!(svm.account(&pda::withdrawal_address()).is_none_or(|account| account.lamports == 0));
+    assert_eq!(svm.process(&[instructions::reconcile()], &[]), Ok(()));
+}
+
+#[test]
+fn queued_withdrawal_can_be_cancelled() {
+    let (mut svm, admin) = setup();
+    svm.process(&[instructions::set_hold_fees(&admin, true)], &[admin]).unwrap();
+    let buyer = svm.new_wallet(SOL);
+    buy(&mut svm, &buyer, 10 * TICKET_PRICE).unwrap();
+    let destination = svm.new_wallet(SOL);
+    svm.process(&[instructions::queue_withdrawal(&admin, TICKET_PRICE, &destination)], &[admin]).unwrap();
+
+    let stranger = svm.new_wallet(SOL);
+    assert_eq!(
+        svm.process(&[instructions::cancel_withdrawal(&stranger)], &[stranger]),
+        Err(raffle_error(RaffleError::Unauthorized))
+    );
+    svm.process(&[instructions::cancel_withdrawal(&admin)], &[admin]).unwrap();
+
+    assert!(svm.account(&pda::withdrawal_address()).is_none_or(|account| account.lamports == 0));
+    svm.advance_time(WITHDRAWAL_DELAY);
+    assert!(svm.process(&[instructions::execute_withdrawal(&admin, &destination)], &[admin]).is_err());
+    assert_eq!(svm.lamports(&destination), SOL);
+    assert_eq!(raffle(&svm).unpaid_fees, 2 * TICKET_PRICE);
+}
+
+/// Attaches an asset prize of `amount` tokens of a fresh mint to the current round,
+/// returning the creator, the mint and the creator's token account.
+fn create_asset_raffle(svm: &mut Svm, admin: &Pubkey, amount: u64) -> (Pubkey, Pubkey, Pubkey) {
+    let creator = svm.new_wallet(SOL);
+    let mint = Pubkey::new_unique();
+    svm.new_mint(mint, 0);
+    let creator_tokens = svm.new_token_account(&creator, &mint, amount);
+    svm.process(&[instructions::create_asset_raffle(admin, &creator, &mint, amount)], &[*admin, creator])
+        .unwrap();
+    (creator, mint, creator_tokens)
+}
+
+#[test]
+fn asset_prize_goes_to_the_winner_and_revenue_to_the_creator() {
+    let (mut svm, admin) = setup();
+    let (creator, mint, creator_tokens) = create_asset_raffle(&mut svm, &admin, 1);
+    let buyer = svm.new_wallet(SOL);
+
+    // Sales open once the prize is in escrow
+    assert_eq!(
+        svm.process(&[instructions::buy_asset_ticket(&buyer, TICKET_PRICE, &creator)], &[buyer]),
+        Err(raffle_error(RaffleError::PrizeNotDeposited))
+    );
+    svm.process(&[instructions::deposit_prize(&creator, &creator_tokens, &mint)], &[creator]).unwrap();
+    assert_eq!(svm.token_balance(&pda::prize_vault_address()), 1);
+    assert_eq!(svm.token_balance(&creator_tokens), 0);
+    assert_eq!(
+        svm.process(&[instructions::deposit_prize(&creator, &creator_tokens, &mint)], &[creator]),
+        Err(raffle_error(RaffleError::PrizeAlreadyDeposited))
+    );
+
+    // Revenue goes to the creator and the treasury, never to the jackpot
+    assert_eq!(buy(&mut svm, &buyer, TICKET_PRICE), Err(raffle_error(RaffleError::InvalidAssetPrize)));
+    let stranger = svm.new_wallet(SOL);
+    assert_eq!(
+        svm.process(&[instructions::buy_asset_ticket(&buyer, TICKET_PRICE, &stranger)], &[buyer]),
+        Err(raffle_error(RaffleError::InvalidCreatorAccount))
+    );
+    let creator_before = svm.lamports(&creator);
+    let treasury_before = svm.lamports(&treasury_wallet());
+    svm.process(&[instructions::buy_asset_ticket(&buyer, 5 * TICKET_PRICE, &creator)], &[buyer]).unwrap();
+    assert_eq!(svm.lamports(&creator), creator_before + 4 * TICKET_PRICE);
+    assert_eq!(svm.lamports(&treasury_wallet()), treasury_before + TICKET_PRICE);
+    assert_eq!(raffle(&svm).jackpot, 0);
+
+    // The draw needs the prize accounts, then sends the prize to a new associated token account
+    svm.advance_time(RAFFLE_DURATION);
+    svm.process(&[instructions::lock_round()], &[]).unwrap();
+    svm.advance_slots(2);
+    let payer = svm.new_wallet(SOL);
+    let end_time = raffle(&svm).end_time;
+    assert_eq!(
+        svm.process(&[instructions::select_winner(&payer, end_time, &buyer)], &[payer]),
+        Err(raffle_error(RaffleError::InvalidAssetPrize))
+    );
+    let asset_prize = state::decode_asset_prize(&svm.account(&pda::asset_prize_address()).unwrap().data).unwrap();
+    let escrow_rent = svm.lamports(&pda::asset_prize_address()) + svm.lamports(&pda::prize_vault_address());
+    let creator_before = svm.lamports(&creator);
+    svm.process(&[instructions::select_asset_winner(&payer, end_time, &buyer, &asset_prize)], &[payer]).unwrap();
+
+    assert_eq!(svm.token_balance(&get_associated_token_address(&buyer, &mint)), 1);
+    assert!(svm.account(&pda::asset_prize_address()).is_none());
+    assert!(svm.account(&pda::prize_vault_address()).is_none());
+    assert_eq!(svm.lamports(&creator), creator_before + escrow_rent);
+    assert!(!raffle(&svm).asset_round);
+
+    // The next round is back to a SOL jackpot
+    svm.advance_time(10);
+    buy(&mut svm, &buyer, TICKET_PRICE).unwrap();
+}
+
+#[test]
+fn asset_round_tickets_are_final() {
+    let (mut svm, admin) = setup();
+    let buyer = svm.new_wallet(SOL);
+    buy(&mut svm, &buyer, TICKET_PRICE).unwrap();
+    svm.advance_time(RAFFLE_DURATION);
+    draw(&mut svm).unwrap();
+    svm.advance_time(10);
+
+    // The creator already has the revenue, so no receipt is kept and even a
+    // buyer holding one from an earlier round can't cancel
+    let (creator, mint, creator_tokens) = create_asset_raffle(&mut svm, &admin, 1);
+    svm.process(&[instructions::deposit_prize(&creator, &creator_tokens, &mint)], &[creator]).unwrap();
+    svm.process(&[instructions::buy_asset_ticket(&buyer, TICKET_PRICE, &creator)], &[buyer]).unwrap();
+    let receipt = state::decode_ticket_receipt(&svm.account(&pda::receipt_address(&buyer)).unwrap().data).unwrap();
+    assert_ne!(receipt.round_end_time, raffle(&svm).end_time);
+    assert_eq!(
+        svm.process(&[instructions::cancel_tickets(&buyer, 1)], &[buyer]),
+        Err(raffle_error(RaffleError::AssetTicketsNotCancellable))
+    );
+}
+
+#[test]
+fn unwinnable_asset_prizes_are_returned_to_the_creator() {
+    let (mut svm, admin) = setup();
+    let (creator, mint, creator_tokens) = create_asset_raffle(&mut svm, &admin, 3);
+    svm.process(&[instructions::deposit_prize(&creator, &creator_tokens, &mint)], &[creator]).unwrap();
+    let asset_prize = state::decode_asset_prize(&svm.account(&pda::asset_prize_address()).unwrap().data).unwrap();
+
+    // Not while the round can still sell tickets, nor by anyone else
+    assert_eq!(
+        svm.process(&[instructions::reclaim_prize(&creator, &creator_tokens, &asset_prize)], &[creator]),
+        Err(raffle_error(RaffleError::PrizeNotReclaimable))
+    );
+    svm.process(&[instructions::cancel_round(&admin)], &[admin]).unwrap();
+    let stranger = svm.new_wallet(SOL);
+    assert_eq!(
+        svm.process(&[instructions::reclaim_prize(&stranger, &creator_tokens, &asset_prize)], &[stranger]),
+        Err(raffle_error(RaffleError::Unauthorized))
+    );
+
+    // A cancelled round's prize goes back with the escrow's rent
+    let escrow_rent = svm.lamports(&pda::asset_prize_address()) + svm.lamports(&pda::prize_vault_address());
+    let creator_before = svm.lamports(&creator);
+    svm.process(&[instructions::reclaim_prize(&creator, &creator_tokens, &asset_prize)], &[creator]).unwrap();
+    assert_eq!(svm.token_balance(&creator_tokens), 3);
+    assert_eq!(svm.lamports(&creator), creator_before + escrow_rent);
+    assert!(svm.account(&pda::asset_prize_address()).is_none());
+    assert!(svm.account(&pda::prize_vault_address()).is_none());
+    assert!(!raffle(&svm).asset_round);
+
+    // So does the prize of a round that ended unsold, reclaimed by the admin
+    svm.process(&[instructions::set_schedule(&admin, Schedule::default())], &[admin]).unwrap();
+    svm.process(&[instructions::create_asset_raffle(&admin, &creator, &mint, 3)], &[admin, creator]).unwrap();
+    svm.process(&[instructions::deposit_prize(&creator, &creator_tokens, &mint)], &[creator]).unwrap();
+    svm.advance_time(RAFFLE_DURATION);
+    svm.process(&[instructions::reclaim_prize(&admin, &creator_tokens, &asset_prize)], &[admin]).unwrap();
+    assert_eq!(svm.token_balance(&creator_tokens), 3);
+    assert!(!raffle(&svm).asset_round);
+}
+
+#[test]
+fn create_asset_raffle_needs_the_admin_and_an_empty_round() {
+    let (mut svm, admin) = setup();
+    let creator = svm.new_wallet(SOL);
+    let mint = Pubkey::new_unique();
+    svm.new_mint(mint, 0);
+
+    assert_eq!(
+        svm.process(&[instructions::create_asset_raffle(&creator, &creator, &mint, 1)], &[creator]),
+        Err(raffle_error(RaffleError::Unauthorized))
+    );
+    assert_eq!(
+        svm.process(&[instructions::create_asset_raffle(&admin, &creator, &mint, 0)], &[admin, creator]),
+        Err(raffle_error(RaffleError::IncorrectAmount))
+    );
+    let buyer = svm.new_wallet(SOL);
+    buy(&mut svm, &buyer, TICKET_PRICE).unwrap();
+    assert_eq!(
+        svm.process(&[instructions::create_asset_raffle(&admin, &creator, &mint, 1)], &[admin, creator]),
+        Err(raffle_error(RaffleError::RoundInProgress))
+    );
+}
+
+#[test]
+fn select_winner_rejects_wrong_winner() {
+    let (mut svm, _) = setup();
+    let buyer = svm.new_wallet(SOL);
+    buy(&mut svm, &buyer, TICKET_PRICE).unwrap();
+    svm.advance_time(RAFFLE_DURATION);
+    svm.process(&[instructions::lock_round()], &[]).unwrap();
+    svm.advance_slots(2);
+    let impostor = svm.new_wallet(SOL);
+    let end_time = raffle(&svm).end_time;
+
+    assert_eq!(
+        svm.process(&[instructions::select_winner(&impostor, end_time, &impostor)], &[impostor]),
+        Err(raffle_error(RaffleError::InvalidWinnerAccount))
+    );
+}
+
+#[test]
+fn denied_wallets_cannot_buy_and_are_redrawn() {
+    let (mut svm, admin) = setup();
+    let alice = svm.new_wallet(SOL);
+    let bob = svm.new_wallet(SOL);
+    let mallory = svm.new_wallet(SOL);
+    assert_eq!(
+        svm.process(&[instructions::deny_wallet(&alice, &mallory)], &[alice]),
+        Err(raffle_error(RaffleError::Unauthorized))
+    );
+    svm.process(&[instructions::deny_wallet(&admin, &mallory)], &[admin]).unwrap();
+
+    // Neither buying nor being gifted tickets gets a denied wallet in
+    assert_eq!(buy(&mut svm, &mallory, TICKET_PRICE), Err(raffle_error(RaffleError::WalletDenied)));
+    assert_eq!(
+        svm.process(&[instructions::buy_ticket_for(&alice, TICKET_PRICE, Some(mallory), None)], &[alice]),
+        Err(raffle_error(RaffleError::WalletDenied))
+    );
+
+    // A wallet denied after entering is skipped if drawn. Slot hashes are known
+    // ahead here, so the test can deny whoever the lock is going to draw.
+    buy(&mut svm, &alice, 5 * TICKET_PRICE).unwrap();
+    buy(&mut svm, &bob, 5 * TICKET_PRICE).unwrap();
+    let tickets = raffle(&svm).tickets;
+    svm.advance_time(RAFFLE_DURATION);
+    let lock_slot = svm.clock.slot;
+    let predicted = DrawTranscript::new(lock_slot, lock_slot + 1, Svm::slot_hash(lock_slot + 1).to_bytes(), &tickets);
+    let first_drawn = tickets[predicted.winning_index as usize];
+    let other = if first_drawn == alice { bob } else { alice };
+    svm.process(&[instructions::deny_wallet(&admin, &first_drawn)], &[admin]).unwrap();
+    svm.process(&[instructions::lock_round()], &[]).unwrap();
+    svm.advance_slots(2);
+    let current = raffle(&svm);
+    assert_eq!(state::drawn_winner(&current, &slot_hashes(&svm), &DenyList::default()), Ok(first_drawn));
+    assert_eq!(state::drawn_winner(&current, &slot_hashes(&svm), &deny_list(&svm)), Ok(other));
+
+    // Once the round is locked the list is frozen until the draw
+    assert_eq!(
+        svm.process(&[instructions::allow_wallet(&admin, &first_drawn)], &[admin]),
+        Err(raffle_error(RaffleError::DenyListLocked))
+    );
+    assert_eq!(
+        svm.process(&[instructions::deny_wallet(&admin, &other)], &[admin]),
+        Err(raffle_error(RaffleError::DenyListLocked))
+    );
+
+    let payer = svm.new_wallet(SOL);
+    assert_eq!(
+        svm.process(&[instructions::select_winner(&payer, current.end_time, &first_drawn)], &[payer]),
+        Err(raffle_error(RaffleError::InvalidWinnerAccount))
+    );
+    svm.process(&[instructions::select_winner(&payer, current.end_time, &other)], &[payer]).unwrap();
+    // The record explains the payment: the first pick, the redraws past it and
+    // the deny list they skipped
+    let denied = deny_list(&svm);
+    let record = state::decode_draw_record(&svm.account(&pda::draw_record_address(current.end_time)).unwrap().data).unwrap();
+    assert_eq!(record.winner, other);
+    assert_eq!(record.transcript.verify(&tickets), Ok(first_drawn));
+    assert!(record.redraws > 0);
+    assert_eq!(tickets[record.paid_index as usize], other);
+    assert_eq!(record.deny_list_hash, denied.hash());
+    assert_eq!(record.verify(&tickets, &denied), Ok(other));
+    let invalid = Err(RaffleError::InvalidDrawTranscript.into());
+    assert_eq!(record.verify(&tickets, &DenyList::default()), invalid);
+    let doctored = DrawRecord { winner: first_drawn, ..record.clone() };
+    assert_eq!(doctored.verify(&tickets, &denied), invalid);
+
+    svm.process(&[instructions::allow_wallet(&admin, &mallory)], &[admin]).unwrap();
+    svm.advance_time(10);
+    buy(&mut svm, &mallory, TICKET_PRICE).unwrap();
+}
+
+#[test]
+fn draw_uses_the_first_slot_hash_after_the_lock() {
+    let (mut svm, _) = setup();
+    let buyers: Vec<Pubkey> = (0..5).map(|_| svm.new_wallet(SOL)).collect();
+    for buyer in &buyers {
+        buy(&mut svm, buyer, 3 * TICKET_PRICE).unwrap();
+    }
+    svm.advance_time(RAFFLE_DURATION);
+    let payer = svm.new_wallet(SOL);
+    let end_time = raffle(&svm).end_time;
+
+    // Drawing needs a lock, and the lock can't be taken while sales are open
+    assert_eq!(
+        svm.process(&[instructions::select_winner(&payer, end_time, &buyers[0])], &[payer]),
+        Err(raffle_error(RaffleError::RoundNotLocked))
+    );
+    svm.process(&[instructions::lock_round()], &[]).unwrap();
+    let locked = raffle(&svm);
+    assert_eq!(locked.draw_slot, svm.clock.slot);
+    assert_eq!(
+        svm.process(&[instructions::lock_round()], &[]),
+        Err(raffle_error(RaffleError::RoundAlreadyLocked))
+    );
+
+    // The target slot itself is not enough, only a slot after it
+    svm.advance_slots(1);
+    assert_eq!(
+        svm.process(&[instructions::select_winner(&payer, end_time, &buyers[0])], &[payer]),
+        Err(raffle_error(RaffleError::DrawTooSoon))
+    );
+
+    svm.advance_slots(20);
+    let entropy_slot = locked.draw_slot + 1;
+    let slot_hash = Svm::slot_hash(entropy_slot).to_bytes();
+    let winner = state::drawn_winner(&locked, &slot_hashes(&svm), &deny_list(&svm)).unwrap();
+    svm.process(&[instructions::select_winner(&payer, end_time, &winner)], &[payer]).unwrap();
+
+    let record = state::decode_draw_record(&svm.account(&pda::draw_record_address(end_time)).unwrap().data).unwrap();
+    assert_eq!(record.round_end_time, end_time);
+    assert_eq!(record.winner, winner);
+    assert_eq!(record.transcript.draw_slot, locked.draw_slot);
+    assert_eq!(record.transcript.entropy_slot, entropy_slot);
+    assert_eq!(record.transcript.slot_hash, slot_hash);
+    assert_eq!(raffle(&svm).draw_slot, 0);
+}
+
+#[test]
+fn draw_transcript_replays_to_the_winner() {
+    let (mut svm, _) = setup();
+    let buyers: Vec<Pubkey> = (0..4).map(|_| svm.new_wallet(SOL)).collect();
+    for (i, buyer) in buyers.iter().enumerate() {
+        buy(&mut svm, buyer, (i as u64 + 1) * TICKET_PRICE).unwrap();
+    }
+    let tickets = raffle(&svm).tickets;
+    svm.advance_time(RAFFLE_DURATION);
+    let end_time = raffle(&svm).end_time;
+    let winner = draw(&mut svm).unwrap();
+
+    let record = state::decode_draw_record(&svm.account(&pda::draw_record_address(end_time)).unwrap().data).unwrap();
+    let transcript = record.transcript;
+    assert_eq!(transcript.ledger_hash, psyduk_raffle::ledger_hash(&tickets));
+    assert_eq!(transcript.modulus, tickets.len() as u64);
+    assert_eq!(tickets[transcript.winning_index as usize], winner);
+    assert_eq!(transcript.verify(&tickets), Ok(winner));
+    assert_eq!((record.redraws, record.paid_index), (0, transcript.winning_index));
+    assert_eq!(record.verify(&tickets, &deny_list(&svm)), Ok(winner));
+
+    // A different ledger or a doctored value no longer replays
+    let invalid = Err(RaffleError::InvalidDrawTranscript.into());
+    assert_eq!(transcript.verify(&tickets[1..]), invalid);
+    let mut reordered = tickets.clone();
+    reordered.reverse();
+    assert_eq!(transcript.verify(&reordered), invalid);
+    for doctored in [
+        DrawTranscript { winning_index: (transcript.winning_index + 1) % transcript.modulus, ..transcript },
+        DrawTranscript { slot_hash: [7; 32], ..transcript },
+        DrawTranscript { entropy_slot: transcript.draw_slot, ..transcript },
+    ] {
+        assert_eq!(doctored.verify(&tickets), invalid);
+    }
+}
+
+#[test]
+fn batched_draw_pays_the_same_winner_in_chunks() {
+    let (mut svm, _) = setup();
+    let buyers: Vec<Pubkey> = (0..4).map(|_| svm.new_wallet(SOL)).collect();
+    for (i, buyer) in buyers.iter().enumerate() {
+        buy(&mut svm, buyer, (i as u64 + 1) * TICKET_PRICE).unwrap();
+    }
+    let tickets = raffle(&svm).tickets;
+    let keeper = svm.new_wallet(SOL);
+    assert_eq!(svm.process(&[instructions::begin_draw(&keeper)], &[keeper]), Err(raffle_error(RaffleError::RaffleStillActive)));
+    svm.advance_time(RAFFLE_DURATION);
+    svm.process(&[instructions::lock_round()], &[]).unwrap();
+    assert_eq!(buy(&mut svm, &buyers[0], TICKET_PRICE), Err(raffle_error(RaffleError::SalesClosed)));
+    assert_eq!(raffle(&svm).status, RoundStatus::SalesClosed);
+    svm.advance_slots(2);
+    let current = raffle(&svm);
+    let expected = state::drawn_winner(&current, &slot_hashes(&svm), &deny_list(&svm)).unwrap();
+
+    svm.process(&[instructions::begin_draw(&keeper)], &[keeper]).unwrap();
+    assert_eq!(raffle(&svm).status, RoundStatus::Drawing);
+    assert_eq!(svm.process(&[instructions::begin_draw(&keeper)], &[keeper]), Err(raffle_error(RaffleError::DrawAlreadyStarted)));
+    assert_eq!(
+        svm.process(&[instructions::select_winner(&keeper, current.end_time, &expected)], &[keeper]),
+        Err(raffle_error(RaffleError::DrawAlreadyStarted))
+    );
+
+    // Any wallet can hash the ledger, which must be complete before paying out
+    for _ in 0..3 {
+        assert_eq!(
+            svm.process(&[instructions::finalize_draw(&keeper, current.end_time, &expected)], &[keeper]),
+            Err(raffle_error(RaffleError::LedgerNotHashed))
+        );
+        svm.process(&[instructions::advance_draw(4)], &[]).unwrap();
+    }
+    assert_eq!(raffle(&svm).status, RoundStatus::PayingOut);
+    assert_eq!(svm.process(&[instructions::advance_draw(4)], &[]), Err(raffle_error(RaffleError::LedgerAlreadyHashed)));
+
+    // The entropy was fixed at the start, so the slot hash may age out meanwhile
+    svm.advance_slots(SLOT_HASH_WINDOW + 10);
+    let progress = state::decode_draw_progress(&svm.account(&pda::draw_progress_address()).unwrap().data).unwrap();
+    assert_eq!(state::batched_winner(&current, &progress, &deny_list(&svm)), Ok(expected));
+    let finisher = svm.new_wallet(SOL);
+    let jackpot = current.jackpot;
+    let before = svm.lamports(&expected);
+    svm.process(&[instructions::finalize_draw(&finisher, current.end_time, &expected)], &[finisher]).unwrap();
+
+    assert_eq!(svm.lamports(&expected), before + jackpot);
+    assert!(svm.account(&pda::draw_progress_address()).is_none_or(|account| account.lamports == 0));
+    let record = state::decode_draw_record(&svm.account(&pda::draw_record_address(current.end_time)).unwrap().data).unwrap();
+    assert_eq!(record.verify(&tickets, &deny_list(&svm)), Ok(expected));
+    assert!(raffle(&svm).tickets.is_empty());
+    assert_eq!(raffle(&svm).status, RoundStatus::Open);
+}
+
+#[test]
+fn expired_lock_can_be_renewed() {
+    let (mut svm, _) = setup();
+    let buyer = svm.new_wallet(SOL);
+    buy(&mut svm, &buyer, TICKET_PRICE).unwrap();
+    svm.advance_time(RAFFLE_DURATION);
+    svm.process(&[instructions::lock_round()], &[]).unwrap();
+    let first_target = raffle(&svm).draw_slot;
+
+    svm.advance_slots(SLOT_HASH_WINDOW + 1);
+    let payer = svm.new_wallet(SOL);
+    let end_time = raffle(&svm).end_time;
+    assert_eq!(
+        svm.process(&[instructions::select_winner(&payer, end_time, &buyer)], &[payer]),
+        Err(raffle_error(RaffleError::DrawSlotExpired))
+    );
+
+    svm.process(&[instructions::lock_round()], &[]).unwrap();
+    assert!(raffle(&svm).draw_slot > first_target);
+    assert_eq!(draw(&mut svm), Ok(buyer));
+}
+
+#[test]
+fn select_winner_on_empty_round_fails() {
+    let (mut svm, _) = setup();
+    svm.advance_time(RAFFLE_DURATION);
+
+    assert_eq!(draw(&mut svm), Err(raffle_error(RaffleError::NoTickets)));
+}
+
+#[test]
+fn recurring_schedule_aligns_rounds_to_the_clock() {
+    // Every 15 minutes on the clock; genesis falls on a quarter hour
+    let (mut svm, _) = setup_with_schedule(recurring(0, 900, 900));
+    assert_eq!(raffle(&svm).start_time, common::GENESIS_TIMESTAMP);
+    assert_eq!(raffle(&svm).end_time, common::GENESIS_TIMESTAMP + 900);
+
+    // A late draw does not shift the next round
+    let buyer = svm.new_wallet(SOL);
+    buy(&mut svm, &buyer, TICKET_PRICE).unwrap();
+    svm.advance_time(900 + 120);
+    draw(&mut svm).unwrap();
+
+    let raffle = raffle(&svm);
+    assert_eq!(raffle.start_time, common::GENESIS_TIMESTAMP + 900);
+    assert_eq!(raffle.end_time, common::GENESIS_TIMESTAMP + 1800);
+}
+
+#[test]
+fn daily_schedule_closes_at_twenty_hundred_utc() {
+    let (svm, _) = setup_with_schedule(recurring(20 * 3600, 86_400, 86_400));
+
+    let raffle = raffle(&svm);
+    assert_eq!(raffle.end_time.rem_euclid(86_400), 20 * 3600);
+    assert!(raffle.start_time <= svm.clock.unix_timestamp && svm.clock.unix_timestamp < raffle.end_time);
+}
+
+#[test]
+fn one_off_round_rejects_early_purchases_and_closes_after_its_draw() {
+    let schedule = Schedule {
+        kind: ScheduleKind::OneOff,
+        anchor: common::GENESIS_TIMESTAMP + 3600,
+        period: 0,
+        duration: 600,
+    };
+    let (mut svm, _) = setup_with_schedule(schedule);
+    let buyer = svm.new_wallet(SOL);
+
+    assert_eq!(buy(&mut svm, &buyer, TICKET_PRICE), Err(raffle_error(RaffleError::RoundNotStarted)));
+
+    svm.advance_time(3000);
+    buy(&mut svm, &buyer, TICKET_PRICE).unwrap();
+    svm.advance_time(600);
+    draw(&mut svm).unwrap();
+
+    let raffle = raffle(&svm);
+    assert_eq!((raffle.start_time, raffle.end_time), (NO_ROUND, NO_ROUND));
+    assert_eq!(raffle.status, RoundStatus::Settled);
+    assert_eq!(buy(&mut svm, &buyer, TICKET_PRICE), Err(raffle_error(RaffleError::NoRoundScheduled)));
+}
+
+#[test]
+fn empty_round_rolls_forward_on_purchase() {
+    let (mut svm, _) = setup_with_schedule(recurring(0, 900, 900));
+    svm.advance_time(2000);
+    let buyer = svm.new_wallet(SOL);
+
+    buy(&mut svm, &buyer, TICKET_PRICE).unwrap();
+
+    let raffle = raffle(&svm);
+    assert_eq!(raffle.start_time, common::GENESIS_TIMESTAMP + 1800);
+    assert_eq!(raffle.end_time, common::GENESIS_TIMESTAMP + 2700);
+}
+
+#[test]
+fn set_schedule_is_admin_only_and_retimes_an_empty_round() {
+    let (mut svm, admin) = setup();
+    let stranger = svm.new_wallet(SOL);
+    let schedule = recurring(0, 900, 300);
+
+    assert_eq!(
+        svm.process(&[instructions::set_schedule(&stranger, schedule)], &[stranger]),
+        Err(raffle_error(RaffleError::Unauthorized))
+    );
+    assert_eq!(
+        svm.process(&[instructions::set_schedule(&admin, recurring(0, 900, 901))], &[admin]),
+        Err(raffle_error(RaffleError::InvalidSchedule))
+    );
+
+    svm.process(&[instructions::set_schedule(&admin, schedule)], &[admin]).unwrap();
+
+    let raffle = raffle(&svm);
+    assert_eq!(raffle.schedule, schedule);
+    assert_eq!(raffle.start_time, common::GENESIS_TIMESTAMP + 600);
+    assert_eq!(raffle.end_time, common::GENESIS_TIMESTAMP + 900);
+}
+
+#[test]
+fn cancelled_round_takes_no_entries_until_rescheduled() {
+    let (mut svm, admin) = setup();
+    let stranger = svm.new_wallet(SOL);
+    let buyer = svm.new_wallet(SOL);
+
+    assert_eq!(
+        svm.process(&[instructions::cancel_round(&stranger)], &[stranger]),
+        Err(raffle_error(RaffleError::Unauthorized))
+    );
+    buy(&mut svm, &buyer, TICKET_PRICE).unwrap();
+    assert_eq!(
+        svm.process(&[instructions::cancel_round(&admin)], &[admin]),
+        Err(raffle_error(RaffleError::RoundInProgress))
+    );
+    svm.advance_time(RAFFLE_DURATION);
+    draw(&mut svm).unwrap();
+    svm.advance_time(10);
+
+    svm.process(&[instructions::cancel_round(&admin)], &[admin]).unwrap();
+    assert_eq!(raffle(&svm).status, RoundStatus::Cancelled);
+    assert_eq!(buy(&mut svm, &buyer, TICKET_PRICE), Err(raffle_error(RaffleError::RoundCancelled)));
+    assert_eq!(
+        svm.process(&[instructions::cancel_round(&admin)], &[admin]),
+        Err(raffle_error(RaffleError::RoundCancelled))
+    );
+
+    svm.process(&[instructions::set_schedule(&admin, Schedule::default())], &[admin]).unwrap();
+    assert_eq!(raffle(&svm).status, RoundStatus::Open);
+    buy(&mut svm, &buyer, TICKET_PRICE).unwrap();
+}
+
+#[test]
+fn subscription_enters_each_round_once_and_refunds_unused_rounds() {
+    let (mut svm, _) = setup();
+    let subscriber = svm.new_wallet(SOL);
+    let crank = svm.new_wallet(SOL);
+    let subscription = pda::subscription_address(&subscriber);
+
+    svm.process(&[instructions::subscribe(&subscriber, 2, 3)], &[subscriber]).unwrap();
+    let escrow_rent = svm.rent.minimum_balance(psyduk_raffle::Subscription::SPACE);
+    assert_eq!(svm.lamports(&subscription), escrow_rent + 6 * TICKET_PRICE);
+
+    // Applying twice in the same round enters the tickets once
+    for _ in 0..2 {
+        svm.process(&[instructions::apply_subscriptions(&crank, &[subscriber])], &[crank]).unwrap();
+    }
+    let raffle_account = raffle(&svm);
+    assert_eq!(raffle_account.tickets, vec![subscriber, subscriber]);
+    assert_eq!(raffle_account.jackpot, 2 * TICKET_PRICE * 80 / 100);
+    assert_eq!(svm.lamports(&subscription), escrow_rent + 4 * TICKET_PRICE);
+
+    // The next round gets its own entry
+    svm.advance_time(RAFFLE_DURATION);
+    assert_eq!(draw(&mut svm), Ok(subscriber));
+    svm.advance_time(10);
+    svm.process(&[instructions::apply_subscriptions(&crank, &[subscriber])], &[crank]).unwrap();
+    assert_eq!(raffle(&svm).tickets, vec![subscriber, subscriber]);
+    let account = svm.account(&subscription).unwrap().clone();
+    assert_eq!(state::decode_subscription(&account.data).unwrap().rounds_remaining, 1);
+
+    // The remaining round and the rent come back
+    let before = svm.lamports(&subscriber);
+    svm.process(&[instructions::unsubscribe(&subscriber)], &[subscriber]).unwrap();
+    assert_eq!(svm.lamports(&subscriber), before + escrow_rent + 2 * TICKET_PRICE);
+    assert!(svm.account(&subscription).is_none());
+}
+
+#[test]
+fn subscriptions_that_overflow_one_instruction_wait_for_the_next() {
+    let (mut svm, _) = setup();
+    let crank = svm.new_wallet(SOL);
+    let max = MAX_TICKETS_PER_INSTRUCTION as u64;
+    let alice = svm.new_wallet(10 * SOL);
+    let bob = svm.new_wallet(10 * SOL);
+
+    assert_eq!(
+        svm.process(&[instructions::subscribe(&alice, max + 1, 1)], &[alice]),
+        Err(raffle_error(RaffleError::TooManyTicketsPerInstruction))
+    );
+    svm.process(&[instructions::subscribe(&alice, max, 2)], &[alice]).unwrap();
+    svm.process(&[instructions::subscribe(&bob, 1, 2)], &[bob]).unwrap();
+
+    // Alice fills the first instruction, so Bob is entered by the next one
+    svm.process(&[instructions::apply_subscriptions(&crank, &[alice, bob])], &[crank]).unwrap();
+    assert_eq!(raffle(&svm).tickets.len(), MAX_TICKETS_PER_INSTRUCTION);
+    svm.process(&[instructions::apply_subscriptions(&crank, &[alice, bob])], &[crank]).unwrap();
+    let tickets = raffle(&svm).tickets;
+    assert_eq!(tickets.len(), MAX_TICKETS_PER_INSTRUCTION + 1);
+    assert_eq!(tickets.last(), Some(&bob));
+}
+
+#[test]
+fn apply_subscriptions_rejects_foreign_accounts() {
+    let (mut svm, _) = setup();
+    let crank = svm.new_wallet(SOL);
+    let mut instruction = instructions::apply_subscriptions(&crank, &[]);
+    instruction.accounts.push(AccountMeta::new(crank, false));
+
+    assert_eq!(
+        svm.process(&[instruction], &[crank]),
+        Err(raffle_error(RaffleError::InvalidSubscriptionAccount))
+    );
+}
+
+#[test]
+fn free_tickets_are_claimable_once_with_a_valid_proof() {
+    let (mut svm, admin) = setup();
+    let alice = svm.new_wallet(SOL);
+    let bob = svm.new_wallet(SOL);
+    let grants = [(alice, 3), (bob, 1), (Pubkey::new_unique(), 2)];
+    let tree = FreeTicketTree::new(&grants);
+    let round_end_time = raffle(&svm).end_time;
+
+    // Only the admin publishes promotions
+    assert_eq!(
+        svm.process(&[instructions::publish_free_tickets(&alice, round_end_time, tree.root())], &[alice]),
+        Err(raffle_error(RaffleError::Unauthorized))
+    );
+    svm.process(&[instructions::publish_free_tickets(&admin, round_end_time, tree.root())], &[admin]).unwrap();
+
+    // Proofs are bound to the wallet and the ticket count
+    assert_eq!(
+        svm.process(&[instructions::claim_free_tickets(&alice, round_end_time, 4, tree.proof(0))], &[alice]),
+        Err(raffle_error(RaffleError::InvalidMerkleProof))
+    );
+    assert_eq!(
+        svm.process(&[instructions::claim_free_tickets(&bob, round_end_time, 3, tree.proof(0))], &[bob]),
+        Err(raffle_error(RaffleError::InvalidMerkleProof))
+    );
+
+    svm.process(&[instructions::claim_free_tickets(&alice, round_end_time, 3, tree.proof(0))], &[alice]).unwrap();
+    svm.process(&[instructions::claim_free_tickets(&bob, round_end_time, 1, tree.proof(1))], &[bob]).unwrap();
+    let raffle_account = raffle(&svm);
+    assert_eq!(state::tickets_of(&raffle_account, &alice), 3);
+    assert_eq!(state::tickets_of(&raffle_account, &bob), 1);
+    assert_eq!(raffle_account.jackpot, 0);
+
+    // The receipt blocks a second claim
+    assert!(svm
+        .process(&[instructions::claim_free_tickets(&alice, round_end_time, 3, tree.proof(0))], &[alice])
+        .is_err());
+    assert_eq!(state::tickets_of(&raffle(&svm), &alice), 3);
+}
+
+#[test]
+fn free_tickets_expire_with_their_round() {
+    let (mut svm, admin) = setup();
+    let alice = svm.new_wallet(SOL);
+    let bob = svm.new_wallet(SOL);
+    let tree = FreeTicketTree::new(&[(alice, 1)]);
+    let round_end_time = raffle(&svm).end_time;
+    svm.process(&[instructions::publish_free_tickets(&admin, round_end_time, tree.root())], &[admin]).unwrap();
+
+    buy(&mut svm, &bob, TICKET_PRICE).unwrap();
+    svm.advance_time(RAFFLE_DURATION);
+    draw(&mut svm).unwrap();
+    svm.advance_time(10);
+
+    assert_eq!(
+        svm.process(&[instructions::claim_free_tickets(&alice, round_end_time, 1, tree.proof(0))], &[alice]),
+        Err(raffle_error(RaffleError::InvalidPromotion))
+    );
+}
+
+/// Opens staking with one ticket per million base units, at most 20 per stake.
+fn setup_staking(svm: &mut Svm, admin: &Pubkey) {
+    svm.new_mint(psyduk_mint(), 6);
+    svm.process(&[instructions::initialize_staking(admin, 1_000_000, 20)], &[*admin]).unwrap();
+}
+
+#[test]
+fn stakes_earn_tickets_each_round_by_size_and_lock() {
+    let (mut svm, admin) = setup();
+    setup_staking(&mut svm, &admin);
+    let alice = svm.new_wallet(SOL);
+    let bob = svm.new_wallet(SOL);
+    let alice_tokens = svm.new_token_account(&alice, &psyduk_mint(), 10_000_000);
+    let bob_tokens = svm.new_token_account(&bob, &psyduk_mint(), 5_000_000);
+    let crank = svm.new_wallet(SOL);
+
+    svm.process(&[instructions::stake(&alice, &alice_tokens, 5_000_000, 0)], &[alice]).unwrap();
+    svm.process(&[instructions::stake(&bob, &bob_tokens, 5_000_000, MAX_STAKE_LOCK)], &[bob]).unwrap();
+    assert_eq!(svm.token_balance(&alice_tokens), 5_000_000);
+    assert_eq!(svm.token_balance(&pda::stake_vault_address()), 10_000_000);
+    let pool = state::decode_stake_pool(&svm.account(&pda::stake_pool_address()).unwrap().data).unwrap();
+    assert_eq!(pool.total_staked, 10_000_000);
+
+    // A full lock doubles the tickets, and each round is entered once
+    for _ in 0..2 {
+        svm.process(&[instructions::allocate_stake_tickets(&crank, &[alice, bob])], &[crank]).unwrap();
+    }
+    let raffle_account = raffle(&svm);
+    assert_eq!(state::tickets_of(&raffle_account, &alice), 5);
+    assert_eq!(state::tickets_of(&raffle_account, &bob), 10);
+    assert_eq!(raffle_account.jackpot, 0);
+
+    // Staking more adds to the position, within the per-stake cap, while the
+    // boost runs down with the time left on the lock
+    svm.process(&[instructions::stake(&alice, &alice_tokens, 5_000_000, 0)], &[alice]).unwrap();
+    svm.advance_time(RAFFLE_DURATION);
+    draw(&mut svm).unwrap();
+    svm.advance_time(10);
+    svm.process(&[instructions::allocate_stake_tickets(&crank, &[alice, bob])], &[crank]).unwrap();
+    let raffle_account = raffle(&svm);
+    assert_eq!(state::tickets_of(&raffle_account, &alice), 10);
+    assert_eq!(state::tickets_of(&raffle_account, &bob), 9);
+}
+
+#[test]
+fn lapsed_locks_lose_their_ticket_boost() {
+    let (mut svm, admin) = setup();
+    setup_staking(&mut svm, &admin);
+    let alice = svm.new_wallet(SOL);
+    let alice_tokens = svm.new_token_account(&alice, &psyduk_mint(), 4_000_000);
+    let crank = svm.new_wallet(SOL);
+    svm.process(&[instructions::stake(&alice, &alice_tokens, 4_000_000, MAX_STAKE_LOCK)], &[alice]).unwrap();
+    let pool = state::decode_stake_pool(&svm.account(&pda::stake_pool_address()).unwrap().data).unwrap();
+    let position = state::decode_stake_position(&svm.account(&pda::stake_position_address(&alice)).unwrap().data).unwrap();
+    assert_eq!(pool.tickets_for(&position, position.locked_until - MAX_STAKE_LOCK), 8);
+    assert_eq!(pool.tickets_for(&position, position.locked_until - MAX_STAKE_LOCK / 2), 6);
+
+    // Once the lock lapses the stake earns its base tickets only
+    svm.advance_time(MAX_STAKE_LOCK + 1);
+    assert_eq!(pool.tickets_for(&position, position.locked_until + 1), 4);
+    svm.process(&[instructions::allocate_stake_tickets(&crank, &[alice])], &[crank]).unwrap();
+    assert_eq!(state::tickets_of(&raffle(&svm), &alice), 4);
+}
+
+#[test]
+fn large_stakes_are_spread_over_instructions() {
+    let (mut svm, admin) = setup();
+    svm.new_mint(psyduk_mint(), 6);
+    let max = MAX_TICKETS_PER_INSTRUCTION as u64;
+    assert_eq!(
+        svm.process(&[instructions::initialize_staking(&admin, 1_000_000, max + 1)], &[admin]),
+        Err(raffle_error(RaffleError::TooManyTicketsPerInstruction))
+    );
+    svm.process(&[instructions::initialize_staking(&admin, 1_000_000, max)], &[admin]).unwrap();
+    let crank = svm.new_wallet(SOL);
+
+    // Each stake earns the most tickets one instruction can add
+    let stakers: Vec<Pubkey> = (0..3)
+        .map(|_| {
+            let staker = svm.new_wallet(SOL);
+            let tokens = svm.new_token_account(&staker, &psyduk_mint(), max * 1_000_000);
+            svm.process(&[instructions::stake(&staker, &tokens, max * 1_000_000, MAX_STAKE_LOCK)], &[staker]).unwrap();
+            staker
+        })
+        .collect();
+
+    // Every pass over the whole batch enters the next stake that fits
+    for entered in 1..=stakers.len() {
+        svm.process(&[instructions::allocate_stake_tickets(&crank, &stakers)], &[crank]).unwrap();
+        assert_eq!(raffle(&svm).tickets.len(), entered * MAX_TICKETS_PER_INSTRUCTION);
+    }
+    for staker in &stakers {
+        assert_eq!(state::tickets_of(&raffle(&svm), staker), MAX_TICKETS_PER_INSTRUCTION);
+    }
+}
+
+#[test]
+fn unstaking_waits_for_the_lock_and_the_cooldown() {
+    let (mut svm, admin) = setup();
+    setup_staking(&mut svm, &admin);
+    let alice = svm.new_wallet(SOL);
+    let alice_tokens = svm.new_token_account(&alice, &psyduk_mint(), 4_000_000);
+    let crank = svm.new_wallet(SOL);
+    let day = 24 * 60 * 60;
+    svm.process(&[instructions::stake(&alice, &alice_tokens, 3_000_000, day)], &[alice]).unwrap();
+
+    assert_eq!(
+        svm.process(&[instructions::request_unstake(&alice)], &[alice]),
+        Err(raffle_error(RaffleError::StakeLocked))
+    );
+    svm.advance_time(day);
+    svm.process(&[instructions::request_unstake(&alice)], &[alice]).unwrap();
+
+    // A stake on its way out earns nothing and takes no top-ups
+    svm.process(&[instructions::allocate_stake_tickets(&crank, &[alice])], &[crank]).unwrap();
+    assert!(raffle(&svm).tickets.is_empty());
+    assert_eq!(
+        svm.process(&[instructions::stake(&alice, &alice_tokens, 1_000_000, 0)], &[alice]),
+        Err(raffle_error(RaffleError::StakeCoolingDown))
+    );
+    assert_eq!(
+        svm.process(&[instructions::unstake(&alice, &alice_tokens)], &[alice]),
+        Err(raffle_error(RaffleError::StakeCoolingDown))
+    );
+
+    svm.advance_time(UNSTAKE_COOLDOWN);
+    svm.process(&[instructions::unstake(&alice, &alice_tokens)], &[alice]).unwrap();
+    assert_eq!(svm.token_balance(&alice_tokens), 4_000_000);
+    assert_eq!(svm.token_balance(&pda::stake_vault_address()), 0);
+    assert!(svm.account(&pda::stake_position_address(&alice)).is_none());
+}
+
+#[test]
+fn reconcile_detects_missing_lamports() {
+    let (mut svm, _) = setup();
+    let buyer = svm.new_wallet(SOL);
+    buy(&mut svm, &buyer, 5 * TICKET_PRICE).unwrap();
+    svm.process(&[instructions::reconcile()], &[]).unwrap();
+
+    let mut vault = svm.account(&pda::vault_address()).unwrap().clone();
+    vault.lamports -= 1;
+    svm.set_account(pda::vault_address(), vault);
+
+    assert_eq!(
+        svm.process(&[instructions::reconcile()], &[]),
+        Err(raffle_error(RaffleError::VaultImbalance))
+    );
+}
+
+#[test]
+fn migrate_upgrades_legacy_layout() {
+    let mut svm = Svm::new();
+    let buyer = Pubkey::new_unique();
+    let legacy = RaffleV0 {
+        jackpot: 8 * TICKET_PRICE / 10,
+        start_time: common::GENESIS_TIMESTAMP,
+        end_time: common::GENESIS_TIMESTAMP + RAFFLE_DURATION,
+        tickets: vec![buyer],
+    };
+    let mut data = Raffle::DISCRIMINATOR.to_vec();
+    legacy.serialize(&mut data).unwrap();
+    data.resize(LEGACY_RAFFLE_SPACE, 0);
+    svm.set_account(
+        pda::raffle_address(),
+        Account {
+            lamports: svm.rent.minimum_balance(LEGACY_RAFFLE_SPACE),
+            data,
+            owner: psyduk_raffle::ID,
+            executable: false,
+        },
+    );
+
+    // Only the upgrade authority may migrate
+    let stranger = svm.new_wallet(SOL);
+    assert_eq!(
+        svm.process(&[instructions::migrate(&stranger)], &[stranger]),
+        Err(raffle_error(RaffleError::Unauthorized))
+    );
+
+    let authority = svm.upgrade_authority;
+    svm.airdrop(&authority, SOL);
+    svm.process(&[instructions::migrate(&authority)], &[authority]).unwrap();
+
+    let raffle = raffle(&svm);
+    assert_eq!(raffle.version, psyduk_raffle::RAFFLE_VERSION);
+    assert_eq!(raffle.admin, authority);
+    assert_eq!(raffle.jackpot, legacy.jackpot);
+    assert_eq!(raffle.end_time, legacy.end_time);
+    assert_eq!(raffle.tickets, legacy.tickets);
+    assert_eq!(raffle.schedule, Schedule::default());
+
+    // Migrating again is a no-op
+    svm.process(&[instructions::migrate(&authority)], &[authority]).unwrap();
+
+    // The vault backs the carried jackpot, which the round's draw pays out
+    assert_eq!(svm.process(&[instructions::reconcile()], &[]), Ok(()));
+    svm.advance_time(RAFFLE_DURATION);
+    assert_eq!(draw(&mut svm), Ok(buyer));
+    assert_eq!(svm.lamports(&buyer), legacy.jackpot);
+    assert_eq!(svm.process(&[instructions::reconcile()], &[]), Ok(()));
+}
+
+#[test]
+fn unknown_instruction_is_rejected() {
+    let (mut svm, _) = setup();
+    let instruction = Instruction {
+        program_id: psyduk_raffle::ID,
+        accounts: vec![AccountMeta::new_readonly(system_program::id(), false)],
+        data: vec![0xff; 8],
+    };
+
+    assert_eq!(
+        svm.process(&[instruction], &[]),
+        Err(anchor_error(ErrorCode::InstructionFallbackNotFound))
+    );
+}
@@ -11,7 +11,7 @@ use teloxide::types::InlineKeyboardButton;
 use log::error;
 use std::env;
 
-const REQUIRED_TOKEN_MINT: &str = "iQuoGfqmXh6J3PShHDntayXGVixfp44wzGkVaH8r8RE"; // Token Mint
+const REQUIRED_TOKEN_MINT: &str = psyduk_raffle_client::PSYDUK_MINT; // Token Mint, shared with the on-chain holder bonus
 const MINIMUM_BALANCE: u64 = 0; // Minimum balance for eligibility
 
 lazy_static! {
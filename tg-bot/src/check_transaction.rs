@@ -1,6 +1,6 @@
 use  std::collections::HashSet;
-use std::convert::TryInto;
 use teloxide::prelude::*;
+use psyduk_raffle_client::{instructions, pda, PROGRAM_ID};
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::{
     pubkey::Pubkey,
@@ -14,12 +14,14 @@ use bincode;
 use std::str::FromStr;
 use tokio::time::{Duration, sleep};
 
+use crate::raffle_state;
+
 const MAX_ATTEMPTS: usize = 5; // Maximum retries for fetching transaction details
 
-/// Monitors Solana transactions to detect payments and sends appropriate bot messages.
+/// Monitors the raffle account's transactions to detect the user's `buy_ticket`
+/// paying `expected_amount` lamports and sends appropriate bot messages.
 pub async fn monitor_transaction(
     user_wallet: String,
-    raffle_wallet: &str,
     expected_amount: u64,
     bot: AutoSend<Bot>,
     chat_id: ChatId,
@@ -27,13 +29,12 @@ pub async fn monitor_transaction(
     use solana_client::rpc_config::RpcTransactionConfig;
     use solana_sdk::commitment_config::CommitmentConfig;
 
-    let client = RpcClient::new_with_timeout(
-        "https://boldest-holy-gadget.solana-devnet.quiknode.pro/43e556824f4d9fdb5fc2f614c2560d8eebf0fc97",
-        Duration::from_secs(15),
-    );
+    let client = RpcClient::new_with_timeout(raffle_state::rpc_url(), Duration::from_secs(15));
 
-    let raffle_pubkey = Pubkey::from_str(raffle_wallet)?;
+    // Every purchase writes the raffle account, so its signatures include the user's
+    let raffle_pubkey = pda::raffle_address();
     let user_pubkey = Pubkey::from_str(&user_wallet)?;
+    let expected_data = instructions::buy_ticket(&user_pubkey, expected_amount).data;
 
     let mut seen_signatures: HashSet<String> = HashSet::new();
     if let Ok(signatures) = client.get_signatures_for_address(&raffle_pubkey) {
@@ -68,12 +69,22 @@ pub async fn monitor_transaction(
                                     fetch_attempt + 1, signature
                                 );
 
+                                let succeeded = transaction_meta
+                                    .transaction
+                                    .meta
+                                    .as_ref()
+                                    .map_or(false, |meta| meta.err.is_none());
+                                if !succeeded {
+                                    eprintln!("Debug: Skipping failed transaction: {:?}", signature);
+                                    break;
+                                }
+
                                 match &transaction_meta.transaction.transaction {
                                     EncodedTransaction::LegacyBinary(encoded, )
                                     | EncodedTransaction::Binary(encoded, _) => {
                                         let tx: VersionedTransaction = bincode::deserialize(&base64::decode(encoded)?)?;
 
-                                        if validate_transaction(&tx, &user_pubkey, &raffle_pubkey, expected_amount) {
+                                        if validate_transaction(&tx, &user_pubkey, &expected_data) {
                                             eprintln!("✅ Valid payment detected for {} lamports.", expected_amount);
 
                                             let _ = bot.send_message(
@@ -120,7 +131,7 @@ pub async fn monitor_transaction(
     }
 
     let _ = bot.send_message(chat_id,
-        "❌ Payment not detected. Please ensure you sent the correct amount to the raffle wallet. If the issue persists, please try again or contact support.",
+        "❌ Purchase not detected. Please make sure you signed and sent the transaction before it expired. If the issue persists, please try again with /buy or contact support.",
     ).await;
 
     Ok(())
@@ -128,20 +139,19 @@ pub async fn monitor_transaction(
 
 
 
-/// Validates if a Solana transaction matches the expected details.
+/// Validates if a Solana transaction contains the expected purchase.
 fn validate_transaction(
     transaction: &VersionedTransaction,
     user_pubkey: &Pubkey,
-    raffle_pubkey: &Pubkey,
-    expected_amount: u64,
+    expected_data: &[u8],
 ) -> bool {
     eprintln!("Debug: Validating transaction: {:?}", transaction);
     match &transaction.message {
         VersionedMessage::Legacy(message) => {
-            validate_legacy_message(message, user_pubkey, raffle_pubkey, expected_amount)
+            validate_legacy_message(message, user_pubkey, expected_data)
         }
         VersionedMessage::V0(message) => {
-            validate_v0_message(message, transaction, user_pubkey, raffle_pubkey, expected_amount)
+            validate_v0_message(message, transaction, user_pubkey, expected_data)
         }
     }
 }
@@ -150,12 +160,11 @@ fn validate_transaction(
 fn validate_legacy_message(
     message: &Message,
     user_pubkey: &Pubkey,
-    raffle_pubkey: &Pubkey,
-    expected_amount: u64,
+    expected_data: &[u8],
 ) -> bool {
     let account_keys = &message.account_keys; // Extract account keys from the legacy message
     for instruction in &message.instructions {
-        if is_valid_transfer(instruction, user_pubkey, raffle_pubkey, expected_amount, account_keys) {
+        if is_valid_purchase(instruction, user_pubkey, expected_data, account_keys) {
             return true;
         }
     }
@@ -167,98 +176,58 @@ fn validate_v0_message(
     message: &solana_sdk::message::v0::Message,
     transaction: &VersionedTransaction,
     user_pubkey: &Pubkey,
-    raffle_pubkey: &Pubkey,
-    expected_amount: u64,
+    expected_data: &[u8],
 ) -> bool {
     let account_keys = &transaction.message.static_account_keys();
 
     for instruction in &message.instructions {
-        if is_valid_transfer(instruction, user_pubkey, raffle_pubkey, expected_amount, account_keys) {
+        if is_valid_purchase(instruction, user_pubkey, expected_data, account_keys) {
             return true;
         }
     }
     false
 }
 
-/// Checks if an instruction is a valid transfer.
-fn is_valid_transfer(
+/// Checks if an instruction is the user's `buy_ticket` for the expected amount.
+fn is_valid_purchase(
     instruction: &CompiledInstruction,
     user_pubkey: &Pubkey,
-    raffle_pubkey: &Pubkey,
-    expected_amount: u64,
+    expected_data: &[u8],
     account_keys: &[Pubkey],
 ) -> bool {
-    let system_program_id = solana_sdk::system_program::id();
-
     // Step 1: Check Program ID
     let program_id = account_keys.get(instruction.program_id_index as usize);
     eprintln!("Debug: Checking instruction with Program ID: {:?}", program_id);
-    if program_id != Some(&system_program_id) {
+    if program_id != Some(&PROGRAM_ID) {
         eprintln!(
-            "Debug: Instruction skipped: Not a System Program instruction. Program ID: {:?}",
+            "Debug: Instruction skipped: Not a raffle instruction. Program ID: {:?}",
             program_id
         );
         return false;
     }
 
-    // Step 2: Validate Accounts
-    if instruction.accounts.len() < 2 {
+    // Step 2: Validate Buyer, the first account of `buy_ticket`
+    let buyer = instruction
+        .accounts
+        .first()
+        .and_then(|index| account_keys.get(*index as usize));
+    if buyer != Some(user_pubkey) {
         eprintln!(
-            "Debug: Instruction skipped: Insufficient accounts. Accounts length: {}",
-            instruction.accounts.len()
+            "Debug: Buyer mismatch. Expected: {}, but got: {:?}",
+            user_pubkey, buyer
         );
         return false;
     }
 
-    let default_pubkey = Pubkey::default();
-    let source_pubkey = account_keys
-        .get(instruction.accounts[0] as usize)
-        .unwrap_or(&default_pubkey);
-    let dest_pubkey = account_keys
-        .get(instruction.accounts[1] as usize)
-        .unwrap_or(&default_pubkey);
-
-    eprintln!(
-        "Debug: Instruction details: Source: {}, Destination: {}, Data Length: {}, Program ID: {}",
-        source_pubkey,
-        dest_pubkey,
-        instruction.data.len(),
-        program_id.unwrap_or(&default_pubkey)
-    );
-
-    if source_pubkey != user_pubkey || dest_pubkey != raffle_pubkey {
-        eprintln!(
-            "Debug: Source/Destination mismatch. Expected Source: {}, Destination: {}, but got Source: {}, Destination: {}",
-            user_pubkey, raffle_pubkey, source_pubkey, dest_pubkey
-        );
-        return false;
-    }
-
-    // Step 3: Validate Transfer Amount
-    if instruction.data.len() >= 12 {
-        // Extract the transfer amount from bytes 4-12 (adjusted byte range)
-        let transfer_amount = u64::from_le_bytes(
-            instruction.data[4..12].try_into().expect("Failed to parse transfer amount"),
-        );
-
-        if transfer_amount == expected_amount {
-            eprintln!(
-                "✅ Debug: Valid transfer detected. Amount matches expected value: {} lamports.",
-                transfer_amount
-            );
-            return true;
-        } else {
-            eprintln!(
-                "Debug: Transfer amount mismatch. Expected: {} lamports, Found: {} lamports.",
-                expected_amount, transfer_amount
-            );
-        }
+    // Step 3: Validate Instruction and Amount, encoded together in the data
+    if instruction.data == expected_data {
+        eprintln!("✅ Debug: Valid purchase detected.");
+        true
     } else {
         eprintln!(
-            "Debug: Instruction data size mismatch. Expected at least 12 bytes, Found: {} bytes.",
-            instruction.data.len()
+            "Debug: Instruction data mismatch. Expected: {:?}, Found: {:?}",
+            expected_data, instruction.data
         );
+        false
     }
-
-    false // If none of the checks pass
-}
\ No newline at end of file
+}
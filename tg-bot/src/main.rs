@@ -6,9 +6,12 @@ use dotenv::dotenv;
 use tokio::sync::Mutex; // Use tokio's async Mutex
 use lazy_static::lazy_static;
 use std::env;
+use std::str::FromStr;
+use solana_sdk::pubkey::Pubkey;
 
 mod check_eligibility;
 mod check_transaction;
+mod raffle_state;
 
 // Shared state to track users who need to provide their wallet address or ticket count
 lazy_static! {
@@ -17,10 +20,6 @@ static ref PENDING_WALLET_ADDRESSES: Arc<Mutex<HashMap<ChatId, (String, bool)>>>
 static ref PENDING_TICKET_COUNT: Arc<Mutex<HashMap<ChatId, String>>> = Arc::new(Mutex::new(HashMap::new()));
 }
 
-// Constants
-const RAFFLE_WALLET: &str = "3Qik6y2XjCymmam65y1s8Tm4MATUpaH18TKDa6TSvexv"; // Replace with actual raffle wallet address
-const TICKET_PRICE: u64 = 10_000_000; // 0.01 SOL in lamports
-
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     dotenv().ok();
@@ -37,6 +36,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         BotCommand::new("/jackpot", "Show the current jackpot 🎰"),
         BotCommand::new("/winners", "Displays the last winners 🥇"),
         BotCommand::new("/buy", "Buy Tickets 🎟"),
+        BotCommand::new("/stake", "Show a wallet's PSYDUK stake 🔒"),
     ])
     .await?;
 
@@ -86,14 +86,21 @@ async fn handle_message(
                         message.chat.id,
                         InputFile::url(image_url.parse().unwrap())
                     )
-                    .caption("🎉 Welcome to the PSYDUK Raffle Bot! 🎉 \n \n ➡ Rules: \n \n 1. Each ticket costs 0.01 SOL 🎟 \n2. Buy as many tickets as you want with the filter /buy. \n3. A draw is made every 15mins ⏳ \n4. The winner takes home the accumulated jackpot! /jackpot \n \n ➡ Check our last winners with the filter: /winners 🏆 \n Good Luck! 🍀")
+                    .caption(format!(
+                        "🎉 Welcome to the PSYDUK Raffle Bot! 🎉 \n \n ➡ Rules: \n \n 1. Each ticket costs {} 🎟 \n2. Buy as many tickets as you want with the filter /buy. \n3. A draw is made every 15mins ⏳ \n4. The winner takes home the accumulated jackpot! /jackpot \n \n ➡ Check our last winners with the filter: /winners 🏆 \n Good Luck! 🍀",
+                        raffle_state::ticket_price_message()
+                    ))
                     .reply_markup(buttons)
                     .await?;
                 }
                 "/jackpot" => {
                     bot.send_message(
                         message.chat.id,
-                        "🎰 Current Jackpot: 100 SOL 🎰\n\nThe next draw is in 10 minutes! ⏳\n\nTo participate, buy your tickets using /buy. Each ticket costs 0.01 SOL. Best of luck!"
+                        format!(
+                            "{}\n\nTo participate, buy your tickets using /buy. Each ticket costs {}. Best of luck!",
+                            raffle_state::jackpot_message(),
+                            raffle_state::ticket_price_message()
+                        )
                     ).await?;
                 }
 "/buy" => {
@@ -112,11 +119,15 @@ async fn handle_message(
                         "🏆 Here are the recent winners:\n\n1. Wallet: ABCD...1234 - Won 50 SOL\n2. Wallet: EFGH...5678 - Won 30 SOL\n\nKeep participating to have a chance to win big! 🎉"
                     ).await?;
                 }
+                command if command.starts_with("/stake") => {
+                    let wallet = command.trim_start_matches("/stake");
+                    bot.send_message(message.chat.id, raffle_state::stake_message(wallet)).await?;
+                }
                 _ => {
                     if is_private_chat {
                         bot.send_message(
                             message.chat.id,
-                            "To buy tickets, please follow these steps: 🎟️\n\n➡️ Send SOL to the following Solana address:\n\nGaCKAKASHIPsyDukCcaf8765300\n\n(For each 0.01 SOL you will get one ticket)\n\n➡️ Our automated system will detect your payment and send you a confirmation 💥"
+                            "To buy tickets, please follow these steps: 🎟️\n\n➡️ Send /buy and share your Solana wallet address\n\n➡️ Tell me how many tickets you want, then sign the purchase transaction I send you with your wallet\n\n➡️ Our automated system will detect your purchase and send you a confirmation 💥"
                         ).await?;
                     }
                 }
@@ -150,14 +161,29 @@ async fn handle_message(
                     }
                 };
 
-                let total_cost = ticket_count * TICKET_PRICE;
+                // Price the purchase from on-chain state and build the program's own
+                // buy_ticket transaction for the user to sign
+                let purchase = Pubkey::from_str(user_wallet.trim()).ok().zip(raffle_state::ticket_price()).and_then(|(buyer, price)| {
+                    let total_cost = raffle_state::purchase_amount(ticket_count, &price)?;
+                    let transaction = raffle_state::purchase_transaction(&buyer, total_cost, &price)?;
+                    Some((price, total_cost, transaction))
+                });
+                let Some((price, total_cost, transaction)) = purchase else {
+                    bot.send_message(
+                        message.chat.id,
+                        "⚠️ Unable to prepare your purchase right now. Please check your wallet address and try again with /buy."
+                    ).await?;
+                    return Ok(());
+                };
 
                 bot.send_message(
                     message.chat.id,
                     format!(
-                        "💳 Please send {} SOL to the following wallet address:\n\n{}\n\nWaiting for payment confirmation...",
+                        "💳 {} tickets at {} each cost {} SOL.\n\nSign and send this transaction with your wallet within a minute, before it expires:\n\n{}\n\nWaiting for purchase confirmation...",
+                        ticket_count,
+                        price.describe(),
                         total_cost as f64 / 1_000_000_000.0,
-                        RAFFLE_WALLET
+                        transaction
                     ),
                 )
                 .await?;
@@ -170,7 +196,6 @@ async fn handle_message(
                     async move {
                         if let Err(e) = check_transaction::monitor_transaction(
                             user_wallet_clone,
-                            RAFFLE_WALLET,
                             total_cost,
                             bot_clone,
                             chat_id_clone,
@@ -206,14 +231,17 @@ async fn handle_callback_query(
             "jackpot" => {
                 bot.send_message(
                     query.from.id,
-                    "🎰 Current Jackpot: 100 SOL 🎰\n\nThe next draw is in 10 minutes! ⏳"
+                    raffle_state::jackpot_message()
                 )
                 .await?;
             }
             "buy" => {
                 bot.send_message(
                     query.from.id,
-                    "To buy tickets, please use the /buy command. Each ticket costs 0.01 SOL."
+                    format!(
+                        "To buy tickets, please use the /buy command. Each ticket costs {}.",
+                        raffle_state::ticket_price_message()
+                    )
                 )
                 .await?;
             }
@@ -0,0 +1,188 @@
+use psyduk_raffle_client::{instructions, pda, state, PriceFeed, Raffle, RoundStatus, NO_ROUND, TICKET_PRICE};
+use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::Transaction;
+use solana_client::rpc_client::RpcClient;
+use log::error;
+use std::env;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// RPC endpoint the bot reads the raffle from and watches purchases on
+pub fn rpc_url() -> String {
+    env::var("SOLANA_RPC_URL").unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string())
+}
+
+fn rpc_client() -> RpcClient {
+    RpcClient::new(rpc_url())
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// What one ticket costs right now
+pub struct TicketPrice {
+    pub lamports: u64,              // Lamports charged per ticket
+    pub usd_cents: Option<u64>,     // USD price the lamports were converted from, if tickets are priced in USD
+    pub price_feed: Option<Pubkey>, // Feed the program converts the USD price with
+}
+
+impl TicketPrice {
+    /// Human-readable price, e.g. "0.01 SOL" or "$1.50 (≈ 0.01 SOL)"
+    pub fn describe(&self) -> String {
+        let sol = self.lamports as f64 / 1_000_000_000.0;
+        match self.usd_cents {
+            Some(cents) => format!("${}.{:02} (≈ {} SOL)", cents / 100, cents % 100, sol),
+            None => format!("{} SOL", sol),
+        }
+    }
+}
+
+/// Fetches the ticket price the program charges: the fixed `TICKET_PRICE`, or the
+/// configured USD price converted at the feed's latest price
+pub fn ticket_price() -> Option<TicketPrice> {
+    let client = rpc_client();
+
+    // Without a pricing config, or with a zero USD price, tickets cost `TICKET_PRICE`
+    let config = client
+        .get_account_data(&pda::price_config_address())
+        .ok()
+        .and_then(|data| state::decode_price_config(&data).ok())
+        .filter(|config| config.ticket_price_cents > 0);
+    let Some(config) = config else {
+        return Some(TicketPrice { lamports: TICKET_PRICE, usd_cents: None, price_feed: None });
+    };
+
+    let feed = match client.get_account_data(&config.price_feed) {
+        Ok(data) => PriceFeed::read(&data).ok(),
+        Err(err) => {
+            error!("Error fetching price feed: {}", err);
+            None
+        }
+    }?;
+    match config.ticket_price(&feed, unix_now()) {
+        Ok(lamports) => Some(TicketPrice {
+            lamports,
+            usd_cents: Some(config.ticket_price_cents),
+            price_feed: Some(config.price_feed),
+        }),
+        Err(err) => {
+            error!("Error converting the ticket price: {}", err);
+            None
+        }
+    }
+}
+
+/// Describes the current ticket price for the bot's messages
+pub fn ticket_price_message() -> String {
+    ticket_price().map_or_else(|| "the price shown by /buy".to_string(), |price| price.describe())
+}
+
+/// Lamports `buyer` passes to `buy_ticket` for `tickets` tickets at `price`
+pub fn purchase_amount(tickets: u64, price: &TicketPrice) -> Option<u64> {
+    tickets.checked_mul(price.lamports)
+}
+
+/// Builds the unsigned `buy_ticket` transaction paying `amount` lamports from `buyer`,
+/// base64-encoded for the buyer to sign and send from their own wallet
+pub fn purchase_transaction(buyer: &Pubkey, amount: u64, price: &TicketPrice) -> Option<String> {
+    let instruction = match price.price_feed {
+        Some(feed) => instructions::buy_ticket_in_usd(buyer, amount, None, None, &feed),
+        None => instructions::buy_ticket(buyer, amount),
+    };
+    let blockhash = match rpc_client().get_latest_blockhash() {
+        Ok(blockhash) => blockhash,
+        Err(err) => {
+            error!("Error fetching a recent blockhash: {}", err);
+            return None;
+        }
+    };
+    let message = Message::new_with_blockhash(&[instruction], Some(buyer), &blockhash);
+    bincode::serialize(&Transaction::new_unsigned(message)).ok().map(base64::encode)
+}
+
+/// Fetches and decodes the on-chain raffle account
+pub fn fetch_raffle() -> Option<Raffle> {
+    let client = rpc_client();
+
+    match client.get_account_data(&pda::raffle_address()) {
+        Ok(data) => match state::decode_raffle(&data) {
+            Ok(raffle) => Some(raffle),
+            Err(err) => {
+                error!("Error decoding raffle account: {}", err);
+                None
+            }
+        },
+        Err(err) => {
+            error!("Error fetching raffle account: {}", err);
+            None
+        }
+    }
+}
+
+/// Builds the jackpot announcement from the live raffle state
+pub fn jackpot_message() -> String {
+    let Some(raffle) = fetch_raffle() else {
+        return "⚠️ Unable to fetch the current jackpot. Please try again later.".to_string();
+    };
+
+    if raffle.status == RoundStatus::Cancelled {
+        return "⏸️ Ticket sales are paused for now. Stay tuned for the next round! ⏸️".to_string();
+    }
+
+    if raffle.end_time == NO_ROUND {
+        return "🎰 No draw is scheduled right now. Stay tuned for the next round! 🎰".to_string();
+    }
+
+    let minutes_left = (raffle.end_time - unix_now()).max(0) / 60;
+    // What the winner takes home, after any fee charged on winnings and with any
+    // top-up to the guaranteed minimum
+    let prize = raffle.split_payout().map_or(raffle.jackpot, |(prize, _)| prize);
+    let prize = prize + raffle.min_prize_top_up(prize);
+
+    format!(
+        "🎰 Current Jackpot: {} SOL 🎰\n\nThe next draw is in {} minutes! ⏳",
+        prize as f64 / 1_000_000_000.0,
+        minutes_left
+    )
+}
+
+/// Describes the stake position of `wallet` and the tickets it earns each round
+pub fn stake_message(wallet: &str) -> String {
+    let Ok(owner) = Pubkey::from_str(wallet.trim()) else {
+        return "⚠️ That doesn't look like a Solana wallet address. Usage: /stake <wallet>".to_string();
+    };
+
+    let client = rpc_client();
+    let position = client
+        .get_account_data(&pda::stake_position_address(&owner))
+        .ok()
+        .and_then(|data| state::decode_stake_position(&data).ok());
+    let Some(position) = position else {
+        return "🔒 This wallet has no PSYDUK staked. Stake to earn free tickets every round!".to_string();
+    };
+    let pool = match client.get_account_data(&pda::stake_pool_address()) {
+        Ok(data) => state::decode_stake_pool(&data).ok(),
+        Err(err) => {
+            error!("Error fetching stake pool: {}", err);
+            None
+        }
+    };
+    let Some(pool) = pool else {
+        return "⚠️ Unable to fetch the stake pool. Please try again later.".to_string();
+    };
+
+    let status = if position.is_active() {
+        format!("🎟️ Free tickets per round: {}", pool.tickets_for(&position, unix_now()))
+    } else {
+        format!("⏳ Unstaking, withdrawable from timestamp {}", position.cooldown_ends)
+    };
+    format!(
+        "🔒 Staked: {} PSYDUK (base units)\n📅 Locked until timestamp {}\n{}",
+        position.amount, position.locked_until, status
+    )
+}